@@ -0,0 +1,359 @@
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use avian3d::math::Scalar;
+use buoy_core::{
+    core::SimState,
+    ensemble::{Dispersion, Ensemble, Scatter},
+    estimation::{Estimation, Parameter},
+    headless::{HeadlessSim, SimOutcome},
+    replay::{Ghost, ReplayConfig, Track},
+    scenario::Scenario,
+    telemetry::{file_stem, TelemetryLog},
+};
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a single simulation to completion.
+    Run {
+        /// Scenario file (RON) to run. Runs the default scenario if omitted.
+        #[arg(long)]
+        scenario: Option<PathBuf>,
+        /// Maximum simulated time (s) before the run is cut short.
+        #[arg(long, default_value_t = 4.0 * 60.0 * 60.0)]
+        duration: f64,
+    },
+    /// Run many perturbed copies of a scenario to predict where it lands.
+    Ensemble {
+        /// Scenario file (RON) to run. Runs the default scenario if omitted.
+        #[arg(long)]
+        scenario: Option<PathBuf>,
+        /// Number of runs.
+        #[arg(long, default_value_t = 100)]
+        runs: usize,
+        /// Seed of the random number generator that perturbs each run.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Number of runs to do at the same time. Defaults to one per CPU.
+        #[arg(long)]
+        threads: Option<usize>,
+        /// Maximum simulated time (s) of each run.
+        #[arg(long, default_value_t = 4.0 * 60.0 * 60.0)]
+        duration: f64,
+        /// Relative standard deviation of the ascent rate.
+        #[arg(long, default_value_t = Dispersion::default().ascent_rate)]
+        ascent_rate_sigma: Scalar,
+        /// Relative standard deviation of the burst diameter.
+        #[arg(long, default_value_t = Dispersion::default().burst_diameter)]
+        burst_diameter_sigma: Scalar,
+        /// Standard deviation (m/s) of the wind error.
+        #[arg(long, default_value_t = Dispersion::default().wind)]
+        wind_sigma: Scalar,
+        /// Relative standard deviation of the drag coefficients.
+        #[arg(long, default_value_t = Dispersion::default().drag_coefficient)]
+        drag_coefficient_sigma: Scalar,
+        /// CSV file for the result of each run. Written to the scenario's
+        /// output directory if omitted.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Run a scenario next to a recorded flight and compare the two. The
+    /// simulation starts when and where the recorded flight did.
+    Replay {
+        /// Scenario file (RON) to run. Runs the default scenario if omitted.
+        #[arg(long)]
+        scenario: Option<PathBuf>,
+        /// Recorded flight: an APRS packet log, IGC file or CSV file.
+        #[arg(long)]
+        track: PathBuf,
+        /// Balloon to compare to the recorded flight. Defaults to the first
+        /// balloon in the scenario.
+        #[arg(long)]
+        balloon: Option<String>,
+        /// Maximum simulated time (s) before the run is cut short.
+        #[arg(long, default_value_t = 4.0 * 60.0 * 60.0)]
+        duration: f64,
+    },
+    /// Fit balloon parameters to a recorded flight. The simulation starts when
+    /// and where the recorded flight did.
+    Estimate {
+        /// Scenario file (RON) to start from. Uses the default scenario if
+        /// omitted.
+        #[arg(long)]
+        scenario: Option<PathBuf>,
+        /// Recorded flight: an APRS packet log, IGC file or CSV file.
+        #[arg(long)]
+        track: PathBuf,
+        /// Balloon that flew the recorded flight. Defaults to the first
+        /// balloon in the scenario.
+        #[arg(long)]
+        balloon: Option<String>,
+        /// Parameters to fit: drag_coefficient, parachute_drag_coefficient,
+        /// free_lift, leak_rate or elasticity.
+        #[arg(long, value_delimiter = ',', default_value = "drag_coefficient,free_lift")]
+        parameters: Vec<Parameter>,
+        /// Maximum number of simulation runs.
+        #[arg(long, default_value_t = 200)]
+        max_runs: usize,
+        /// Number of runs to do at the same time. Defaults to one per CPU.
+        #[arg(long)]
+        threads: Option<usize>,
+        /// Scenario file (RON) to write with the fitted values.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn load_scenario(path: Option<PathBuf>) -> Result<Scenario, ExitCode> {
+    match path.map(Scenario::load).transpose() {
+        Ok(scenario) => Ok(scenario.unwrap_or_default()),
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Print how a run ended and write its telemetry if the simulation didn't.
+fn finish_run(sim: &HeadlessSim, outcome: &SimOutcome) {
+    println!(
+        "{:?} after {:.1} s ({} steps)",
+        outcome.state,
+        outcome.elapsed.as_secs_f64(),
+        outcome.steps
+    );
+    for fault in sim.faults() {
+        println!("  {}", fault);
+    }
+    // Telemetry is written when the simulation stops or faults. A run that
+    // was cut short never gets there, so write it here instead.
+    if outcome.state == SimState::Running {
+        let world = sim.world();
+        let scenario = world.resource::<Scenario>();
+        if let Err(e) = world.resource::<TelemetryLog>().write_all(
+            &scenario.outputs.directory,
+            &scenario.name,
+            &scenario.outputs.telemetry,
+        ) {
+            eprintln!("could not write telemetry: {}", e);
+        }
+    }
+}
+
+fn print_scatter(label: &str, scatter: Option<Scatter>) {
+    let Some(scatter) = scatter else {
+        println!("{}: none", label);
+        return;
+    };
+    println!(
+        "{}: {} points, mean ({:.0}, {:.0}) m, CEP {:.0} m",
+        label, scatter.count, scatter.mean.x, scatter.mean.y, scatter.cep
+    );
+    for probability in [0.5, 0.95] {
+        let ellipse = scatter.ellipse(probability);
+        println!(
+            "  {:.0}% ellipse: {:.0} m x {:.0} m at {:.0}°",
+            probability * 100.0,
+            ellipse.semi_major,
+            ellipse.semi_minor,
+            ellipse.orientation.to_degrees()
+        );
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Run { scenario, duration } => {
+            let scenario = match load_scenario(scenario) {
+                Ok(scenario) => scenario,
+                Err(code) => return code,
+            };
+            let mut sim = HeadlessSim::default()
+                .with_scenario(scenario)
+                .with_max_duration(Duration::from_secs_f64(duration));
+            let outcome = sim.run();
+            finish_run(&sim, &outcome);
+            ExitCode::from(outcome.exit_code())
+        }
+        Command::Ensemble {
+            scenario,
+            runs,
+            seed,
+            threads,
+            duration,
+            ascent_rate_sigma,
+            burst_diameter_sigma,
+            wind_sigma,
+            drag_coefficient_sigma,
+            output,
+        } => {
+            let scenario = match load_scenario(scenario) {
+                Ok(scenario) => scenario,
+                Err(code) => return code,
+            };
+            let output = output.unwrap_or_else(|| {
+                scenario
+                    .outputs
+                    .directory
+                    .join(format!("{}-ensemble.csv", file_stem(&scenario.name)))
+            });
+            let mut ensemble = Ensemble::new(scenario, runs)
+                .with_seed(seed)
+                .with_max_duration(Duration::from_secs_f64(duration))
+                .with_dispersion(Dispersion {
+                    ascent_rate: ascent_rate_sigma,
+                    burst_diameter: burst_diameter_sigma,
+                    wind: wind_sigma,
+                    drag_coefficient: drag_coefficient_sigma,
+                });
+            if let Some(threads) = threads {
+                ensemble = ensemble.with_threads(threads);
+            }
+            println!("running {} runs on {} threads", runs, ensemble.threads);
+            let result = ensemble.run();
+
+            print_scatter("landings", result.landing_scatter());
+            print_scatter("bursts", result.burst_scatter());
+            if let Some((mean, std_dev)) = result.burst_altitude() {
+                println!("burst altitude: {:.0} ± {:.0} m", mean, std_dev);
+            }
+            if let Some(directory) = output.parent() {
+                if let Err(e) = std::fs::create_dir_all(directory) {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            if let Err(e) = result.write_csv(&output) {
+                eprintln!("could not write {}: {}", output.display(), e);
+                return ExitCode::FAILURE;
+            }
+            println!("wrote {}", output.display());
+            ExitCode::SUCCESS
+        }
+        Command::Replay {
+            scenario,
+            track,
+            balloon,
+            duration,
+        } => {
+            let mut scenario = match load_scenario(scenario) {
+                Ok(scenario) => scenario,
+                Err(code) => return code,
+            };
+            let recorded = match Track::load(&track) {
+                Ok(recorded) => recorded,
+                Err(e) => {
+                    eprintln!("{}: {}", track.display(), e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let Some(balloon) =
+                balloon.or_else(|| scenario.balloons.first().map(|b| b.name.clone()))
+            else {
+                eprintln!("scenario has no balloons");
+                return ExitCode::FAILURE;
+            };
+            scenario.epoch = recorded.start().time;
+            scenario.launch_site = recorded.launch_site();
+            scenario.replays.push(ReplayConfig {
+                track,
+                format: None,
+                balloon,
+            });
+            if let Err(e) = scenario.validate() {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+            println!(
+                "recorded flight from {} to {}",
+                recorded.start().time,
+                recorded.end().time
+            );
+            let mut sim = HeadlessSim::default()
+                .with_scenario(scenario)
+                .with_max_duration(Duration::from_secs_f64(duration));
+            let outcome = sim.run();
+            finish_run(&sim, &outcome);
+            let world = sim.world_mut();
+            for ghost in world.query::<&Ghost>().iter(world) {
+                println!("{}: {}", ghost.balloon, ghost.metrics);
+            }
+            ExitCode::from(outcome.exit_code())
+        }
+        Command::Estimate {
+            scenario,
+            track,
+            balloon,
+            parameters,
+            max_runs,
+            threads,
+            output,
+        } => {
+            let mut scenario = match load_scenario(scenario) {
+                Ok(scenario) => scenario,
+                Err(code) => return code,
+            };
+            let recorded = match Track::load(&track) {
+                Ok(recorded) => recorded,
+                Err(e) => {
+                    eprintln!("{}: {}", track.display(), e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let Some(balloon) =
+                balloon.or_else(|| scenario.balloons.first().map(|b| b.name.clone()))
+            else {
+                eprintln!("scenario has no balloons");
+                return ExitCode::FAILURE;
+            };
+            scenario.epoch = recorded.start().time;
+            scenario.launch_site = recorded.launch_site();
+            let mut estimation = Estimation::new(scenario, recorded, balloon)
+                .with_parameters(parameters)
+                .with_max_runs(max_runs);
+            if let Some(threads) = threads {
+                estimation = estimation.with_threads(threads);
+            }
+            println!(
+                "fitting {} to {} on {} threads",
+                estimation
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.name())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                track.display(),
+                estimation.threads
+            );
+            let result = match estimation.run() {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            println!("{}", result);
+            if let Some(output) = output {
+                let written = result
+                    .scenario
+                    .to_ron()
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| std::fs::write(&output, text).map_err(|e| e.to_string()));
+                if let Err(e) = written {
+                    eprintln!("could not write {}: {}", output.display(), e);
+                    return ExitCode::FAILURE;
+                }
+                println!("wrote {}", output.display());
+            }
+            ExitCode::SUCCESS
+        }
+    }
+}
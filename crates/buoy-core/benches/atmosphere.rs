@@ -0,0 +1,65 @@
+//! Density queries with and without an atmosphere table, and baking the
+//! tables.
+//!
+//! ```bash
+//! cargo bench -p buoy-core --bench atmosphere
+//! ```
+
+use avian3d::math::{Scalar, Vector};
+use buoy_core::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Altitude (m) between table samples.
+const TABLE_STEP: Scalar = 10.0;
+
+/// Positions spread over the altitudes of a typical flight.
+fn positions() -> Vec<Vector> {
+    (0..1000)
+        .map(|i| Vector::new(0.0, i as Scalar * 37.0, 0.0))
+        .collect()
+}
+
+/// Models that can be baked into a table. The empirical model changes with
+/// place and time, so it never is.
+fn sources() -> [(&'static str, AtmosphereSource); 4] {
+    [
+        ("standard", AtmosphereSource::StandardAtmosphere1976),
+        ("offset", AtmosphereSource::StandardOffset(15.0)),
+        ("supplement", AtmosphereSource::Supplement(Supplement::SubarcticWinter)),
+        ("mil_std_210", AtmosphereSource::MilStd210(MilStd210::Hot)),
+    ]
+}
+
+fn density(c: &mut Criterion) {
+    let positions = positions();
+    let mut group = c.benchmark_group("density");
+    for (name, source) in sources() {
+        let atmosphere = Atmosphere::new(source).with_humidity(Humidity::Standard(0.5));
+        let table = atmosphere.clone().with_table(TABLE_STEP);
+        for (variant, atmosphere) in [("model", &atmosphere), ("table", &table)] {
+            group.bench_with_input(BenchmarkId::new(variant, name), &positions, |b, positions| {
+                b.iter(|| {
+                    for position in positions {
+                        black_box(atmosphere.density(black_box(*position)));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bake(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bake");
+    group.sample_size(10);
+    for (name, source) in sources() {
+        let atmosphere = Atmosphere::new(source).with_humidity(Humidity::Standard(0.5));
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(atmosphere.clone().with_table(TABLE_STEP)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, density, bake);
+criterion_main!(benches);
@@ -1,77 +1,486 @@
-//! Atmosphere model based on the US Standard Atmosphere, 1976.
+//! Atmosphere models: the US Standard Atmosphere, 1976 and non-standard days.
 //!
 //! Reference:
 //! - https://apps.dtic.mil/dtic/tr/fulltext/u2/a035728.pdf
 //! - https://www.translatorscafe.com/unit-converter/en-US/calculator/altitude
-//! - https://www.grc.nasa.gov/WWW/K-12/airplane/atmosmet.html
+//!
+//! The standard atmosphere is the default. An [`AtmosphereSource`] selects a
+//! warmer or colder one instead: the standard with a temperature offset
+//! (ISA+ΔT), a profile from the U.S. Standard Atmosphere Supplements, 1966, or
+//! a MIL-STD-210 hot or cold day. All of these, the standard included, are
+//! made of layers where temperature changes linearly with geopotential
+//! altitude, and the pressure comes from hydrostatic balance from the ground
+//! up, so the standard and ISA+0 are the same atmosphere. The supplement and MIL-STD-210
+//! profiles are coarse fits to the published tables: good for seeing how a
+//! summer or winter launch changes a flight, not for reproducing the tables
+//! to a tenth of a kelvin.
+//!
+//! The [`empirical`](crate::empirical_atmosphere) model also changes with the
+//! latitude and longitude of each position and with the date and time of the
+//! [`SimClock`], and reaches up into the thermosphere.
+//!
+//! Besides temperature, pressure and density, the atmosphere gives the
+//! transport properties of the air as an [`AtmosphericState`]. Viscosity,
+//! thermal conductivity and mean free path follow the formulas of the 1976
+//! standard. Density accounts for the water vapor of the [`Humidity`] of the
+//! air, which is dry unless a scenario says otherwise.
+//!
+//! Any model that only changes with altitude can be baked into a
+//! [table](crate::atmosphere_table) of altitudes to make queries faster.
 
-use avian3d::prelude::{Position, RigidBody};
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{Physics, Position, RigidBody},
+};
 use bevy::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uom::si::{
-    f32::*,
-    thermodynamic_temperature::{degree_celsius, kelvin},
-    pressure::kilopascal,
+    acceleration::meter_per_second_squared,
+    dynamic_viscosity::pascal_second,
+    heat_capacity::joule_per_kelvin,
+    kinematic_viscosity::square_meter_per_second,
+    length::meter,
+    mass_density::kilogram_per_cubic_meter,
+    molar_heat_capacity::joule_per_kelvin_mole,
+    molar_mass::kilogram_per_mole,
+    pressure::{kilopascal, pascal},
+    ratio::ratio,
+    thermal_conductivity::watt_per_meter_kelvin,
+    thermodynamic_temperature::kelvin,
+    velocity::meter_per_second,
+    volumetric_number_density::per_cubic_meter,
 };
 
 use crate::{
+    atmosphere_table::{AtmosphereTable, Sample},
+    constants::{
+        BOLTZMANN_CONSTANT, GAS_CONSTANT, PI, STANDARD_GRAVITY, STANDARD_PRESSURE,
+        STANDARD_TEMPERATURE,
+    },
     core::SimState,
+    empirical_atmosphere::{self, EmpiricalAtmosphere, SpaceWeather},
+    fault::{FaultReason, Faults, SimFault},
+    humidity::{dewpoint, saturation_vapor_pressure, virtual_temperature, Humidity},
     ideal_gas::{ideal_gas_density, GasSpecies},
-    constants::{STANDARD_TEMPERATURE, STANDARD_PRESSURE},
+    quantity::{
+        DynamicViscosity, KinematicViscosity, Length, MassDensity, MolarMass, Pressure,
+        ThermalConductivity, ThermodynamicTemperature, Velocity, VolumetricNumberDensity,
+    },
+    scenario::Scenario,
+    time::SimClock,
 };
 
 pub(crate) fn plugin(app: &mut App) {
-    app.insert_resource(Atmosphere);
+    app.init_resource::<Atmosphere>();
     app.add_systems(
-        Update,
-        pause_on_out_of_bounds.run_if(in_state(SimState::Running)),
+        PreUpdate,
+        configure_atmosphere.run_if(resource_changed::<Scenario>),
     );
+    app.add_systems(FixedPreUpdate, update_atmosphere_time);
+    app.add_systems(
+        FixedUpdate,
+        detect_out_of_bounds.run_if(in_state(SimState::Running)),
+    );
+}
+
+fn configure_atmosphere(scenario: Res<Scenario>, mut atmosphere: ResMut<Atmosphere>) {
+    *atmosphere = scenario.atmosphere();
 }
 
-fn pause_on_out_of_bounds(
-    positions: Query<&Position, With<RigidBody>>,
-    mut state: ResMut<NextState<SimState>>,
+/// Keep the time of the atmosphere in step with the calendar clock.
+fn update_atmosphere_time(clock: Res<SimClock>, mut atmosphere: ResMut<Atmosphere>) {
+    let now = clock.now();
+    if atmosphere.time != now {
+        atmosphere.time = now;
+    }
+}
+
+fn detect_out_of_bounds(
+    physics_time: Res<Time<Physics>>,
+    atmosphere: Res<Atmosphere>,
+    positions: Query<(Entity, &Position), With<RigidBody>>,
+    faults: Res<Faults>,
+    mut events: EventWriter<SimFault>,
 ) {
-    for position in positions.iter() {
-        if position.y < Atmosphere::MIN_ALTITUDE || position.y > Atmosphere::MAX_ALTITUDE {
-            error!("Atmosphere out of bounds: {}", position.y);
-            state.set(SimState::Stopped);
+    for (entity, position) in positions.iter() {
+        // Clamping and extrapolating still give an answer outside the model,
+        // so only bodies the atmosphere would fail on are at fault.
+        if atmosphere.evaluated_position(position.0).is_err() {
+            let reason = FaultReason::AtmosphereOutOfBounds {
+                altitude: position.y,
+            };
+            if !faults.has(entity, &reason) {
+                events.send(SimFault {
+                    reason,
+                    entity,
+                    time: physics_time.elapsed(),
+                });
+            }
         }
     }
 }
 
-/// US Standard Atmosphere, 1976
-#[derive(Resource)]
-pub struct Atmosphere;
+/// What the atmosphere gives for positions outside the altitudes covered by
+/// the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutOfRange {
+    /// Use the nearest altitude covered by the model.
+    #[default]
+    Clamp,
+    /// Extend the nearest layer of the model past the edge of the range.
+    Extrapolate,
+    /// Fail with an [`AtmosphereError`]. Bodies outside the range are
+    /// reported as a [`FaultReason::AtmosphereOutOfBounds`] fault.
+    Error,
+}
+
+/// Which model describes the atmosphere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum AtmosphereSource {
+    /// US Standard Atmosphere, 1976.
+    #[default]
+    StandardAtmosphere1976,
+    /// The 1976 standard with every temperature offset by this much (K), also
+    /// known as ISA+ΔT. The pressure at sea level stays standard.
+    StandardOffset(Scalar),
+    /// A profile from the U.S. Standard Atmosphere Supplements, 1966.
+    Supplement(Supplement),
+    /// A hot or cold day from MIL-STD-210.
+    MilStd210(MilStd210),
+    /// An empirical model that changes with place, date and time, and with
+    /// solar activity in the thermosphere. See [`crate::empirical_atmosphere`].
+    Empirical(SpaceWeather),
+}
+
+impl AtmosphereSource {
+    /// Whether the model changes with place and time as well as altitude, so
+    /// it can't be baked into a table.
+    pub fn varies_with_place_and_time(&self) -> bool {
+        matches!(self, AtmosphereSource::Empirical(_))
+    }
+}
+
+/// Latitude and season of a profile from the U.S. Standard Atmosphere
+/// Supplements, 1966.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Supplement {
+    /// 15°N, all year.
+    Tropical,
+    /// 30°N, July.
+    SubtropicalSummer,
+    /// 30°N, January.
+    SubtropicalWinter,
+    /// 45°N, July.
+    MidlatitudeSummer,
+    /// 45°N, January.
+    MidlatitudeWinter,
+    /// 60°N, July.
+    SubarcticSummer,
+    /// 60°N, January.
+    SubarcticWinter,
+}
+
+/// Extreme day profiles from MIL-STD-210. The standard only covers the lowest
+/// 30 km, so above that they join the 1976 standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MilStd210 {
+    Hot,
+    Cold,
+}
+
+/// The atmosphere of the simulation. The US Standard Atmosphere, 1976 unless
+/// built from another [`AtmosphereSource`].
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct Atmosphere {
+    /// What queries outside the range of the model give.
+    pub out_of_range: OutOfRange,
+    /// Water vapor in the air. See [`Atmosphere::with_humidity`].
+    pub humidity: Humidity,
+    /// Latitude and longitude (degrees) of the origin of world coordinates.
+    origin: (f64, f64),
+    /// Date and time (UTC) of the air, kept in step with the [`SimClock`].
+    pub time: DateTime<Utc>,
+    source: AtmosphereSource,
+    model: Model,
+    /// The model baked into a table. See [`Atmosphere::with_table`].
+    table: Option<AtmosphereTable>,
+}
+
+/// How an [`AtmosphereSource`] is evaluated.
+#[derive(Debug, Clone, PartialEq)]
+enum Model {
+    Layered(LayeredProfile),
+    Empirical(Box<EmpiricalAtmosphere>),
+}
+
+impl Default for Model {
+    /// The layers of the 1976 standard.
+    fn default() -> Self {
+        Model::Layered(LayeredProfile::new(101325.0, USSA_1976, 0.0))
+    }
+}
 
 impl Atmosphere {
-    pub const MAX_ALTITUDE: f32 = 84999.0; // small margin to avoid panics
-    pub const MIN_ALTITUDE: f32 = -56.0; // small margin to avoid panics
+    pub const MAX_ALTITUDE: Scalar = 84999.0; // small margin to avoid panics
+    pub const MIN_ALTITUDE: Scalar = -56.0; // small margin to avoid panics
+
+    pub fn new(source: AtmosphereSource) -> Self {
+        let model = match source {
+            AtmosphereSource::StandardAtmosphere1976 => Model::default(),
+            AtmosphereSource::StandardOffset(offset) => {
+                Model::Layered(LayeredProfile::new(101325.0, USSA_1976, offset))
+            }
+            AtmosphereSource::Supplement(supplement) => {
+                let (surface_pressure, breakpoints) = supplement.breakpoints();
+                Model::Layered(LayeredProfile::new(surface_pressure, breakpoints, 0.0))
+            }
+            AtmosphereSource::MilStd210(day) => {
+                Model::Layered(LayeredProfile::new(101325.0, day.breakpoints(), 0.0))
+            }
+            AtmosphereSource::Empirical(weather) => {
+                Model::Empirical(Box::new(EmpiricalAtmosphere::new(weather)))
+            }
+        };
+        Atmosphere {
+            source,
+            model,
+            ..default()
+        }
+    }
+
+    pub fn with_out_of_range(self, out_of_range: OutOfRange) -> Self {
+        Self {
+            out_of_range,
+            ..self
+        }
+    }
+
+    /// Add water vapor to the air, sorting the levels of a sounding.
+    pub fn with_humidity(self, humidity: Humidity) -> Self {
+        Self {
+            humidity: humidity.sorted(),
+            ..self
+        }
+    }
+
+    /// Place the origin of world coordinates at some latitude and longitude
+    /// (degrees), like the launch site.
+    pub fn with_origin(self, latitude: f64, longitude: f64) -> Self {
+        Self {
+            origin: (latitude, longitude),
+            ..self
+        }
+    }
+
+    /// Set the date and time (UTC) of the air.
+    pub fn with_time(self, time: DateTime<Utc>) -> Self {
+        Self { time, ..self }
+    }
+
+    /// Bake the model into a table with a sample every `step` meters of
+    /// altitude. Call this last: the table is sampled with the humidity the
+    /// atmosphere has now. A model that
+    /// [varies with place and time](AtmosphereSource::varies_with_place_and_time)
+    /// is left as it is, since a table would hold it at one place and time.
+    pub fn with_table(self, step: Scalar) -> Self {
+        let model = Self { table: None, ..self };
+        if model.source.varies_with_place_and_time() {
+            return model;
+        }
+        let table = AtmosphereTable::new(
+            Atmosphere::MIN_ALTITUDE,
+            model.max_altitude(),
+            step,
+            |altitude| model.model_sample(Vector::new(0.0, altitude, 0.0)),
+        );
+        Self {
+            table: Some(table),
+            ..model
+        }
+    }
+
+    /// The model this atmosphere was built from.
+    pub fn source(&self) -> AtmosphereSource {
+        self.source
+    }
+
+    /// Altitude (m) between the samples of the table the model is baked
+    /// into, if it is.
+    pub fn table_step(&self) -> Option<Scalar> {
+        self.table.as_ref().map(AtmosphereTable::step)
+    }
+
+    /// Highest altitude (m) covered by the model. [`Atmosphere::MAX_ALTITUDE`]
+    /// except for models that reach into the thermosphere.
+    pub fn max_altitude(&self) -> Scalar {
+        match self.model {
+            Model::Empirical(_) => empirical_atmosphere::MAX_ALTITUDE,
+            _ => Atmosphere::MAX_ALTITUDE,
+        }
+    }
+
+    /// Position where the model is evaluated for a position.
+    fn evaluated_position(&self, position: Vector) -> Result<Vector, AtmosphereError> {
+        let altitude = position.y;
+        let max_altitude = self.max_altitude();
+        if (Atmosphere::MIN_ALTITUDE..=max_altitude).contains(&altitude) {
+            return Ok(position);
+        }
+        let altitude = match self.out_of_range {
+            OutOfRange::Clamp if !altitude.is_nan() => {
+                altitude.clamp(Atmosphere::MIN_ALTITUDE, max_altitude)
+            }
+            OutOfRange::Extrapolate if !altitude.is_nan() => altitude,
+            _ => return Err(AtmosphereError::OutOfBounds(altitude)),
+        };
+        Ok(Vector::new(position.x, altitude, position.z))
+    }
 
     /// Temperature (K) of the atmosphere at a position.
-    pub fn temperature(&self, position: Vec3) -> ThermodynamicTemperature {
-        // TODO: Look up temperature based on latitude, longitude, not just altitude
-        coesa_temperature(position.y).unwrap_or_else(|e| {
-            error!("Atmosphere temperature out of bounds: {}", e);
-            STANDARD_TEMPERATURE.clone()
-        }) // we should handle this better
+    pub fn try_temperature(
+        &self,
+        position: Vector,
+    ) -> Result<ThermodynamicTemperature, AtmosphereError> {
+        let position = self.evaluated_position(position)?;
+        Ok(match self.tabulated(position.y) {
+            Some(sample) => ThermodynamicTemperature::new::<kelvin>(sample.temperature),
+            None => self.temperature_at(position),
+        })
     }
 
     /// Pressure (Pa) of the atmosphere at a position.
-    pub fn pressure(&self, position: Vec3) -> Pressure {
-        // TODO: Look up pressure based on latitude, longitude, not just altitude
-        coesa_pressure(position.y).unwrap_or_else(|e| {
-            error!("Atmosphere pressure out of bounds: {}", e);
-            STANDARD_PRESSURE.clone()
-        }) // we should handle this better
+    pub fn try_pressure(&self, position: Vector) -> Result<Pressure, AtmosphereError> {
+        let position = self.evaluated_position(position)?;
+        Ok(match self.tabulated(position.y) {
+            Some(sample) => Pressure::new::<pascal>(sample.pressure),
+            None => self.pressure_at(position),
+        })
     }
 
-    /// Density (kg/m³) of the atmosphere at a position.
-    pub fn density(&self, position: Vec3) -> MassDensity {
-        ideal_gas_density(
-            self.temperature(position),
-            self.pressure(position),
-            &GasSpecies::air(),
-        )
+    /// Partial pressure (Pa) of water vapor at a position.
+    pub fn try_vapor_pressure(&self, position: Vector) -> Result<Pressure, AtmosphereError> {
+        let position = self.evaluated_position(position)?;
+        Ok(match self.tabulated(position.y) {
+            Some(sample) => Pressure::new::<pascal>(sample.vapor_pressure),
+            None => self.vapor_pressure_at(position),
+        })
+    }
+
+    /// Density (kg/m³) of the moist air at a position.
+    pub fn try_density(&self, position: Vector) -> Result<MassDensity, AtmosphereError> {
+        let position = self.evaluated_position(position)?;
+        let sample = self
+            .tabulated(position.y)
+            .unwrap_or_else(|| self.model_sample(position));
+        let temperature = ThermodynamicTemperature::new::<kelvin>(sample.temperature);
+        let pressure = Pressure::new::<pascal>(sample.pressure);
+        let vapor_pressure = Pressure::new::<pascal>(sample.vapor_pressure);
+        Ok(MolarMass::new::<kilogram_per_mole>(sample.molar_mass) * pressure
+            / (*GAS_CONSTANT * virtual_temperature(temperature, pressure, vapor_pressure)))
+    }
+
+    /// The air at an altitude (m) from the table, if there is one and it
+    /// covers the altitude.
+    fn tabulated(&self, altitude: Scalar) -> Option<Sample> {
+        self.table.as_ref()?.get(altitude)
+    }
+
+    /// The air at a position from the model itself.
+    fn model_sample(&self, position: Vector) -> Sample {
+        Sample {
+            temperature: self.temperature_at(position).get::<kelvin>(),
+            pressure: self.pressure_at(position).get::<pascal>(),
+            vapor_pressure: self.vapor_pressure_at(position).get::<pascal>(),
+            molar_mass: self.molar_mass_at(position).get::<kilogram_per_mole>(),
+        }
+    }
+
+    fn temperature_at(&self, position: Vector) -> ThermodynamicTemperature {
+        match &self.model {
+            Model::Layered(profile) => {
+                ThermodynamicTemperature::new::<kelvin>(profile.temperature(position.y))
+            }
+            Model::Empirical(model) => ThermodynamicTemperature::new::<kelvin>(
+                model.temperature(position, self.origin, self.time),
+            ),
+        }
+    }
+
+    fn pressure_at(&self, position: Vector) -> Pressure {
+        match &self.model {
+            Model::Layered(profile) => Pressure::new::<pascal>(profile.pressure(position.y)),
+            Model::Empirical(model) => {
+                Pressure::new::<pascal>(model.pressure(position, self.origin, self.time))
+            }
+        }
+    }
+
+    fn vapor_pressure_at(&self, position: Vector) -> Pressure {
+        self.humidity.vapor_pressure(position.y, |altitude| {
+            self.temperature_at(Vector::new(position.x, altitude, position.z))
+        })
+    }
+
+    /// Mean molar mass of dry air, which only changes above the turbopause.
+    fn molar_mass_at(&self, position: Vector) -> MolarMass {
+        match &self.model {
+            Model::Empirical(_) => MolarMass::new::<kilogram_per_mole>(
+                empirical_atmosphere::molar_mass(position.y),
+            ),
+            _ => GasSpecies::air().molar_mass,
+        }
+    }
+
+    /// Temperature (K) of the atmosphere at a position. NaN where
+    /// [`Atmosphere::try_temperature`] fails, so the error shows up as a
+    /// fault instead of a plausible value.
+    pub fn temperature(&self, position: Vector) -> ThermodynamicTemperature {
+        self.try_temperature(position)
+            .unwrap_or_else(|_| ThermodynamicTemperature::new::<kelvin>(Scalar::NAN))
+    }
+
+    /// Pressure (Pa) of the atmosphere at a position. NaN where
+    /// [`Atmosphere::try_pressure`] fails.
+    pub fn pressure(&self, position: Vector) -> Pressure {
+        self.try_pressure(position)
+            .unwrap_or_else(|_| Pressure::new::<kilopascal>(Scalar::NAN))
+    }
+
+    /// Partial pressure (Pa) of water vapor at a position. NaN where
+    /// [`Atmosphere::try_vapor_pressure`] fails.
+    pub fn vapor_pressure(&self, position: Vector) -> Pressure {
+        self.try_vapor_pressure(position)
+            .unwrap_or_else(|_| Pressure::new::<kilopascal>(Scalar::NAN))
+    }
+
+    /// Density (kg/m³) of the moist air at a position. NaN where
+    /// [`Atmosphere::try_density`] fails.
+    pub fn density(&self, position: Vector) -> MassDensity {
+        self.try_density(position)
+            .unwrap_or_else(|_| MassDensity::new::<kilogram_per_cubic_meter>(Scalar::NAN))
+    }
+
+    /// Everything about the air at a position.
+    pub fn try_state(&self, position: Vector) -> Result<AtmosphericState, AtmosphereError> {
+        let mut state = AtmosphericState::moist(
+            self.try_temperature(position)?,
+            self.try_pressure(position)?,
+            self.try_vapor_pressure(position)?,
+        );
+        // Lighter gases take over above the turbopause.
+        state.density = self.try_density(position)?;
+        state.kinematic_viscosity = state.dynamic_viscosity / state.density;
+        Ok(state)
+    }
+
+    /// Everything about the air at a position. Every value is NaN where
+    /// [`Atmosphere::try_state`] fails.
+    pub fn state(&self, position: Vector) -> AtmosphericState {
+        self.try_state(position).unwrap_or_else(|_| {
+            let nan = ThermodynamicTemperature::new::<kelvin>(Scalar::NAN);
+            AtmosphericState::new(nan, Pressure::new::<pascal>(Scalar::NAN))
+        })
     }
 
     pub fn standard_temperature() -> ThermodynamicTemperature {
@@ -91,58 +500,376 @@ impl Atmosphere {
     }
 }
 
-#[derive(Debug)]
-enum AtmosphereError {
-    #[allow(dead_code)]
-    OutOfBounds(f32),
+/// Sutherland's constant β (kg/(m·s·K^½)) for the viscosity of air.
+const SUTHERLAND_BETA: Scalar = 1.458e-6;
+/// Sutherland's constant S (K) for the viscosity of air.
+const SUTHERLAND_S: Scalar = 110.4;
+/// Ratio of specific heats of air.
+const AIR_HEAT_CAPACITY_RATIO: Scalar = 1.4;
+/// Effective collision diameter (m) of an air molecule.
+const AIR_COLLISION_DIAMETER: Scalar = 3.65e-10;
+
+/// The state of the air at one place: what it is, and how it moves heat and
+/// momentum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtmosphericState {
+    pub temperature: ThermodynamicTemperature,
+    pub pressure: Pressure,
+    /// Density of the moist air.
+    pub density: MassDensity,
+    /// Partial pressure of water vapor.
+    pub vapor_pressure: Pressure,
+    /// Vapor pressure as a fraction of the saturation vapor pressure.
+    pub relative_humidity: Scalar,
+    /// Temperature where the vapor would start to condense. `None` in dry air.
+    pub dewpoint: Option<ThermodynamicTemperature>,
+    /// Temperature where dry air at the same pressure has the same density.
+    pub virtual_temperature: ThermodynamicTemperature,
+    /// Dynamic viscosity, from Sutherland's law.
+    pub dynamic_viscosity: DynamicViscosity,
+    /// Dynamic viscosity divided by density.
+    pub kinematic_viscosity: KinematicViscosity,
+    pub thermal_conductivity: ThermalConductivity,
+    pub speed_of_sound: Velocity,
+    /// Number of air molecules per unit volume.
+    pub number_density: VolumetricNumberDensity,
+    /// Mean distance an air molecule travels between collisions.
+    pub mean_free_path: Length,
+}
+
+impl AtmosphericState {
+    /// State of dry air at some temperature and pressure.
+    pub fn new(temperature: ThermodynamicTemperature, pressure: Pressure) -> Self {
+        AtmosphericState::moist(temperature, pressure, Pressure::default())
+    }
+
+    /// State of air at some temperature and pressure that holds water vapor
+    /// with some partial pressure. Transport properties are those of dry air.
+    pub fn moist(
+        temperature: ThermodynamicTemperature,
+        pressure: Pressure,
+        vapor_pressure: Pressure,
+    ) -> Self {
+        let air = GasSpecies::air();
+        let virtual_temperature = virtual_temperature(temperature, pressure, vapor_pressure);
+        let density = ideal_gas_density(virtual_temperature, pressure, &air);
+        let t = temperature.get::<kelvin>();
+        let p = pressure.get::<pascal>();
+        let rho = density.get::<kilogram_per_cubic_meter>();
+        let mu = SUTHERLAND_BETA * t * t.sqrt() / (t + SUTHERLAND_S);
+        let k = 2.64638e-3 * t * t.sqrt() / (t + 245.4 * Scalar::powf(10.0, -12.0 / t));
+        let specific_gas_constant = GAS_CONSTANT.get::<joule_per_kelvin_mole>()
+            / air.molar_mass.get::<kilogram_per_mole>();
+        // Sound travels a little faster in humid air, about as fast as in dry
+        // air at the virtual temperature.
+        let tv = virtual_temperature.get::<kelvin>();
+        let a = (AIR_HEAT_CAPACITY_RATIO * specific_gas_constant * tv).sqrt();
+        let n = p / (BOLTZMANN_CONSTANT.get::<joule_per_kelvin>() * t);
+        let mean_free_path =
+            1.0 / (Scalar::sqrt(2.0) * PI * AIR_COLLISION_DIAMETER * AIR_COLLISION_DIAMETER * n);
+        AtmosphericState {
+            temperature,
+            pressure,
+            density,
+            vapor_pressure,
+            relative_humidity: (vapor_pressure / saturation_vapor_pressure(temperature))
+                .get::<ratio>(),
+            dewpoint: (vapor_pressure > Pressure::default()).then(|| dewpoint(vapor_pressure)),
+            virtual_temperature,
+            dynamic_viscosity: DynamicViscosity::new::<pascal_second>(mu),
+            kinematic_viscosity: KinematicViscosity::new::<square_meter_per_second>(mu / rho),
+            thermal_conductivity: ThermalConductivity::new::<watt_per_meter_kelvin>(k),
+            speed_of_sound: Velocity::new::<meter_per_second>(a),
+            number_density: VolumetricNumberDensity::new::<per_cubic_meter>(n),
+            mean_free_path: Length::new::<meter>(mean_free_path),
+        }
+    }
+
+    /// Reynolds number of a body with some characteristic length moving at
+    /// some speed through the air.
+    pub fn reynolds_number(&self, speed: Velocity, length: Length) -> Scalar {
+        (speed * length / self.kinematic_viscosity).get::<ratio>()
+    }
+
+    /// Mach number of a body moving at some speed through the air.
+    pub fn mach_number(&self, speed: Velocity) -> Scalar {
+        (speed / self.speed_of_sound).get::<ratio>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AtmosphereError {
+    /// The altitude (m) is outside the range covered by the model.
+    OutOfBounds(Scalar),
 }
 
 impl std::fmt::Display for AtmosphereError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-/// Temperature (K) of the atmosphere at a given altitude (m).
-/// Only valid for altitudes below 85,000 meters.
-/// Based on the US Standard Atmosphere, 1976. (aka COESA)
-fn coesa_temperature(altitude: f32) -> Result<ThermodynamicTemperature, AtmosphereError> {
-    if (-57.0..11000.0).contains(&altitude) {
-        Ok(ThermodynamicTemperature::new::<degree_celsius>(15.04 - 0.00649 * altitude))
-    } else if (11000.0..25000.0).contains(&altitude) {
-        Ok(ThermodynamicTemperature::new::<degree_celsius>(-56.46))
-    } else if (25000.0..85000.0).contains(&altitude) {
-        Ok(ThermodynamicTemperature::new::<degree_celsius>(-131.21 + 0.00299 * altitude))
-    } else {
-        Err(AtmosphereError::OutOfBounds(altitude))
-    }
-}
-
-/// Pressure (Pa) of the atmosphere at a given altitude (m).
-/// Only valid for altitudes below 85,000 meters.
-/// Based on the US Standard Atmosphere, 1976. (aka COESA)
-fn coesa_pressure(altitude: f32) -> Result<Pressure, AtmosphereError> {
-    if (-57.0..11000.0).contains(&altitude) {
-        Ok(Pressure::new::<kilopascal>(
-            101.29
-                * f32::powf(
-                    coesa_temperature(altitude).unwrap_or_default().get::<kelvin>() / 288.08,
-                    5.256,
-                ),
-        ))
-    } else if (11000.0..25000.0).contains(&altitude) {
-        Ok(Pressure::new::<kilopascal>(
-            22.65 * f32::exp(1.73 - 0.000157 * altitude),
-        ))
-    } else if (25000.0..85000.0).contains(&altitude) {
-        Ok(Pressure::new::<kilopascal>(
-            2.488
-                * f32::powf(
-                    coesa_temperature(altitude).unwrap_or_default().get::<kelvin>() / 216.6,
-                    -11.388,
-                ),
-        ))
-    } else {
-        Err(AtmosphereError::OutOfBounds(altitude))
+        match self {
+            AtmosphereError::OutOfBounds(altitude) => {
+                write!(f, "altitude {} m is outside the atmosphere model", altitude)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtmosphereError {}
+
+/// Radius (m) of the Earth used to convert geometric to geopotential altitude.
+const GEOPOTENTIAL_RADIUS: Scalar = 6356766.0;
+
+/// Geopotential altitude (m) of a geometric altitude (m): the height in a
+/// uniform field of standard gravity with the same potential energy.
+fn geopotential_altitude(altitude: Scalar) -> Scalar {
+    GEOPOTENTIAL_RADIUS * altitude / (GEOPOTENTIAL_RADIUS + altitude)
+}
+
+/// Temperature (K) at the base of each layer of the 1976 standard, by
+/// geopotential altitude (m).
+const USSA_1976: &[(Scalar, Scalar)] = &[
+    (0.0, 288.15),
+    (11000.0, 216.65),
+    (20000.0, 216.65),
+    (32000.0, 228.65),
+    (47000.0, 270.65),
+    (51000.0, 270.65),
+    (71000.0, 214.65),
+    (84852.0, 186.946),
+];
+
+impl Supplement {
+    /// Sea level pressure (Pa) and temperature (K) by geopotential altitude
+    /// (m).
+    pub(crate) fn breakpoints(self) -> (Scalar, &'static [(Scalar, Scalar)]) {
+        match self {
+            Supplement::Tropical => (
+                101300.0,
+                &[
+                    (0.0, 299.7),
+                    (17000.0, 194.8),
+                    (20000.0, 206.7),
+                    (25000.0, 221.4),
+                    (35000.0, 243.1),
+                    (47500.0, 269.6),
+                    (50000.0, 270.2),
+                    (70000.0, 218.9),
+                    (85000.0, 177.1),
+                ],
+            ),
+            Supplement::SubtropicalSummer => (
+                101500.0,
+                &[
+                    (0.0, 301.2),
+                    (15500.0, 200.0),
+                    (20000.0, 211.0),
+                    (32000.0, 230.0),
+                    (48000.0, 272.0),
+                    (52000.0, 272.0),
+                    (72000.0, 220.0),
+                    (85000.0, 187.0),
+                ],
+            ),
+            Supplement::SubtropicalWinter => (
+                102200.0,
+                &[
+                    (0.0, 287.2),
+                    (13500.0, 210.0),
+                    (20000.0, 212.0),
+                    (32000.0, 228.0),
+                    (48000.0, 262.0),
+                    (52000.0, 262.0),
+                    (72000.0, 220.0),
+                    (85000.0, 190.0),
+                ],
+            ),
+            Supplement::MidlatitudeSummer => (
+                101300.0,
+                &[
+                    (0.0, 294.2),
+                    (13000.0, 216.8),
+                    (17000.0, 216.7),
+                    (25000.0, 224.0),
+                    (47500.0, 275.7),
+                    (50000.0, 276.2),
+                    (70000.0, 218.1),
+                    (85000.0, 165.1),
+                ],
+            ),
+            Supplement::MidlatitudeWinter => (
+                101800.0,
+                &[
+                    (0.0, 272.2),
+                    (10000.0, 219.7),
+                    (25000.0, 215.7),
+                    (30000.0, 217.4),
+                    (50000.0, 265.7),
+                    (70000.0, 230.7),
+                    (85000.0, 210.0),
+                ],
+            ),
+            Supplement::SubarcticSummer => (
+                101000.0,
+                &[
+                    (0.0, 287.2),
+                    (10000.0, 235.7),
+                    (12000.0, 225.2),
+                    (23000.0, 225.2),
+                    (30000.0, 233.0),
+                    (47000.0, 275.0),
+                    (52000.0, 277.2),
+                    (72000.0, 225.0),
+                    (85000.0, 165.0),
+                ],
+            ),
+            Supplement::SubarcticWinter => (
+                101300.0,
+                &[
+                    (0.0, 257.2),
+                    (1000.0, 259.1),
+                    (9000.0, 217.2),
+                    (20000.0, 214.0),
+                    (30000.0, 217.0),
+                    (48000.0, 260.0),
+                    (54000.0, 260.0),
+                    (72000.0, 230.0),
+                    (85000.0, 210.0),
+                ],
+            ),
+        }
+    }
+}
+
+impl MilStd210 {
+    /// Temperature (K) by geopotential altitude (m).
+    fn breakpoints(self) -> &'static [(Scalar, Scalar)] {
+        match self {
+            MilStd210::Hot => &[
+                (0.0, 312.6),
+                (14000.0, 215.0),
+                (20000.0, 222.0),
+                (32000.0, 228.65),
+                (47000.0, 270.65),
+                (51000.0, 270.65),
+                (71000.0, 214.65),
+                (84852.0, 186.946),
+            ],
+            // A strong inversion over the cold ground.
+            MilStd210::Cold => &[
+                (0.0, 222.1),
+                (1000.0, 244.3),
+                (9000.0, 210.0),
+                (15000.0, 190.0),
+                (32000.0, 228.65),
+                (47000.0, 270.65),
+                (51000.0, 270.65),
+                (71000.0, 214.65),
+                (84852.0, 186.946),
+            ],
+        }
+    }
+}
+
+/// g₀M/R (K/m) for air: how quickly pressure falls with altitude relative to
+/// temperature.
+fn hydrostatic_constant() -> Scalar {
+    STANDARD_GRAVITY.get::<meter_per_second_squared>()
+        * GasSpecies::air().molar_mass.get::<kilogram_per_mole>()
+        / GAS_CONSTANT.get::<joule_per_kelvin_mole>()
+}
+
+/// One layer of a [`LayeredProfile`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layer {
+    /// Geopotential altitude (m) of the base of the layer.
+    base: Scalar,
+    /// Temperature (K) at the base of the layer.
+    temperature: Scalar,
+    /// Pressure (Pa) at the base of the layer.
+    pressure: Scalar,
+    /// Change of temperature (K/m) with geopotential altitude.
+    lapse_rate: Scalar,
+}
+
+impl Layer {
+    /// Temperature (K) at a geopotential altitude (m) in or past this layer.
+    fn temperature(&self, altitude: Scalar) -> Scalar {
+        self.temperature + self.lapse_rate * (altitude - self.base)
+    }
+
+    /// Pressure (Pa) at a geopotential altitude (m) in or past this layer,
+    /// given the [`hydrostatic_constant`].
+    fn pressure(&self, altitude: Scalar, hydrostatic: Scalar) -> Scalar {
+        if self.lapse_rate.abs() < 1e-9 {
+            self.pressure * Scalar::exp(-hydrostatic * (altitude - self.base) / self.temperature)
+        } else {
+            self.pressure
+                * Scalar::powf(
+                    self.temperature / self.temperature(altitude),
+                    hydrostatic / self.lapse_rate,
+                )
+        }
+    }
+}
+
+/// An atmosphere made of layers where temperature changes linearly with
+/// geopotential altitude. The lowest and highest layers go on past the ends
+/// of the profile.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayeredProfile {
+    layers: Vec<Layer>,
+    hydrostatic: Scalar,
+}
+
+impl LayeredProfile {
+    /// Profile from the sea level pressure (Pa) and the temperature (K) at
+    /// the breakpoints between layers, by geopotential altitude (m), with
+    /// every temperature offset by some amount (K).
+    pub(crate) fn new(
+        surface_pressure: Scalar,
+        breakpoints: &[(Scalar, Scalar)],
+        offset: Scalar,
+    ) -> Self {
+        let hydrostatic = hydrostatic_constant();
+        let mut layers: Vec<Layer> = Vec::with_capacity(breakpoints.len() - 1);
+        for pair in breakpoints.windows(2) {
+            let [(base, temperature), (top, top_temperature)] = [pair[0], pair[1]];
+            let pressure = match layers.last() {
+                Some(below) => below.pressure(base, hydrostatic),
+                None => surface_pressure,
+            };
+            layers.push(Layer {
+                base,
+                temperature: temperature + offset,
+                pressure,
+                lapse_rate: (top_temperature - temperature) / (top - base),
+            });
+        }
+        LayeredProfile {
+            layers,
+            hydrostatic,
+        }
+    }
+
+    /// The layer that holds a geometric altitude (m), and the geopotential
+    /// altitude (m).
+    fn layer(&self, altitude: Scalar) -> (&Layer, Scalar) {
+        let altitude = geopotential_altitude(altitude);
+        let index = self
+            .layers
+            .partition_point(|layer| layer.base <= altitude)
+            .saturating_sub(1);
+        (&self.layers[index], altitude)
+    }
+
+    /// Temperature (K) at a geometric altitude (m).
+    pub(crate) fn temperature(&self, altitude: Scalar) -> Scalar {
+        let (layer, altitude) = self.layer(altitude);
+        layer.temperature(altitude)
+    }
+
+    /// Pressure (Pa) at a geometric altitude (m).
+    pub(crate) fn pressure(&self, altitude: Scalar) -> Scalar {
+        let (layer, altitude) = self.layer(altitude);
+        layer.pressure(altitude, self.hydrostatic)
     }
 }
@@ -0,0 +1,107 @@
+//! Atmosphere models baked into a table of altitudes.
+//!
+//! Every force, valve and sensor asks the atmosphere for its density or
+//! pressure on every step, and Monte Carlo ensembles run thousands of flights.
+//! The formulas of the models are full of `powf` and `exp`, and the empirical
+//! model integrates pressure through the thermosphere on every query. A table
+//! samples the model once at evenly spaced altitudes, so a query is an index
+//! and a linear interpolation.
+//!
+//! Any [`AtmosphereSource`](crate::atmosphere::AtmosphereSource) that only
+//! changes with altitude can be baked with
+//! [`Atmosphere::with_table`](crate::atmosphere::Atmosphere::with_table), or
+//! from a scenario:
+//!
+//! ```ron
+//! atmosphere_table: Some(10.0),
+//! ```
+//!
+//! Pressure falls by a factor of e about every 7 km, so a step of 10 m keeps
+//! the error of linear interpolation below a millionth. The table covers the
+//! altitudes of the model. Queries outside of it, which only happen when the
+//! atmosphere extrapolates, go to the model itself.
+//!
+//! Baking takes one query of the model per sample.
+//!
+//! The empirical model also changes with place, season and time of day, which
+//! a table would freeze at one place and time. It is never baked: `with_table`
+//! leaves it as it is, and a scenario can't ask for a table of it.
+
+use avian3d::math::Scalar;
+
+/// Raw values of the air at one altitude, in SI units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Sample {
+    /// Temperature (K).
+    pub temperature: Scalar,
+    /// Pressure (Pa).
+    pub pressure: Scalar,
+    /// Partial pressure (Pa) of water vapor.
+    pub vapor_pressure: Scalar,
+    /// Mean molar mass (kg/mol) of dry air.
+    pub molar_mass: Scalar,
+}
+
+impl Sample {
+    fn lerp(self, other: Sample, t: Scalar) -> Sample {
+        let lerp = |a: Scalar, b: Scalar| a + (b - a) * t;
+        Sample {
+            temperature: lerp(self.temperature, other.temperature),
+            pressure: lerp(self.pressure, other.pressure),
+            vapor_pressure: lerp(self.vapor_pressure, other.vapor_pressure),
+            molar_mass: lerp(self.molar_mass, other.molar_mass),
+        }
+    }
+}
+
+/// Samples of the air at evenly spaced altitudes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AtmosphereTable {
+    /// Altitudes (m) of the first and last samples.
+    bottom: Scalar,
+    top: Scalar,
+    /// Altitude (m) between samples.
+    step: Scalar,
+    samples: Vec<Sample>,
+}
+
+impl AtmosphereTable {
+    /// Sample the air from the bottom to the top altitude (m), every step (m),
+    /// in order of altitude. The last sample is at the top even if it is
+    /// closer than a step.
+    pub(crate) fn new(
+        bottom: Scalar,
+        top: Scalar,
+        step: Scalar,
+        mut sample: impl FnMut(Scalar) -> Sample,
+    ) -> Self {
+        let intervals = ((top - bottom) / step).ceil().max(1.0) as usize;
+        let samples = (0..=intervals)
+            .map(|i| sample((bottom + i as Scalar * step).min(top)))
+            .collect();
+        AtmosphereTable {
+            bottom,
+            top,
+            step,
+            samples,
+        }
+    }
+
+    /// Altitude (m) between samples.
+    pub(crate) fn step(&self) -> Scalar {
+        self.step
+    }
+
+    /// The air at an altitude (m), or `None` outside the table.
+    pub(crate) fn get(&self, altitude: Scalar) -> Option<Sample> {
+        if !(self.bottom..=self.top).contains(&altitude) {
+            return None;
+        }
+        let index = (((altitude - self.bottom) / self.step) as usize).min(self.samples.len() - 2);
+        let below = self.bottom + index as Scalar * self.step;
+        // The last interval can be shorter than a step.
+        let above = (below + self.step).min(self.top);
+        let t = (altitude - below) / (above - below);
+        Some(self.samples[index].lerp(self.samples[index + 1], t))
+    }
+}
@@ -0,0 +1,266 @@
+//! Ballast that can be dropped to make a flight train lighter.
+//!
+//! Zero-pressure balloons control altitude by dropping ballast. Ballast is
+//! released at a limited flow rate, either when it is commanded with a
+//! [`BallastCommand`] or when a [`BallastRule`] is triggered. The released mass
+//! is removed from the rigid body that carries the ballast.
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{LinearVelocity, Position},
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    length::meter,
+    mass::kilogram,
+    mass_rate::kilogram_per_second,
+    velocity::meter_per_second,
+};
+
+use crate::{
+    core::SimState,
+    quantity::{Length, Mass, MassRate, Velocity},
+    scenario::Scenario,
+    time::{solar_elevation, SimClock},
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Ballast>();
+    app.add_event::<BallastCommand>();
+    app.add_event::<BallastDropped>();
+    app.add_systems(
+        FixedUpdate,
+        (handle_ballast_commands, apply_ballast_rules, release_ballast)
+            .chain()
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// A supply of ballast carried by a rigid body. The ballast mass is part of
+/// the body's mass and is removed from it as the ballast is released.
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, Serialize, Deserialize)]
+pub struct Ballast {
+    /// Mass of ballast remaining.
+    pub mass: Mass,
+    /// Maximum rate that ballast can be released.
+    pub flow_rate: MassRate,
+    /// Rules that release ballast automatically.
+    pub rules: Vec<BallastRule>,
+    /// Mass of ballast still to be released by the current drop.
+    pending: Mass,
+    /// Mass of ballast released so far by the current drop.
+    released: Mass,
+    /// Whether each rule has dropped ballast since its condition was last
+    /// clear.
+    fired: Vec<bool>,
+}
+
+impl Ballast {
+    pub fn new(mass: Mass, flow_rate: MassRate) -> Self {
+        Ballast {
+            mass,
+            flow_rate,
+            rules: Vec::new(),
+            pending: Mass::default(),
+            released: Mass::default(),
+            fired: Vec::new(),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: BallastRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Whether ballast is being released right now.
+    pub fn is_releasing(&self) -> bool {
+        self.pending > Mass::default() && self.mass > Mass::default()
+    }
+
+    /// Queue up a drop of some amount of ballast.
+    pub fn release(&mut self, amount: Mass) {
+        self.pending += amount;
+    }
+
+    /// Cancel any drop that is in progress.
+    pub fn stop(&mut self) {
+        self.pending = Mass::default();
+    }
+}
+
+/// A condition that triggers a drop of ballast when it is met. A rule drops
+/// ballast once each time its condition is met, and not again until the
+/// condition has cleared. Rules don't trigger while a drop is in progress.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum BallastRule {
+    /// Drop `amount` of ballast when the body descends faster than
+    /// `descent_rate`.
+    DescentRate { descent_rate: Velocity, amount: Mass },
+    /// Drop `amount` of ballast when the body falls below `altitude`.
+    MinimumAltitude { altitude: Length, amount: Mass },
+    /// Drop `amount` of ballast when the body descends faster than
+    /// `descent_rate` while the sun is lower than `solar_elevation` (degrees)
+    /// above the horizon where the body is. The lift gas cools at sunset, so
+    /// an elevation of -0.833 (the top of the sun on the horizon) catches the
+    /// sink that follows.
+    NightDescentRate {
+        solar_elevation: Scalar,
+        descent_rate: Velocity,
+        amount: Mass,
+    },
+}
+
+impl BallastRule {
+    /// Amount of ballast to drop, given the velocity (m/s) and altitude (m)
+    /// of the body and the elevation (degrees) of the sun where it is.
+    fn triggered(&self, velocity: Vector, altitude: Scalar, sun: f64) -> Option<Mass> {
+        let descending_faster =
+            |descent_rate: &Velocity| -velocity.y > descent_rate.get::<meter_per_second>();
+        match self {
+            BallastRule::DescentRate {
+                descent_rate,
+                amount,
+            } => descending_faster(descent_rate).then_some(*amount),
+            BallastRule::MinimumAltitude {
+                altitude: minimum,
+                amount,
+            } => (altitude < minimum.get::<meter>()).then_some(*amount),
+            BallastRule::NightDescentRate {
+                solar_elevation,
+                descent_rate,
+                amount,
+            } => (sun < f64::from(*solar_elevation) && descending_faster(descent_rate))
+                .then_some(*amount),
+        }
+    }
+}
+
+/// Commands that control the release of ballast from an entity.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BallastCommand {
+    pub entity: Entity,
+    pub action: BallastAction,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BallastAction {
+    /// Release some amount of ballast.
+    Release(Mass),
+    /// Release all of the remaining ballast.
+    ReleaseAll,
+    /// Stop releasing ballast.
+    Stop,
+}
+
+/// Sent when a drop of ballast is finished.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BallastDropped {
+    pub entity: Entity,
+    /// Mass of ballast released by the drop.
+    pub mass: Mass,
+    /// Mass of ballast remaining after the drop.
+    pub remaining: Mass,
+    /// Altitude (m) where the drop finished.
+    pub altitude: Scalar,
+}
+
+fn handle_ballast_commands(
+    mut commands: EventReader<BallastCommand>,
+    mut ballasts: Query<&mut Ballast>,
+) {
+    for command in commands.read() {
+        let Ok(mut ballast) = ballasts.get_mut(command.entity) else {
+            warn!("{:?} does not carry ballast", command.entity);
+            continue;
+        };
+        match command.action {
+            BallastAction::Release(amount) => ballast.release(amount),
+            BallastAction::ReleaseAll => {
+                let remaining = ballast.mass;
+                ballast.stop();
+                ballast.release(remaining);
+            }
+            BallastAction::Stop => ballast.stop(),
+        }
+    }
+}
+
+fn apply_ballast_rules(
+    mut ballasts: Query<(&mut Ballast, &LinearVelocity, &Position)>,
+    scenario: Res<Scenario>,
+    clock: Res<SimClock>,
+) {
+    for (mut ballast, velocity, position) in ballasts.iter_mut() {
+        let (latitude, longitude) = scenario.launch_site.coordinates(position.0);
+        let sun = solar_elevation(latitude, longitude, clock.now());
+        let mut releasing = ballast.is_releasing();
+        let ballast = &mut *ballast;
+        ballast.fired.resize(ballast.rules.len(), false);
+        for (rule, fired) in ballast.rules.iter().zip(ballast.fired.iter_mut()) {
+            let Some(amount) = rule.triggered(velocity.0, position.y, sun) else {
+                // The condition cleared, so the rule may drop again.
+                *fired = false;
+                continue;
+            };
+            if !*fired && !releasing {
+                *fired = true;
+                releasing = true;
+                ballast.pending += amount;
+            }
+        }
+    }
+}
+
+fn release_ballast(
+    mut ballasts: Query<(
+        Entity,
+        &mut Ballast,
+        &mut avian3d::prelude::Mass,
+        &Position,
+    )>,
+    mut dropped: EventWriter<BallastDropped>,
+    time: Res<Time>,
+) {
+    for (entity, mut ballast, mut body_mass, position) in ballasts.iter_mut() {
+        // Nothing is left to drop, so forget about drops that were asked for.
+        if ballast.mass <= Mass::default() {
+            ballast.pending = Mass::default();
+        }
+        // Keep going until a drop that was started has been reported, even
+        // if it was stopped early.
+        if !ballast.is_releasing() && ballast.released == Mass::default() {
+            continue;
+        }
+        let step = Mass::new::<kilogram>(
+            ballast.flow_rate.get::<kilogram_per_second>() * time.delta_secs_f64() as Scalar,
+        )
+        .min(ballast.pending)
+        .min(ballast.mass);
+        ballast.mass -= step;
+        ballast.pending -= step;
+        ballast.released += step;
+        body_mass.0 -= step.get::<kilogram>();
+
+        if !ballast.is_releasing() {
+            let mass = ballast.released;
+            let altitude = position.y;
+            info!(
+                "{:?} dropped {:.3} kg of ballast at {:.1} m, {:.3} kg remaining",
+                entity,
+                mass.get::<kilogram>(),
+                altitude,
+                ballast.mass.get::<kilogram>(),
+            );
+            dropped.send(BallastDropped {
+                entity,
+                mass,
+                remaining: ballast.mass,
+                altitude,
+            });
+            ballast.pending = Mass::default();
+            ballast.released = Mass::default();
+        }
+    }
+}
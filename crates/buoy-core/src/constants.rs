@@ -6,17 +6,19 @@
 //! given constant, we can just use these.
 //! 
 //! All constants are computed using the `uom` crate and support conversion.
-//! 
-//! TODO: Add an option to support f64 features that are available in the crates
-//! that `buoy-core` depends on.
+//! They have the floating point precision of the build, which is `f64` with
+//! the `f64` feature and `f32` otherwise.
 
 use std::sync::LazyLock;
+use avian3d::math::Scalar;
 use uom::si::{
-    acceleration::standard_gravity, f32::*, heat_capacity::boltzmann_constant, length::meter,
+    acceleration::standard_gravity, heat_capacity::boltzmann_constant, length::meter,
     molar_heat_capacity::molar_gas_constant, pressure::pascal, thermodynamic_temperature::kelvin,
 };
 
-pub static PI: f32 = std::f32::consts::PI;
+use crate::quantity::*;
+
+pub static PI: Scalar = std::f64::consts::PI as Scalar;
 pub static BOLTZMANN_CONSTANT: LazyLock<HeatCapacity> =
     LazyLock::new(|| HeatCapacity::new::<boltzmann_constant>(1.0));
 pub static GAS_CONSTANT: LazyLock<MolarHeatCapacity> =
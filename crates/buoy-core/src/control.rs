@@ -0,0 +1,443 @@
+//! Altitude control for balloons that can vent gas and drop ballast.
+//!
+//! A [`Controller`] runs every fixed step. It reads the sensed state of the
+//! balloon it is attached to and turns the [`Actuation`] returned by its
+//! control law into [`VentCommand`]s and [`BallastCommand`]s.
+//!
+//! Control laws implement [`ControlLaw`]. Two altitude-hold laws are built in
+//! so that custom laws have something to be compared against:
+//! - [`PidAltitudeHold`]
+//! - [`BangBangAltitudeHold`]
+//!
+//! The built-in laws can be saved, state and all, so a [`Controller`] that runs
+//! one of them is part of a snapshot. Custom laws aren't.
+
+use avian3d::{
+    math::Scalar,
+    prelude::{LinearVelocity, Position},
+};
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    length::meter,
+    mass::kilogram,
+    mass_rate::kilogram_per_second,
+    velocity::meter_per_second,
+};
+
+use crate::{
+    ballast::{Ballast, BallastAction, BallastCommand},
+    core::SimState,
+    ideal_gas::IdealGas,
+    quantity::{Length, Mass, MassRate, Velocity},
+    vent::{VentAction, VentCommand, VentValve},
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<SavedController>();
+    app.add_systems(
+        FixedUpdate,
+        run_controllers.run_if(in_state(SimState::Running)),
+    );
+}
+
+/// What a controller can sense about the balloon it controls.
+#[derive(Debug, Clone)]
+pub struct Sensors {
+    pub altitude: Length,
+    /// Vertical velocity, positive up.
+    pub ascent_rate: Velocity,
+    /// State of the lift gas inside the balloon.
+    pub gas: IdealGas,
+    /// Ballast remaining, if the flight train carries any.
+    pub ballast: Option<Mass>,
+    /// Whether the vent valve is open, if the balloon has one.
+    pub vent_open: Option<bool>,
+}
+
+/// Actuator commands produced by a control law for a single step.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Actuation {
+    /// Whether the vent valve should be open.
+    pub vent_open: bool,
+    /// Mass of ballast to drop this step.
+    pub ballast: Mass,
+}
+
+/// A control law that decides what the actuators should do from the sensed
+/// state of a balloon.
+pub trait ControlLaw: Send + Sync + 'static {
+    /// Name of the control law, used in logs.
+    fn name(&self) -> &str;
+
+    /// Compute the actuator commands for a step of `dt` seconds.
+    fn update(&mut self, sensors: &Sensors, dt: Scalar) -> Actuation;
+
+    /// The law and its state in a form that can be saved in a snapshot.
+    /// Laws that return `None` are left out of snapshots.
+    fn save(&self) -> Option<SavedLaw> {
+        None
+    }
+}
+
+/// A built-in control law and its state, as saved in a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedLaw {
+    Pid(PidAltitudeHold),
+    BangBang(BangBangAltitudeHold),
+}
+
+impl SavedLaw {
+    fn into_law(self) -> Box<dyn ControlLaw> {
+        match self {
+            SavedLaw::Pid(law) => Box::new(law),
+            SavedLaw::BangBang(law) => Box::new(law),
+        }
+    }
+}
+
+/// Runs a control law against the balloon on the same entity. The vent valve
+/// is expected on the same entity, while the ballast is usually carried by
+/// the payload.
+#[derive(Component)]
+pub struct Controller {
+    pub law: Box<dyn ControlLaw>,
+    /// Entity that carries the [`Ballast`], if any.
+    pub ballast: Option<Entity>,
+    /// Actuation computed during the last step.
+    pub last: Actuation,
+}
+
+impl Controller {
+    pub fn new(law: impl ControlLaw) -> Self {
+        Controller {
+            law: Box::new(law),
+            ballast: None,
+            last: Actuation::default(),
+        }
+    }
+
+    pub fn with_ballast(self, ballast: Entity) -> Self {
+        Self {
+            ballast: Some(ballast),
+            ..self
+        }
+    }
+
+    /// The controller as it is saved in a snapshot, if its law can be saved.
+    pub fn save(&self) -> Option<SavedController> {
+        Some(SavedController {
+            law: self.law.save()?,
+            ballast: self.ballast,
+        })
+    }
+}
+
+/// A [`Controller`] as it is saved in a snapshot. Restoring the snapshot turns
+/// it back into a controller.
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, MapEntities, Serialize, Deserialize)]
+pub struct SavedController {
+    pub law: SavedLaw,
+    pub ballast: Option<Entity>,
+}
+
+impl MapEntities for SavedController {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        if let Some(ballast) = &mut self.ballast {
+            *ballast = entity_mapper.map_entity(*ballast);
+        }
+    }
+}
+
+impl From<SavedController> for Controller {
+    fn from(saved: SavedController) -> Self {
+        Controller {
+            law: saved.law.into_law(),
+            ballast: saved.ballast,
+            last: Actuation::default(),
+        }
+    }
+}
+
+/// Holds altitude with a PID loop on the altitude error.
+///
+/// A positive output means the balloon needs more lift, so ballast is dropped
+/// at a rate proportional to the output. A negative output means the balloon
+/// needs less lift, so the vent valve is opened. Outputs within the deadband
+/// do nothing.
+///
+/// The integral is limited to what saturates the output, and stops growing
+/// toward an actuator that can't act (no ballast left, or no vent valve), so
+/// it doesn't wind up while the balloon can't follow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PidAltitudeHold {
+    pub target: Length,
+    pub kp: Scalar,
+    pub ki: Scalar,
+    pub kd: Scalar,
+    pub deadband: Scalar,
+    /// Ballast flow rate for an output of 1.0.
+    pub ballast_rate: MassRate,
+    integral: Scalar,
+}
+
+impl PidAltitudeHold {
+    pub fn new(target: Length, kp: Scalar, ki: Scalar, kd: Scalar) -> Self {
+        PidAltitudeHold {
+            target,
+            kp,
+            ki,
+            kd,
+            deadband: 0.1,
+            ballast_rate: MassRate::new::<kilogram_per_second>(0.01),
+            integral: 0.0,
+        }
+    }
+
+    pub fn with_deadband(self, deadband: Scalar) -> Self {
+        Self { deadband, ..self }
+    }
+
+    pub fn with_ballast_rate(self, ballast_rate: MassRate) -> Self {
+        Self {
+            ballast_rate,
+            ..self
+        }
+    }
+}
+
+impl ControlLaw for PidAltitudeHold {
+    fn name(&self) -> &str {
+        "PID altitude hold"
+    }
+
+    fn update(&mut self, sensors: &Sensors, dt: Scalar) -> Actuation {
+        let error = (self.target - sensors.altitude).get::<meter>();
+        let out_of_ballast = sensors.ballast.is_some_and(|ballast| ballast <= Mass::default());
+        let no_vent = sensors.vent_open.is_none();
+        if !(error > 0.0 && out_of_ballast || error < 0.0 && no_vent) {
+            let limit = if self.ki > 0.0 {
+                1.0 / self.ki
+            } else {
+                Scalar::INFINITY
+            };
+            self.integral = (self.integral + error * dt).clamp(-limit, limit);
+        }
+        // Take the derivative of the measurement rather than the error so that
+        // changing the target doesn't kick the output.
+        let derivative = -sensors.ascent_rate.get::<meter_per_second>();
+        let output = self.kp * error + self.ki * self.integral + self.kd * derivative;
+
+        if output > self.deadband {
+            Actuation {
+                vent_open: false,
+                ballast: Mass::new::<kilogram>(
+                    output.min(1.0) * self.ballast_rate.get::<kilogram_per_second>() * dt,
+                ),
+            }
+        } else if output < -self.deadband {
+            Actuation {
+                vent_open: true,
+                ballast: Mass::default(),
+            }
+        } else {
+            Actuation::default()
+        }
+    }
+
+    fn save(&self) -> Option<SavedLaw> {
+        Some(SavedLaw::Pid(self.clone()))
+    }
+}
+
+/// Holds altitude within a band around the target. The valve opens when the
+/// balloon climbs above the band and a fixed amount of ballast is dropped
+/// once each time the balloon sinks below the band while descending.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BangBangAltitudeHold {
+    pub target: Length,
+    /// Half-width of the band around the target.
+    pub band: Length,
+    /// Mass of ballast dropped each time the balloon sinks below the band.
+    pub ballast_drop: Mass,
+    venting: bool,
+    /// Whether ballast was dropped since the balloon last left the band.
+    dropped: bool,
+}
+
+impl BangBangAltitudeHold {
+    pub fn new(target: Length, band: Length, ballast_drop: Mass) -> Self {
+        BangBangAltitudeHold {
+            target,
+            band,
+            ballast_drop,
+            venting: false,
+            dropped: false,
+        }
+    }
+}
+
+impl ControlLaw for BangBangAltitudeHold {
+    fn name(&self) -> &str {
+        "bang-bang altitude hold"
+    }
+
+    fn update(&mut self, sensors: &Sensors, _dt: Scalar) -> Actuation {
+        let upper = self.target + self.band;
+        let lower = self.target - self.band;
+
+        // Vent from above the band until the balloon is back at the target.
+        if sensors.altitude > upper {
+            self.venting = true;
+        } else if sensors.altitude <= self.target {
+            self.venting = false;
+        }
+
+        // Drop once per excursion below the band, not on every step.
+        if sensors.altitude >= lower {
+            self.dropped = false;
+        }
+        let descending = sensors.ascent_rate < Velocity::default();
+        let ballast = if sensors.altitude < lower && descending && !self.dropped {
+            self.dropped = true;
+            self.ballast_drop
+        } else {
+            Mass::default()
+        };
+
+        Actuation {
+            vent_open: self.venting,
+            ballast,
+        }
+    }
+
+    fn save(&self) -> Option<SavedLaw> {
+        Some(SavedLaw::BangBang(self.clone()))
+    }
+}
+
+fn run_controllers(
+    mut controllers: Query<(
+        Entity,
+        &mut Controller,
+        &Position,
+        &LinearVelocity,
+        &IdealGas,
+        Option<&VentValve>,
+    )>,
+    ballasts: Query<&Ballast>,
+    mut vent_commands: EventWriter<VentCommand>,
+    mut ballast_commands: EventWriter<BallastCommand>,
+    time: Res<Time>,
+) {
+    for (entity, mut controller, position, velocity, gas, vent_valve) in controllers.iter_mut() {
+        let ballast = controller
+            .ballast
+            .and_then(|ballast| ballasts.get(ballast).ok());
+        let sensors = Sensors {
+            altitude: Length::new::<meter>(position.y),
+            ascent_rate: Velocity::new::<meter_per_second>(velocity.y),
+            gas: gas.clone(),
+            ballast: ballast.map(|ballast| ballast.mass),
+            vent_open: vent_valve.map(|valve| valve.open),
+        };
+
+        let actuation = controller.law.update(&sensors, time.delta_secs_f64() as Scalar);
+        if actuation != controller.last {
+            trace!(
+                "{:?} {}: {:?}",
+                entity,
+                controller.law.name(),
+                actuation
+            );
+        }
+
+        if let Some(open) = sensors.vent_open {
+            if open != actuation.vent_open {
+                vent_commands.send(VentCommand {
+                    entity,
+                    action: if actuation.vent_open {
+                        VentAction::Open
+                    } else {
+                        VentAction::Close
+                    },
+                });
+            }
+        }
+
+        if let (Some(ballast), Some(remaining)) = (controller.ballast, sensors.ballast) {
+            if actuation.ballast > Mass::default() && remaining > Mass::default() {
+                ballast_commands.send(BallastCommand {
+                    entity: ballast,
+                    action: BallastAction::Release(actuation.ballast),
+                });
+            }
+        }
+
+        controller.last = actuation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a balloon with a closed vent valve senses at some altitude (m),
+    /// ascent rate (m/s) and ballast (kg) remaining.
+    fn sensors(altitude: Scalar, ascent_rate: Scalar, ballast: Scalar) -> Sensors {
+        Sensors {
+            altitude: Length::new::<meter>(altitude),
+            ascent_rate: Velocity::new::<meter_per_second>(ascent_rate),
+            gas: IdealGas::default(),
+            ballast: Some(Mass::new::<kilogram>(ballast)),
+            vent_open: Some(false),
+        }
+    }
+
+    #[test]
+    fn pid_integral_stays_within_its_clamp() {
+        let mut pid = PidAltitudeHold::new(Length::new::<meter>(1000.0), 0.0, 0.01, 0.0);
+        // An hour a kilometer below the target would wind the integral up to
+        // 3.6e6 m·s, but an integral of 1/ki already saturates the output.
+        for _ in 0..3600 {
+            pid.update(&sensors(0.0, 0.0, 1.0), 1.0);
+        }
+        assert_eq!(pid.integral, 100.0);
+        // So a second above the target unwinds it right away.
+        pid.update(&sensors(1100.0, 0.0, 1.0), 1.0);
+        assert_eq!(pid.integral, 0.0);
+        // Out of ballast, it doesn't grow toward drops that can't happen.
+        pid.update(&sensors(0.0, 0.0, 0.0), 1.0);
+        assert_eq!(pid.integral, 0.0);
+    }
+
+    #[test]
+    fn bang_bang_drops_once_per_excursion() {
+        let mut law = BangBangAltitudeHold::new(
+            Length::new::<meter>(1000.0),
+            Length::new::<meter>(50.0),
+            Mass::new::<kilogram>(0.5),
+        );
+        let mut drop = |altitude, ascent_rate| {
+            law.update(&sensors(altitude, ascent_rate, 1.0), 1.0)
+                .ballast
+                .get::<kilogram>()
+        };
+        // Sinking below the band drops once, however long it keeps sinking.
+        assert_eq!(drop(960.0, -1.0), 0.0);
+        assert_eq!(drop(940.0, -1.0), 0.5);
+        assert_eq!(drop(930.0, -1.0), 0.0);
+        assert_eq!(drop(920.0, -0.5), 0.0);
+        // Climbing back into the band sets it up for the next excursion.
+        assert_eq!(drop(935.0, 1.0), 0.0);
+        assert_eq!(drop(955.0, 1.0), 0.0);
+        assert_eq!(drop(945.0, -1.0), 0.5);
+    }
+}
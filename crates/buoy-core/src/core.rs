@@ -6,30 +6,70 @@ use bevy::{
     app::{PluginGroup, PluginGroupBuilder},
     prelude::*,
 };
-use uom::si::{f32::*, Quantity};
+use uom::si::Quantity;
 
-pub struct BuoyPlugin;
+use crate::quantity::*;
+
+#[derive(Default)]
+pub struct BuoyPlugin {
+    /// Make runs reproducible bit for bit. See [`determinism`].
+    pub deterministic: bool,
+}
+
+impl BuoyPlugin {
+    pub fn deterministic() -> Self {
+        BuoyPlugin {
+            deterministic: true,
+        }
+    }
+}
 
 impl Plugin for BuoyPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             CoreSystemsPlugin,
-            CorePhysicsPlugin,
+            CorePhysicsPlugin {
+                deterministic: self.deterministic,
+            },
         ));
+        if self.deterministic {
+            app.add_plugins(determinism::deterministic_plugin);
+        }
     }
 }
 
-struct CorePhysicsPlugin;
+struct CorePhysicsPlugin {
+    deterministic: bool,
+}
 
 impl Plugin for CorePhysicsPlugin {
     fn build(&self, app: &mut App) {
+        if self.deterministic {
+            // Interpolation smooths rendering between physics steps, which
+            // makes transforms depend on the frame rate.
+            app.add_plugins(
+                PhysicsPlugins::default()
+                    .build()
+                    .disable::<PhysicsInterpolationPlugin>(),
+            );
+        } else {
+            app.add_plugins(
+                PhysicsPlugins::default().set(PhysicsInterpolationPlugin::interpolate_all()),
+            );
+        }
         app.add_plugins((
-            PhysicsPlugins::default().set(PhysicsInterpolationPlugin::interpolate_all()),
             ideal_gas::plugin,
             atmosphere::plugin,
+            ballast::plugin,
+            control::plugin,
             forces::plugin,
+            flight_train::plugin,
             grid::plugin,
             time::plugin,
+            turbulence::plugin,
+            vent::plugin,
+            water::plugin,
+            wind::plugin,
         ));
     }
 }
@@ -41,8 +81,13 @@ impl Plugin for CoreSystemsPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<SimState>();
         app.add_plugins((
+            determinism::plugin,
+            fault::plugin,
             format::plugin,
-            scene::plugin,
+            replay::plugin,
+            scenario::plugin,
+            snapshot::plugin,
+            telemetry::plugin,
         ));
     }
 }
@@ -0,0 +1,107 @@
+//! Reproducible simulation runs.
+//!
+//! Regression tests and Monte Carlo ensembles need two runs of the same
+//! scenario to give bit-identical results. By default the simulation favors
+//! smooth rendering over reproducibility, so deterministic mode changes a few
+//! things:
+//!
+//! - The fixed timestep is set explicitly instead of relying on the default.
+//! - Physics interpolation is left out, since it depends on the frame rate.
+//! - Systems in the fixed schedules run one at a time, in the same order every
+//!   step. Systems that don't conflict could otherwise run in any order, and
+//!   floating point sums depend on the order they are added up in.
+//!
+//! Randomness always comes from [`SimRng`], which is seeded from the scenario,
+//! so runs with the same scenario draw the same random numbers whether or not
+//! deterministic mode is on.
+//!
+//! Runs are only reproducible for the same build on the same platform. Enable
+//! the `enhanced-determinism` feature to make the physics engine use the same
+//! math on every platform.
+
+use bevy::{
+    ecs::schedule::{ExecutorKind, ScheduleLabel},
+    prelude::*,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::scenario::Scenario;
+
+/// Fixed timestep (Hz) used in deterministic mode.
+pub const DETERMINISTIC_TIMESTEP_HZ: f64 = 64.0;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<SimRngState>();
+    app.insert_resource(SimRng::seed_from_u64(0));
+    app.add_systems(PreStartup, seed_rng);
+}
+
+/// Set up an app for deterministic runs. Added by the core plugin when
+/// deterministic mode is on.
+pub(crate) fn deterministic_plugin(app: &mut App) {
+    app.insert_resource(Deterministic);
+    app.insert_resource(Time::<Fixed>::from_hz(DETERMINISTIC_TIMESTEP_HZ));
+    single_threaded(app, FixedFirst);
+    single_threaded(app, FixedPreUpdate);
+    single_threaded(app, FixedUpdate);
+    single_threaded(app, FixedPostUpdate);
+    single_threaded(app, FixedLast);
+}
+
+fn single_threaded(app: &mut App, label: impl ScheduleLabel) {
+    app.edit_schedule(label, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+}
+
+/// Present when the simulation runs in deterministic mode.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Deterministic;
+
+/// The source of every random number in the simulation. It is seeded from
+/// [`Scenario::seed`] at startup.
+#[derive(Resource, Debug, Clone, Deref, DerefMut)]
+pub struct SimRng(pub ChaCha8Rng);
+
+impl SimRng {
+    pub fn seed_from_u64(seed: u64) -> Self {
+        SimRng(ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Where the generator is in its sequence of random numbers.
+    pub fn state(&self) -> SimRngState {
+        let word_pos = self.0.get_word_pos();
+        SimRngState {
+            seed: self.0.get_seed(),
+            stream: self.0.get_stream(),
+            word_pos: ((word_pos >> 64) as u64, word_pos as u64),
+        }
+    }
+
+    /// A generator that picks up the sequence where the state left off.
+    pub fn from_state(state: &SimRngState) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        let (high, low) = state.word_pos;
+        rng.set_word_pos((u128::from(high) << 64) | u128::from(low));
+        SimRng(rng)
+    }
+}
+
+/// The state of a [`SimRng`], which is saved in snapshots so a restored run
+/// draws the same random numbers as the original one.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Resource, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimRngState {
+    seed: [u8; 32],
+    stream: u64,
+    /// Position in the stream, split into its high and low 64 bits because
+    /// scenes can't hold 128-bit integers.
+    word_pos: (u64, u64),
+}
+
+fn seed_rng(mut commands: Commands, scenario: Res<Scenario>) {
+    commands.insert_resource(SimRng::seed_from_u64(scenario.seed));
+}
@@ -0,0 +1,347 @@
+//! An empirical atmosphere that changes with place, season, time of day and
+//! solar activity.
+//!
+//! Up to the mesopause, temperature and pressure come from the profiles of the
+//! U.S. Standard Atmosphere Supplements, 1966. The profiles for the latitude
+//! bands on either side of a position are blended, and each is blended between
+//! its January and July profiles by the day of year. Seasons are flipped in
+//! the southern hemisphere.
+//!
+//! Above the mesopause the temperature follows the shape of the thermosphere
+//! of the 1976 standard, rising to an exospheric temperature from the Jacchia
+//! (1971) model. That temperature rises with the solar radio flux (F10.7) and
+//! geomagnetic activity (Ap) and swings with the local solar time, peaking in
+//! the afternoon under the subsolar point. Pressure comes from hydrostatic
+//! balance, for a gas whose molar mass falls from that of air toward that of
+//! atomic oxygen above the turbopause.
+//!
+//! This is much simpler than models like NRLMSISE-00: there is no separate
+//! density for each species and the lower atmosphere has no tides. It is
+//! meant to show how a flight changes with the place and date of launch, and
+//! to give plausible densities for anything that climbs past 85 km.
+//!
+//! Reference:
+//! - Jacchia, L. G. (1971), Revised static models of the thermosphere and
+//!   exosphere with empirical temperature profiles, SAO Special Report 332.
+
+use avian3d::math::{Scalar, Vector};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    acceleration::meter_per_second_squared, length::meter,
+    molar_heat_capacity::joule_per_kelvin_mole,
+};
+
+use crate::{
+    atmosphere::{LayeredProfile, Supplement},
+    constants::{EARTH_RADIUS_M, GAS_CONSTANT, PI, STANDARD_GRAVITY},
+    time::solar_angles,
+};
+
+/// Highest altitude (m) covered by the model.
+pub(crate) const MAX_ALTITUDE: Scalar = 1_000_000.0;
+
+/// Geometric altitude (m) where the lower profiles hand over to the
+/// thermosphere.
+const MESOPAUSE: Scalar = 86_000.0;
+
+/// Temperature (K) of the 1976 standard from 91 km up to the bottom of the
+/// elliptical segment of its thermosphere.
+const MESOPAUSE_TEMPERATURE: Scalar = 186.8673;
+
+/// Temperature (K) and temperature gradient (K/m) at 120 km, where the
+/// thermosphere starts to approach the exospheric temperature.
+const T_120: Scalar = 360.0;
+const GRADIENT_120: Scalar = 0.012;
+
+/// Geometric altitude (m) of the turbopause, above which the gases of the air
+/// separate and the mean molar mass falls.
+const TURBOPAUSE: Scalar = 100_000.0;
+
+/// Molar masses (kg/mol) of air and atomic oxygen.
+const AIR_MOLAR_MASS: Scalar = 0.0289644;
+const OXYGEN_MOLAR_MASS: Scalar = 0.016;
+
+/// Altitude (m) over which the mean molar mass above the turbopause moves a
+/// factor of e closer to that of atomic oxygen.
+const MOLAR_MASS_SCALE_HEIGHT: Scalar = 100_000.0;
+
+/// Step (m) of the hydrostatic integration through the thermosphere.
+const INTEGRATION_STEP: Scalar = 1000.0;
+
+/// Radius (m) of the Earth used for gravity in the thermosphere.
+const GRAVITY_RADIUS: Scalar = 6_356_766.0;
+
+/// Solar and geomagnetic activity that heats the thermosphere.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpaceWeather {
+    /// Solar radio flux at 10.7 cm (10⁻²² W/m²/Hz) of the previous day.
+    pub f107: Scalar,
+    /// Solar radio flux at 10.7 cm, averaged over 81 days.
+    pub f107_average: Scalar,
+    /// Daily geomagnetic index.
+    pub ap: Scalar,
+}
+
+impl Default for SpaceWeather {
+    /// Moderate solar activity and a quiet magnetic field.
+    fn default() -> Self {
+        SpaceWeather {
+            f107: 150.0,
+            f107_average: 150.0,
+            ap: 4.0,
+        }
+    }
+}
+
+impl SpaceWeather {
+    /// Exospheric temperature (K) at some latitude (rad), solar declination
+    /// (rad) and hour angle of the sun (rad) from the Jacchia (1971) model.
+    fn exospheric_temperature(
+        &self,
+        latitude: Scalar,
+        declination: Scalar,
+        hour_angle: Scalar,
+    ) -> Scalar {
+        const R: Scalar = 0.3;
+        const M: Scalar = 2.2;
+        const N: Scalar = 3.0;
+        let beta = Scalar::to_radians(-37.0);
+        let p = Scalar::to_radians(6.0);
+        let gamma = Scalar::to_radians(43.0);
+
+        let night = 379.0 + 3.24 * self.f107_average + 1.3 * (self.f107 - self.f107_average);
+        let eta = (latitude - declination).abs() / 2.0;
+        let theta = (latitude + declination).abs() / 2.0;
+        let tau = wrap_angle(hour_angle + beta + p * (hour_angle + gamma).sin());
+        let sin_theta = theta.sin().powf(M);
+        let cos_eta = eta.cos().powf(M);
+        let diurnal = (cos_eta - sin_theta) / (1.0 + R * sin_theta) * (tau / 2.0).cos().powf(N);
+        let local = night * (1.0 + R * sin_theta) * (1.0 + R * diurnal);
+        let geomagnetic = self.ap + 100.0 * (1.0 - Scalar::exp(-0.08 * self.ap));
+        local + geomagnetic
+    }
+}
+
+/// Latitudes (degrees) of the bands of the supplements, with their January
+/// and July profiles.
+const BANDS: [(Scalar, Supplement, Supplement); 4] = [
+    (15.0, Supplement::Tropical, Supplement::Tropical),
+    (30.0, Supplement::SubtropicalWinter, Supplement::SubtropicalSummer),
+    (45.0, Supplement::MidlatitudeWinter, Supplement::MidlatitudeSummer),
+    (60.0, Supplement::SubarcticWinter, Supplement::SubarcticSummer),
+];
+
+/// The empirical model, placed on the Earth and in time.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EmpiricalAtmosphere {
+    weather: SpaceWeather,
+    /// January and July profiles of each of the [`BANDS`].
+    profiles: Vec<(LayeredProfile, LayeredProfile)>,
+}
+
+/// Where and when the model is evaluated.
+struct Conditions {
+    /// Geodetic latitude (degrees).
+    latitude: Scalar,
+    /// Weight of the July profiles, from 0 in January to 1 in July.
+    summer: Scalar,
+    /// Exospheric temperature (K).
+    exospheric: Scalar,
+}
+
+impl EmpiricalAtmosphere {
+    pub(crate) fn new(weather: SpaceWeather) -> Self {
+        let profile = |supplement: Supplement| {
+            let (surface_pressure, breakpoints) = supplement.breakpoints();
+            LayeredProfile::new(surface_pressure, breakpoints, 0.0)
+        };
+        EmpiricalAtmosphere {
+            weather,
+            profiles: BANDS
+                .iter()
+                .map(|(_, winter, summer)| (profile(*winter), profile(*summer)))
+                .collect(),
+        }
+    }
+
+    /// Temperature (K) at a position, for an origin at some latitude and
+    /// longitude (degrees) and some time.
+    pub(crate) fn temperature(
+        &self,
+        position: Vector,
+        origin: (f64, f64),
+        time: DateTime<Utc>,
+    ) -> Scalar {
+        let conditions = self.conditions(position, origin, time);
+        let altitude = position.y;
+        if altitude <= MESOPAUSE {
+            self.lower(altitude, &conditions).0
+        } else {
+            self.thermosphere_temperature(altitude, &conditions)
+        }
+    }
+
+    /// Pressure (Pa) at a position, for an origin at some latitude and
+    /// longitude (degrees) and some time.
+    pub(crate) fn pressure(
+        &self,
+        position: Vector,
+        origin: (f64, f64),
+        time: DateTime<Utc>,
+    ) -> Scalar {
+        let conditions = self.conditions(position, origin, time);
+        let altitude = position.y;
+        if altitude <= MESOPAUSE {
+            return self.lower(altitude, &conditions).1;
+        }
+        let log_pressure = self.lower(MESOPAUSE, &conditions).1.ln();
+        self.integrate(MESOPAUSE, log_pressure, altitude, &conditions).exp()
+    }
+
+    /// Integrate ln p up through the thermosphere, from the log of the
+    /// pressure (Pa) at one altitude (m) to another.
+    fn integrate(
+        &self,
+        from: Scalar,
+        mut log_pressure: Scalar,
+        to: Scalar,
+        conditions: &Conditions,
+    ) -> Scalar {
+        let steps = ((to - from) / INTEGRATION_STEP).ceil().max(1.0);
+        let dz = (to - from) / steps;
+        let gas_constant = GAS_CONSTANT.get::<joule_per_kelvin_mole>();
+        let surface_gravity = STANDARD_GRAVITY.get::<meter_per_second_squared>();
+        for step in 0..steps as usize {
+            let z = from + (step as Scalar + 0.5) * dz;
+            let gravity = surface_gravity * (GRAVITY_RADIUS / (GRAVITY_RADIUS + z)).powi(2);
+            let temperature = self.thermosphere_temperature(z, conditions);
+            log_pressure -= gravity * molar_mass(z) / (gas_constant * temperature) * dz;
+        }
+        log_pressure
+    }
+
+    /// Latitude, season and exospheric temperature at a position, for an
+    /// origin at some latitude and longitude (degrees) and some time.
+    fn conditions(&self, position: Vector, origin: (f64, f64), time: DateTime<Utc>) -> Conditions {
+        let radius = f64::from(EARTH_RADIUS_M.get::<meter>());
+        let (origin_latitude, origin_longitude) = origin;
+        let latitude = origin_latitude + (-f64::from(position.z) / radius).to_degrees();
+        let longitude = origin_longitude
+            + (f64::from(position.x) / (radius * origin_latitude.to_radians().cos())).to_degrees();
+        let hours = f64::from(time.num_seconds_from_midnight()) / 3600.0;
+        let day = f64::from(time.ordinal0()) + hours / 24.0;
+        let year = 2.0 * std::f64::consts::PI / 365.25;
+
+        // Zero in mid January and one in mid July, the other way around in
+        // the southern hemisphere.
+        let mut summer = (1.0 - ((day - 15.0) * year).cos()) / 2.0;
+        if latitude < 0.0 {
+            summer = 1.0 - summer;
+        }
+        let (declination, hour_angle) = solar_angles(longitude, time);
+        let exospheric = self
+            .weather
+            .exospheric_temperature(
+                latitude.to_radians() as Scalar,
+                declination as Scalar,
+                wrap_angle(hour_angle as Scalar),
+            )
+            .max(T_120 + 100.0);
+        Conditions {
+            latitude: latitude as Scalar,
+            summer: summer as Scalar,
+            exospheric,
+        }
+    }
+
+    /// Temperature (K) and pressure (Pa) below the mesopause, blended from
+    /// the profiles of the supplements.
+    fn lower(&self, altitude: Scalar, conditions: &Conditions) -> (Scalar, Scalar) {
+        let latitude = conditions.latitude.abs();
+        let upper = BANDS.partition_point(|(band, _, _)| *band < latitude);
+        let (index, weight) = match upper {
+            0 => (0, 0.0),
+            upper if upper == BANDS.len() => (BANDS.len() - 2, 1.0),
+            upper => {
+                let (below, above) = (BANDS[upper - 1].0, BANDS[upper].0);
+                (upper - 1, (latitude - below) / (above - below))
+            }
+        };
+        let band = |index: usize| {
+            let (winter, summer) = &self.profiles[index];
+            let s = conditions.summer;
+            (
+                (1.0 - s) * winter.temperature(altitude) + s * summer.temperature(altitude),
+                (1.0 - s) * winter.pressure(altitude).ln() + s * summer.pressure(altitude).ln(),
+            )
+        };
+        let (t0, p0) = band(index);
+        let (t1, p1) = band(index + 1);
+        (t0 + (t1 - t0) * weight, (p0 + (p1 - p0) * weight).exp())
+    }
+
+    /// Temperature (K) above the mesopause.
+    fn thermosphere_temperature(&self, altitude: Scalar, conditions: &Conditions) -> Scalar {
+        let km = altitude / 1000.0;
+        if km < 91.0 {
+            // Join the blended profile to the isothermal mesopause.
+            let t86 = self.lower(MESOPAUSE, conditions).0;
+            t86 + (MESOPAUSE_TEMPERATURE - t86) * (km - 86.0) / 5.0
+        } else if km < 110.0 {
+            let x = (km - 91.0) / -19.9429;
+            263.1905 - 76.3232 * (1.0 - x * x).max(0.0).sqrt()
+        } else if km < 120.0 {
+            240.0 + 12.0 * (km - 110.0)
+        } else {
+            let exospheric = conditions.exospheric;
+            let lambda = GRADIENT_120 * 1000.0 / (exospheric - T_120);
+            let radius = GRAVITY_RADIUS / 1000.0;
+            let xi = (km - 120.0) * (radius + 120.0) / (radius + km);
+            exospheric - (exospheric - T_120) * Scalar::exp(-lambda * xi)
+        }
+    }
+}
+
+/// Mean molar mass (kg/mol) of the gas at a geometric altitude (m).
+pub(crate) fn molar_mass(altitude: Scalar) -> Scalar {
+    if altitude <= TURBOPAUSE {
+        AIR_MOLAR_MASS
+    } else {
+        OXYGEN_MOLAR_MASS
+            + (AIR_MOLAR_MASS - OXYGEN_MOLAR_MASS)
+                * Scalar::exp(-(altitude - TURBOPAUSE) / MOLAR_MASS_SCALE_HEIGHT)
+    }
+}
+
+/// Angle (rad) wrapped into -π to π.
+fn wrap_angle(angle: Scalar) -> Scalar {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thermosphere_matches_the_1976_standard() {
+        let model = EmpiricalAtmosphere::new(SpaceWeather::default());
+        let conditions = Conditions {
+            latitude: 45.0,
+            summer: 0.5,
+            exospheric: 1000.0,
+        };
+        let temperature = |km: Scalar| model.thermosphere_temperature(km * 1000.0, &conditions);
+        // The bottom of the elliptical segment, the start of the linear
+        // segment and the start of the exponential segment.
+        for (km, expected) in [(91.0, 186.8673), (110.0, 240.0), (120.0, T_120)] {
+            for actual in [temperature(km - 1e-3), temperature(km)] {
+                assert!(
+                    (actual - expected).abs() < 0.05,
+                    "temperature at {km} km is {actual} K, expected {expected} K"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,440 @@
+//! Monte Carlo ensembles for predicting where a flight lands.
+//!
+//! A single trajectory says little about where to go looking for a payload.
+//! An [`Ensemble`] runs the same scenario many times, each time with the
+//! uncertain parameters perturbed by a random draw from a [`Dispersion`], and
+//! collects where each balloon burst and each payload landed. The scatter of
+//! those points is summarized by error ellipses and the circular error
+//! probable (CEP).
+//!
+//! Runs are spread across threads. Every run draws its perturbation from its
+//! own stream of a seeded random number generator and runs in deterministic
+//! mode, so an ensemble gives the same results for the same seed no matter how
+//! many threads it runs on.
+//!
+//! Horizontal points are the (x, z) world coordinates of the 3D positions:
+//! meters east and south of the grid origin.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use avian3d::math::{Matrix2, Scalar, Vector, Vector2};
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+
+use crate::{
+    flight_train::BalloonBurst,
+    headless::{HeadlessSim, SimOutcome},
+    scenario::{PayloadLanded, Scenario},
+    telemetry::TelemetryConfig,
+};
+
+/// Perturbed factors are kept above this so that a wild draw can't make a
+/// parameter zero or negative.
+const MIN_FACTOR: Scalar = 0.1;
+
+/// How much each uncertain parameter varies from run to run. Each value is
+/// the standard deviation of a normal distribution centered on the nominal
+/// value from the scenario.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dispersion {
+    /// Relative standard deviation of the ascent rate.
+    pub ascent_rate: Scalar,
+    /// Relative standard deviation of the burst diameter.
+    pub burst_diameter: Scalar,
+    /// Standard deviation (m/s) of the error in each horizontal component of
+    /// the wind.
+    pub wind: Scalar,
+    /// Relative standard deviation of every drag coefficient.
+    pub drag_coefficient: Scalar,
+}
+
+impl Default for Dispersion {
+    fn default() -> Self {
+        Dispersion {
+            ascent_rate: 0.05,
+            burst_diameter: 0.07,
+            wind: 2.0,
+            drag_coefficient: 0.1,
+        }
+    }
+}
+
+/// The perturbation applied to one run of an ensemble.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Perturbation {
+    /// Factor applied to the ascent rate.
+    pub ascent_rate: Scalar,
+    /// Factor applied to the burst diameter.
+    pub burst_diameter: Scalar,
+    /// Velocity (m/s) added to the wind at every altitude.
+    pub wind: Vector,
+    /// Factor applied to every drag coefficient.
+    pub drag_coefficient: Scalar,
+}
+
+impl Default for Perturbation {
+    fn default() -> Self {
+        Perturbation {
+            ascent_rate: 1.0,
+            burst_diameter: 1.0,
+            wind: Vector::ZERO,
+            drag_coefficient: 1.0,
+        }
+    }
+}
+
+impl Perturbation {
+    /// Draw a perturbation from a dispersion.
+    pub fn sample(dispersion: &Dispersion, rng: &mut impl Rng) -> Self {
+        let mut normal = |std_dev: Scalar| rng.sample::<Scalar, _>(StandardNormal) * std_dev;
+        Perturbation {
+            ascent_rate: (1.0 + normal(dispersion.ascent_rate)).max(MIN_FACTOR),
+            burst_diameter: (1.0 + normal(dispersion.burst_diameter)).max(MIN_FACTOR),
+            wind: Vector::new(normal(dispersion.wind), 0.0, normal(dispersion.wind)),
+            drag_coefficient: (1.0 + normal(dispersion.drag_coefficient)).max(MIN_FACTOR),
+        }
+    }
+
+    /// Perturb a scenario.
+    ///
+    /// The ascent rate is changed through the mass of lift gas. Ascent rate
+    /// goes with the square root of free lift, so the free lift is scaled by
+    /// the square of the ascent rate factor.
+    pub fn apply(&self, scenario: &mut Scenario) {
+        let properties = scenario.properties();
+        for balloon in scenario.balloons.iter_mut() {
+            if let Some(free_lift) = balloon.free_lift(&properties).filter(|lift| *lift > 0.0) {
+                let free_lift = free_lift * self.ascent_rate * self.ascent_rate;
+                balloon.set_free_lift(free_lift, &properties);
+            }
+            if let Some(burst_diameter) = balloon.envelope.burst_diameter.as_mut() {
+                *burst_diameter *= self.burst_diameter;
+            }
+            balloon.envelope.drag_coefficient *= self.drag_coefficient;
+            balloon.payload.drag_coefficient *= self.drag_coefficient;
+            if let Some(parachute) = balloon.parachute.as_mut() {
+                parachute.drag_coefficient *= self.drag_coefficient;
+            }
+        }
+        scenario.wind.offset(self.wind);
+    }
+}
+
+/// A batch of perturbed runs of one scenario.
+#[derive(Debug, Clone)]
+pub struct Ensemble {
+    pub scenario: Scenario,
+    pub runs: usize,
+    /// Seed of the random number generator that perturbs each run.
+    pub seed: u64,
+    pub dispersion: Dispersion,
+    /// Each run stops when this much physics time has elapsed.
+    pub max_duration: Duration,
+    /// Number of runs to do at the same time.
+    pub threads: usize,
+}
+
+impl Ensemble {
+    pub fn new(scenario: Scenario, runs: usize) -> Self {
+        Ensemble {
+            scenario,
+            runs,
+            seed: 0,
+            dispersion: Dispersion::default(),
+            max_duration: Duration::from_secs(4 * 60 * 60),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    pub fn with_dispersion(self, dispersion: Dispersion) -> Self {
+        Self { dispersion, ..self }
+    }
+
+    pub fn with_max_duration(self, max_duration: Duration) -> Self {
+        Self {
+            max_duration,
+            ..self
+        }
+    }
+
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self { threads, ..self }
+    }
+
+    /// Random numbers for one run. Each run has its own stream, so results
+    /// do not depend on the order runs are done in.
+    fn rng(&self, index: usize) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(index as u64);
+        rng
+    }
+
+    /// The perturbation applied to one run.
+    pub fn perturbation(&self, index: usize) -> Perturbation {
+        Perturbation::sample(&self.dispersion, &mut self.rng(index))
+    }
+
+    /// Do one run of the ensemble.
+    pub fn run_one(&self, index: usize) -> RunResult {
+        let mut rng = self.rng(index);
+        let perturbation = Perturbation::sample(&self.dispersion, &mut rng);
+        let mut scenario = self.scenario.clone();
+        perturbation.apply(&mut scenario);
+        // Randomness inside the simulation is seeded per run as well.
+        scenario.seed = rng.gen();
+        // Runs only report where they burst and landed.
+        scenario.outputs.telemetry = TelemetryConfig {
+            sample_rate: 0.0,
+            csv: false,
+            parquet: false,
+        };
+
+        let mut app = HeadlessSim::batch_app();
+        app.init_resource::<FlightEvents>();
+        app.add_systems(FixedPostUpdate, record_flight_events);
+        let mut sim = HeadlessSim::from_app(app)
+            .with_scenario(scenario)
+            .with_max_duration(self.max_duration);
+        let outcome = sim.run();
+        let events = sim
+            .world_mut()
+            .remove_resource::<FlightEvents>()
+            .unwrap_or_default();
+        RunResult {
+            index,
+            perturbation,
+            outcome,
+            bursts: events.bursts,
+            landings: events.landings,
+        }
+    }
+
+    /// Do every run of the ensemble, spread across threads.
+    pub fn run(&self) -> EnsembleResult {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(self.runs));
+        thread::scope(|scope| {
+            for _ in 0..self.threads.clamp(1, self.runs.max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= self.runs {
+                        break;
+                    }
+                    let result = self.run_one(index);
+                    results.lock().unwrap().push(result);
+                });
+            }
+        });
+        let mut runs = results.into_inner().unwrap();
+        runs.sort_by_key(|run| run.index);
+        EnsembleResult { runs }
+    }
+}
+
+/// What happened in one run of an ensemble.
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    pub index: usize,
+    pub perturbation: Perturbation,
+    pub outcome: SimOutcome,
+    /// Positions (m) where balloons burst.
+    pub bursts: Vec<Vector>,
+    /// Positions (m) where payloads landed.
+    pub landings: Vec<Vector>,
+}
+
+/// Every run of an ensemble, in order.
+#[derive(Debug, Clone, Default)]
+pub struct EnsembleResult {
+    pub runs: Vec<RunResult>,
+}
+
+impl EnsembleResult {
+    /// Horizontal positions (m) of every landing.
+    pub fn landing_points(&self) -> Vec<Vector2> {
+        self.runs
+            .iter()
+            .flat_map(|run| run.landings.iter().map(|position| position.xz()))
+            .collect()
+    }
+
+    /// Horizontal positions (m) of every burst.
+    pub fn burst_points(&self) -> Vec<Vector2> {
+        self.runs
+            .iter()
+            .flat_map(|run| run.bursts.iter().map(|position| position.xz()))
+            .collect()
+    }
+
+    pub fn landing_scatter(&self) -> Option<Scatter> {
+        Scatter::from_points(&self.landing_points())
+    }
+
+    pub fn burst_scatter(&self) -> Option<Scatter> {
+        Scatter::from_points(&self.burst_points())
+    }
+
+    /// Mean and standard deviation (m) of the burst altitude.
+    pub fn burst_altitude(&self) -> Option<(Scalar, Scalar)> {
+        let altitudes = self
+            .runs
+            .iter()
+            .flat_map(|run| run.bursts.iter().map(|position| position.y))
+            .collect::<Vec<_>>();
+        if altitudes.is_empty() {
+            return None;
+        }
+        let n = altitudes.len() as Scalar;
+        let mean = altitudes.iter().sum::<Scalar>() / n;
+        let variance = altitudes.iter().map(|a| (a - mean).powi(2)).sum::<Scalar>() / n;
+        Some((mean, variance.sqrt()))
+    }
+
+    /// Write one row per run: its perturbation, how it ended and where it
+    /// burst and landed. Only the first burst and landing of each run are
+    /// written.
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record([
+            "run",
+            "ascent_rate_factor",
+            "burst_diameter_factor",
+            "wind_error_x (m/s)",
+            "wind_error_z (m/s)",
+            "drag_coefficient_factor",
+            "state",
+            "elapsed (s)",
+            "burst_x (m)",
+            "burst_y (m)",
+            "burst_z (m)",
+            "landing_x (m)",
+            "landing_z (m)",
+        ])?;
+        let optional = |value: Option<Scalar>| value.map_or(String::new(), |v| v.to_string());
+        for run in &self.runs {
+            let burst = run.bursts.first();
+            let landing = run.landings.first();
+            writer.write_record([
+                run.index.to_string(),
+                run.perturbation.ascent_rate.to_string(),
+                run.perturbation.burst_diameter.to_string(),
+                run.perturbation.wind.x.to_string(),
+                run.perturbation.wind.z.to_string(),
+                run.perturbation.drag_coefficient.to_string(),
+                format!("{:?}", run.outcome.state),
+                run.outcome.elapsed.as_secs_f64().to_string(),
+                optional(burst.map(|p| p.x)),
+                optional(burst.map(|p| p.y)),
+                optional(burst.map(|p| p.z)),
+                optional(landing.map(|p| p.x)),
+                optional(landing.map(|p| p.z)),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Summary statistics of a scatter of horizontal points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scatter {
+    pub count: usize,
+    /// Mean position (m).
+    pub mean: Vector2,
+    /// Covariance (m²) of the positions.
+    pub covariance: Matrix2,
+    /// Circular error probable (m): the radius around the mean that contains
+    /// half of the points.
+    pub cep: Scalar,
+}
+
+impl Scatter {
+    pub fn from_points(points: &[Vector2]) -> Option<Self> {
+        if points.is_empty() {
+            return None;
+        }
+        let n = points.len() as Scalar;
+        let mean = points.iter().copied().sum::<Vector2>() / n;
+        let (mut xx, mut xy, mut yy) = (0.0, 0.0, 0.0);
+        for point in points {
+            let d = *point - mean;
+            xx += d.x * d.x;
+            xy += d.x * d.y;
+            yy += d.y * d.y;
+        }
+        let covariance =
+            Matrix2::from_cols(Vector2::new(xx, xy), Vector2::new(xy, yy)).mul_scalar(1.0 / n);
+        let mut distances = points
+            .iter()
+            .map(|point| point.distance(mean))
+            .collect::<Vec<_>>();
+        distances.sort_by(Scalar::total_cmp);
+        let cep = distances[(distances.len() - 1) / 2];
+        Some(Scatter {
+            count: points.len(),
+            mean,
+            covariance,
+            cep,
+        })
+    }
+
+    /// The ellipse around the mean that contains a fraction of the points, if
+    /// they are normally distributed.
+    pub fn ellipse(&self, probability: Scalar) -> ErrorEllipse {
+        let a = self.covariance.x_axis.x;
+        let b = self.covariance.x_axis.y;
+        let c = self.covariance.y_axis.y;
+        // Eigenvalues of the covariance are the variances along the axes.
+        let mid = 0.5 * (a + c);
+        let spread = (0.25 * (a - c).powi(2) + b * b).sqrt();
+        let scale = (-2.0 * (1.0 - probability.clamp(0.0, 0.9999)).ln()).sqrt();
+        ErrorEllipse {
+            center: self.mean,
+            semi_major: scale * (mid + spread).max(0.0).sqrt(),
+            semi_minor: scale * (mid - spread).max(0.0).sqrt(),
+            orientation: 0.5 * Scalar::atan2(2.0 * b, a - c),
+        }
+    }
+}
+
+/// An ellipse that bounds a fraction of a scatter of points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorEllipse {
+    pub center: Vector2,
+    /// Length (m) of the semi-major axis.
+    pub semi_major: Scalar,
+    /// Length (m) of the semi-minor axis.
+    pub semi_minor: Scalar,
+    /// Angle (rad) from the x axis to the major axis.
+    pub orientation: Scalar,
+}
+
+/// Where things burst and landed during one run.
+#[derive(Resource, Debug, Default)]
+struct FlightEvents {
+    bursts: Vec<Vector>,
+    landings: Vec<Vector>,
+}
+
+fn record_flight_events(
+    mut bursts: EventReader<BalloonBurst>,
+    mut landings: EventReader<PayloadLanded>,
+    mut events: ResMut<FlightEvents>,
+) {
+    events.bursts.extend(bursts.read().map(|burst| burst.position));
+    events.landings.extend(landings.read().map(|landing| landing.position));
+}
@@ -0,0 +1,911 @@
+//! Fit scenario parameters to a recorded flight.
+//!
+//! The drag coefficients, free lift and envelope properties in a scenario are
+//! educated guesses, and a real flight rarely follows them exactly. An
+//! [`Estimation`] tunes a selection of [`Parameter`]s of one balloon until its
+//! simulated trajectory matches a recorded [`Track`] as closely as it can.
+//!
+//! The trajectory error is the sum of squared differences (m²) between the
+//! simulated and recorded positions at every fix of the track: altitude, east
+//! and north. Fix times are measured from the epoch of the scenario, so set
+//! the epoch and launch site to those of the recorded flight first. Each
+//! evaluation of the error is a full headless run in deterministic mode, so
+//! the error is a repeatable function of the parameters.
+//!
+//! The error is minimized with the Nelder-Mead simplex method, which needs no
+//! derivatives. Runs that can be done at the same time are spread across
+//! threads.
+//!
+//! Confidence in the fitted values comes from linearizing the trajectory
+//! around them. With `J` the sensitivity of the residuals to the parameters
+//! (by central differences) and `s² = SSR / (n - p)` the variance of the
+//! residuals, the covariance of the parameters is `s² (JᵀJ)⁻¹`. This assumes
+//! independent, normally distributed residuals, which consecutive GPS fixes
+//! are not, so treat the confidence intervals as a guide rather than a
+//! guarantee. When a parameter barely changes the trajectory (like the leak
+//! rate of a balloon that bursts early), the fit has no standard errors.
+
+use std::{
+    fmt, slice,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{Physics, PhysicsSet, Position},
+};
+use bevy::prelude::*;
+
+use crate::{
+    flight_train::Balloon,
+    headless::HeadlessSim,
+    properties::Properties,
+    replay::Track,
+    scenario::{BalloonScenario, Scenario, ScenarioError},
+    telemetry::TelemetryConfig,
+};
+
+/// Simulated positions are recorded at most this often (s).
+const RECORD_INTERVAL: f64 = 1.0;
+
+/// Runs continue this long (s) after the last fix of the track.
+const DURATION_MARGIN: f64 = 60.0;
+
+/// Two-sided 95% quantile of the normal distribution.
+const Z_95: f64 = 1.959_964;
+
+/// Finite differences for the sensitivities use this fraction of the first
+/// step of the search.
+const DIFFERENCE_FRACTION: f64 = 0.1;
+
+/// A parameter of a balloon that can be fitted to a recorded flight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    /// Drag coefficient of the envelope.
+    DragCoefficient,
+    /// Drag coefficient of the parachute.
+    ParachuteDragCoefficient,
+    /// Free lift (kg) at launch, set through the mass of lift gas.
+    FreeLift,
+    /// Rate (kg/s) that lift gas leaks through the envelope.
+    LeakRate,
+    /// Young's modulus (Pa) of the envelope. Starts from the elasticity of the
+    /// envelope material when the scenario doesn't set one.
+    Elasticity,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 5] = [
+        Parameter::DragCoefficient,
+        Parameter::ParachuteDragCoefficient,
+        Parameter::FreeLift,
+        Parameter::LeakRate,
+        Parameter::Elasticity,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Parameter::DragCoefficient => "drag_coefficient",
+            Parameter::ParachuteDragCoefficient => "parachute_drag_coefficient",
+            Parameter::FreeLift => "free_lift",
+            Parameter::LeakRate => "leak_rate",
+            Parameter::Elasticity => "elasticity",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Parameter::DragCoefficient | Parameter::ParachuteDragCoefficient => "",
+            Parameter::FreeLift => "kg",
+            Parameter::LeakRate => "kg/s",
+            Parameter::Elasticity => "Pa",
+        }
+    }
+
+    /// Value of the parameter in a balloon. `None` if the balloon doesn't have
+    /// it, like the drag coefficient of a missing parachute.
+    pub fn get(&self, balloon: &BalloonScenario, properties: &Properties) -> Option<Scalar> {
+        match self {
+            Parameter::DragCoefficient => Some(balloon.envelope.drag_coefficient),
+            Parameter::ParachuteDragCoefficient => balloon
+                .parachute
+                .as_ref()
+                .map(|parachute| parachute.drag_coefficient),
+            Parameter::FreeLift => balloon.free_lift(properties),
+            Parameter::LeakRate => Some(balloon.lift_gas.leak_rate),
+            Parameter::Elasticity => balloon.envelope.elasticity.or_else(|| {
+                properties
+                    .material(&balloon.envelope.material)
+                    .map(|material| material.elasticity)
+            }),
+        }
+    }
+
+    /// Change the parameter in a balloon.
+    pub fn set(&self, balloon: &mut BalloonScenario, value: Scalar, properties: &Properties) {
+        match self {
+            Parameter::DragCoefficient => balloon.envelope.drag_coefficient = value,
+            Parameter::ParachuteDragCoefficient => {
+                if let Some(parachute) = balloon.parachute.as_mut() {
+                    parachute.drag_coefficient = value;
+                }
+            }
+            Parameter::FreeLift => balloon.set_free_lift(value, properties),
+            Parameter::LeakRate => balloon.lift_gas.leak_rate = value,
+            Parameter::Elasticity => balloon.envelope.elasticity = Some(value),
+        }
+    }
+
+    /// Whether a value makes physical sense.
+    fn is_valid(&self, value: f64) -> bool {
+        match self {
+            Parameter::LeakRate => value >= 0.0,
+            _ => value > 0.0,
+        }
+    }
+
+    /// First step of the search away from a starting value.
+    fn initial_step(&self, value: f64) -> f64 {
+        match self {
+            // About 4 g/h, a slow leak for a latex balloon.
+            Parameter::LeakRate if value == 0.0 => 1e-6,
+            _ if value == 0.0 => 0.1,
+            _ => 0.2 * value.abs(),
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Parameter {
+    type Err = EstimationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parameter::ALL
+            .into_iter()
+            .find(|parameter| parameter.name() == s)
+            .ok_or_else(|| EstimationError::UnknownParameter(s.to_string()))
+    }
+}
+
+/// A fit of balloon parameters to a recorded flight.
+#[derive(Debug, Clone)]
+pub struct Estimation {
+    pub scenario: Scenario,
+    pub track: Track,
+    /// Name of the balloon that flew the track.
+    pub balloon: String,
+    pub parameters: Vec<Parameter>,
+    /// The search stops after this many runs.
+    pub max_runs: usize,
+    /// The search stops when the trajectory errors of the simplex are within
+    /// this fraction of each other.
+    pub tolerance: f64,
+    /// Number of runs to do at the same time.
+    pub threads: usize,
+}
+
+impl Estimation {
+    pub fn new(scenario: Scenario, track: Track, balloon: impl Into<String>) -> Self {
+        Estimation {
+            scenario,
+            track,
+            balloon: balloon.into(),
+            parameters: vec![Parameter::DragCoefficient, Parameter::FreeLift],
+            max_runs: 200,
+            tolerance: 1e-4,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+
+    pub fn with_parameters(self, parameters: Vec<Parameter>) -> Self {
+        Self { parameters, ..self }
+    }
+
+    pub fn with_max_runs(self, max_runs: usize) -> Self {
+        Self { max_runs, ..self }
+    }
+
+    pub fn with_tolerance(self, tolerance: f64) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self { threads, ..self }
+    }
+
+    /// Fit the parameters to the track.
+    pub fn run(&self) -> Result<EstimationResult, EstimationError> {
+        let problem = Problem::new(self)?;
+        let dimensions = self.parameters.len();
+
+        // The search works in steps from the starting values, so that every
+        // parameter moves on the same scale.
+        let simplex = (0..=dimensions)
+            .map(|vertex| {
+                let mut point = vec![0.0; dimensions];
+                if vertex > 0 {
+                    point[vertex - 1] = 1.0;
+                }
+                point
+            })
+            .collect::<Vec<_>>();
+        let errors = problem.errors(&simplex);
+        let initial_error = errors[0];
+        if !initial_error.is_finite() {
+            return Err(EstimationError::NoTrajectory);
+        }
+
+        let fitted = &nelder_mead(
+            simplex,
+            errors,
+            self.tolerance,
+            |points| problem.errors(points),
+            || problem.runs() >= self.max_runs,
+        );
+        let residuals = problem
+            .residuals(fitted)
+            .ok_or(EstimationError::NoTrajectory)?;
+        let standard_errors = problem.standard_errors(fitted, &residuals);
+
+        let mut scenario = self.scenario.clone();
+        problem.apply(fitted, &mut scenario);
+        let parameters = self
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(i, parameter)| FittedParameter {
+                parameter: *parameter,
+                initial: problem.initial[i],
+                value: problem.value(fitted, i),
+                standard_error: standard_errors.as_ref().and_then(|errors| errors[i]),
+            })
+            .collect();
+        // Each fix has three residuals, one for each axis.
+        let count = problem.fixes.len() as f64;
+        Ok(EstimationResult {
+            parameters,
+            initial_rms: (initial_error / count).sqrt(),
+            rms: (sum_of_squares(&residuals) / count).sqrt(),
+            fixes: problem.fixes.len(),
+            runs: problem.runs(),
+            scenario,
+        })
+    }
+}
+
+/// One fitted parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FittedParameter {
+    pub parameter: Parameter,
+    /// Value the search started from.
+    pub initial: f64,
+    pub value: f64,
+    /// Standard error of the value, if the trajectory is sensitive enough to
+    /// the parameter to estimate one.
+    pub standard_error: Option<f64>,
+}
+
+impl FittedParameter {
+    /// Approximate 95% confidence interval of the value.
+    pub fn confidence_interval(&self) -> Option<(f64, f64)> {
+        let half_width = self.standard_error? * Z_95;
+        Some((self.value - half_width, self.value + half_width))
+    }
+}
+
+/// The outcome of fitting parameters to a recorded flight.
+#[derive(Debug, Clone)]
+pub struct EstimationResult {
+    pub parameters: Vec<FittedParameter>,
+    /// Root mean square (m) of the distance between the simulated and
+    /// recorded positions at each fix, before fitting.
+    pub initial_rms: f64,
+    /// Root mean square (m) of the distance between the simulated and
+    /// recorded positions at each fix, with the fitted values.
+    pub rms: f64,
+    /// Number of track fixes compared.
+    pub fixes: usize,
+    /// Number of simulation runs it took.
+    pub runs: usize,
+    /// The scenario with the fitted values.
+    pub scenario: Scenario,
+}
+
+impl fmt::Display for EstimationResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for fitted in &self.parameters {
+            let unit = match fitted.parameter.unit() {
+                "" => String::new(),
+                unit => format!(" {}", unit),
+            };
+            write!(
+                f,
+                "{}: {:.6e}{} (started at {:.6e})",
+                fitted.parameter, fitted.value, unit, fitted.initial
+            )?;
+            match (fitted.standard_error, fitted.confidence_interval()) {
+                (Some(error), Some((low, high))) => writeln!(
+                    f,
+                    ", standard error {:.3e}, 95% interval {:.6e} to {:.6e}",
+                    error, low, high
+                )?,
+                _ => writeln!(f, ", not identifiable from this track")?,
+            }
+        }
+        write!(
+            f,
+            "rms position error {:.1} m (was {:.1} m) over {} fixes in {} runs",
+            self.rms, self.initial_rms, self.fixes, self.runs
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum EstimationError {
+    UnknownBalloon(String),
+    UnknownParameter(String),
+    /// The balloon has nothing for a parameter to change.
+    MissingParameter {
+        balloon: String,
+        parameter: Parameter,
+    },
+    NoParameters,
+    /// No fix of the track is after the epoch of the scenario.
+    NoOverlap,
+    /// The balloon wasn't simulated with the starting values.
+    NoTrajectory,
+    Scenario(ScenarioError),
+}
+
+impl fmt::Display for EstimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EstimationError::UnknownBalloon(name) => write!(f, "no balloon named {}", name),
+            EstimationError::UnknownParameter(name) => write!(
+                f,
+                "unknown parameter {} (expected one of {})",
+                name,
+                Parameter::ALL.map(|parameter| parameter.name()).join(", ")
+            ),
+            EstimationError::MissingParameter { balloon, parameter } => {
+                write!(f, "balloon {} has no {}", balloon, parameter)
+            }
+            EstimationError::NoParameters => write!(f, "no parameters to fit"),
+            EstimationError::NoOverlap => {
+                write!(f, "track has no fixes after the scenario epoch")
+            }
+            EstimationError::NoTrajectory => {
+                write!(f, "balloon was not simulated with the starting values")
+            }
+            EstimationError::Scenario(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EstimationError {}
+
+impl From<ScenarioError> for EstimationError {
+    fn from(e: ScenarioError) -> Self {
+        EstimationError::Scenario(e)
+    }
+}
+
+/// Everything needed to evaluate the trajectory error at a point of the
+/// search.
+struct Problem<'a> {
+    estimation: &'a Estimation,
+    properties: Properties,
+    /// Starting value of each parameter.
+    initial: Vec<f64>,
+    /// First step of the search for each parameter.
+    steps: Vec<f64>,
+    /// Physics time (s) and position (m) of each recorded fix.
+    fixes: Vec<(f64, Vector)>,
+    max_duration: Duration,
+    runs: AtomicUsize,
+}
+
+impl<'a> Problem<'a> {
+    fn new(estimation: &'a Estimation) -> Result<Self, EstimationError> {
+        if estimation.parameters.is_empty() {
+            return Err(EstimationError::NoParameters);
+        }
+        estimation.scenario.validate()?;
+        let properties = estimation.scenario.properties();
+        let balloon = estimation
+            .scenario
+            .balloons
+            .iter()
+            .find(|balloon| balloon.name == estimation.balloon)
+            .ok_or_else(|| EstimationError::UnknownBalloon(estimation.balloon.clone()))?;
+        let initial = estimation
+            .parameters
+            .iter()
+            .map(|parameter| {
+                parameter
+                    .get(balloon, &properties)
+                    .map(f64::from)
+                    .ok_or_else(|| EstimationError::MissingParameter {
+                        balloon: balloon.name.clone(),
+                        parameter: *parameter,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let steps = estimation
+            .parameters
+            .iter()
+            .zip(&initial)
+            .map(|(parameter, value)| parameter.initial_step(*value))
+            .collect();
+
+        let scenario = &estimation.scenario;
+        let fixes = estimation
+            .track
+            .points()
+            .iter()
+            .filter_map(|point| {
+                let time = (point.time - scenario.epoch).num_microseconds()? as f64 * 1e-6;
+                let position = scenario.launch_site.local_position(
+                    point.latitude,
+                    point.longitude,
+                    point.altitude,
+                );
+                (time >= 0.0).then_some((time, position))
+            })
+            .collect::<Vec<_>>();
+        let Some((last_time, _)) = fixes.last() else {
+            return Err(EstimationError::NoOverlap);
+        };
+        let max_duration = Duration::from_secs_f64(last_time + DURATION_MARGIN);
+
+        Ok(Problem {
+            estimation,
+            properties,
+            initial,
+            steps,
+            fixes,
+            max_duration,
+            runs: AtomicUsize::new(0),
+        })
+    }
+
+    fn runs(&self) -> usize {
+        self.runs.load(Ordering::Relaxed)
+    }
+
+    /// Value of a parameter at a point of the search.
+    fn value(&self, point: &[f64], index: usize) -> f64 {
+        self.initial[index] + point[index] * self.steps[index]
+    }
+
+    /// Set the parameters of a scenario to a point of the search.
+    fn apply(&self, point: &[f64], scenario: &mut Scenario) {
+        let Some(balloon) = scenario
+            .balloons
+            .iter_mut()
+            .find(|balloon| balloon.name == self.estimation.balloon)
+        else {
+            return;
+        };
+        for (index, parameter) in self.estimation.parameters.iter().enumerate() {
+            parameter.set(balloon, self.value(point, index) as Scalar, &self.properties);
+        }
+    }
+
+    /// Differences (m) between the simulated and recorded positions at every
+    /// fix, or `None` if the parameters are out of range or the balloon
+    /// wasn't simulated.
+    fn residuals(&self, point: &[f64]) -> Option<Vec<f64>> {
+        let valid = self
+            .estimation
+            .parameters
+            .iter()
+            .enumerate()
+            .all(|(index, parameter)| parameter.is_valid(self.value(point, index)));
+        if !valid {
+            return None;
+        }
+        let mut scenario = self.estimation.scenario.clone();
+        self.apply(point, &mut scenario);
+        scenario.validate().ok()?;
+        // Runs only report the trajectory of the balloon.
+        scenario.outputs.telemetry = TelemetryConfig {
+            sample_rate: 0.0,
+            csv: false,
+            parquet: false,
+        };
+        scenario.replays.clear();
+
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        let mut app = HeadlessSim::batch_app();
+        app.insert_resource(Trajectory {
+            balloon: self.estimation.balloon.clone(),
+            points: Vec::new(),
+        });
+        app.add_systems(
+            FixedPostUpdate,
+            record_trajectory.after(PhysicsSet::StepSimulation),
+        );
+        let mut sim = HeadlessSim::from_app(app)
+            .with_scenario(scenario)
+            .with_max_duration(self.max_duration);
+        sim.run();
+        let trajectory = sim.world_mut().remove_resource::<Trajectory>()?;
+        if trajectory.points.is_empty() {
+            return None;
+        }
+
+        let mut residuals = Vec::with_capacity(3 * self.fixes.len());
+        for (time, recorded) in &self.fixes {
+            let simulated = trajectory.position_at(*time);
+            let difference = simulated - *recorded;
+            residuals.extend([difference.y, difference.x, difference.z].map(f64::from));
+        }
+        Some(residuals)
+    }
+
+    /// Trajectory error (m²) at a point of the search. Infinite if the
+    /// balloon couldn't be simulated there.
+    fn error(&self, point: &[f64]) -> f64 {
+        self.residuals(point)
+            .map_or(f64::INFINITY, |residuals| sum_of_squares(&residuals))
+    }
+
+    /// Trajectory errors at several points, run at the same time.
+    fn errors(&self, points: &[Vec<f64>]) -> Vec<f64> {
+        self.map(points, |point| self.error(point))
+    }
+
+    /// Apply a function to several points, spread across threads.
+    fn map<T: Send>(&self, points: &[Vec<f64>], f: impl Fn(&[f64]) -> T + Sync) -> Vec<T> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(points.len()));
+        thread::scope(|scope| {
+            for _ in 0..self.estimation.threads.clamp(1, points.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= points.len() {
+                        break;
+                    }
+                    let result = f(points[index].as_slice());
+                    results.lock().unwrap().push((index, result));
+                });
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Standard error of each parameter at the fitted point, from the
+    /// sensitivity of the residuals to each parameter. `None` if there are
+    /// too few fixes or a nearby run failed, and `None` for every parameter
+    /// if the trajectory is not sensitive to one of them.
+    fn standard_errors(&self, fitted: &[f64], residuals: &[f64]) -> Option<Vec<Option<f64>>> {
+        let dimensions = fitted.len();
+        let count = residuals.len();
+        if count <= dimensions {
+            return None;
+        }
+
+        let points = (0..dimensions)
+            .flat_map(|i| {
+                [DIFFERENCE_FRACTION, -DIFFERENCE_FRACTION].map(|offset| {
+                    let mut point = fitted.to_vec();
+                    point[i] += offset;
+                    point
+                })
+            })
+            .collect::<Vec<_>>();
+        let differences = self.map(&points, |point| self.residuals(point));
+
+        // Sensitivity of each residual to each parameter, in parameter units.
+        let mut jacobian = vec![vec![0.0; dimensions]; count];
+        for i in 0..dimensions {
+            let (Some(plus), Some(minus)) = (&differences[2 * i], &differences[2 * i + 1]) else {
+                return None;
+            };
+            let step = 2.0 * DIFFERENCE_FRACTION * self.steps[i];
+            for (row, (plus, minus)) in jacobian.iter_mut().zip(plus.iter().zip(minus)) {
+                row[i] = (plus - minus) / step;
+            }
+        }
+
+        let mut normal = vec![vec![0.0; dimensions]; dimensions];
+        for row in &jacobian {
+            for i in 0..dimensions {
+                for j in 0..dimensions {
+                    normal[i][j] += row[i] * row[j];
+                }
+            }
+        }
+        let variance = sum_of_squares(residuals) / (count - dimensions) as f64;
+        let inverse = invert(normal);
+        Some(
+            (0..dimensions)
+                .map(|i| {
+                    let inverse = inverse.as_ref()?;
+                    let covariance = variance * inverse[i][i];
+                    (covariance.is_finite() && covariance >= 0.0).then(|| covariance.sqrt())
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Simulated positions of the balloon being fitted.
+#[derive(Resource)]
+struct Trajectory {
+    balloon: String,
+    /// Physics time (s) and position (m).
+    points: Vec<(f64, Vector)>,
+}
+
+impl Trajectory {
+    /// Position (m) at a physics time, interpolated between recorded points.
+    /// After the run ended, the balloon stays where it was last seen.
+    fn position_at(&self, time: f64) -> Vector {
+        let next = self.points.partition_point(|(point_time, _)| *point_time < time);
+        match (next.checked_sub(1).map(|i| self.points[i]), self.points.get(next)) {
+            (Some((previous_time, previous)), Some((next_time, next))) => {
+                let fraction = (time - previous_time) / (next_time - previous_time);
+                previous.lerp(*next, fraction as Scalar)
+            }
+            (Some((_, previous)), None) => previous,
+            (None, Some((_, next))) => *next,
+            (None, None) => Vector::ZERO,
+        }
+    }
+}
+
+fn record_trajectory(
+    mut trajectory: ResMut<Trajectory>,
+    physics_time: Res<Time<Physics>>,
+    balloons: Query<(&Name, &Position), With<Balloon>>,
+) {
+    let time = physics_time.elapsed_secs_f64();
+    if trajectory
+        .points
+        .last()
+        .is_some_and(|(last_time, _)| time < last_time + RECORD_INTERVAL)
+    {
+        return;
+    }
+    if let Some((_, position)) = balloons
+        .iter()
+        .find(|(name, _)| name.as_str() == trajectory.balloon)
+    {
+        trajectory.points.push((time, position.0));
+    }
+}
+
+/// Minimize a function with the Nelder-Mead simplex method, starting from a
+/// simplex and the values of the function at its vertices. `evaluate` finds
+/// the values at several points at once. The search stops when `exhausted`
+/// says so or when the values at the vertices are within a fraction
+/// `tolerance` of each other. Returns the best vertex.
+fn nelder_mead(
+    mut simplex: Vec<Vec<f64>>,
+    mut errors: Vec<f64>,
+    tolerance: f64,
+    evaluate: impl Fn(&[Vec<f64>]) -> Vec<f64>,
+    exhausted: impl Fn() -> bool,
+) -> Vec<f64> {
+    let dimensions = simplex.len() - 1;
+    loop {
+        let mut order = (0..simplex.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| errors[*a].total_cmp(&errors[*b]));
+        simplex = order.iter().map(|i| simplex[*i].clone()).collect();
+        errors = order.iter().map(|i| errors[*i]).collect();
+
+        let best = errors[0];
+        let worst = errors[dimensions];
+        if exhausted() || worst - best <= tolerance * best.max(f64::MIN_POSITIVE) {
+            break;
+        }
+
+        let centroid = (0..dimensions)
+            .map(|i| simplex[..dimensions].iter().map(|p| p[i]).sum::<f64>() / dimensions as f64)
+            .collect::<Vec<_>>();
+        let toward = |from: &[f64], factor: f64| {
+            centroid
+                .iter()
+                .zip(from)
+                .map(|(c, x)| c + factor * (x - c))
+                .collect::<Vec<_>>()
+        };
+
+        let reflected = toward(&simplex[dimensions], -1.0);
+        let reflected_error = evaluate(slice::from_ref(&reflected))[0];
+        if reflected_error < best {
+            let expanded = toward(&simplex[dimensions], -2.0);
+            let expanded_error = evaluate(slice::from_ref(&expanded))[0];
+            if expanded_error < reflected_error {
+                simplex[dimensions] = expanded;
+                errors[dimensions] = expanded_error;
+            } else {
+                simplex[dimensions] = reflected;
+                errors[dimensions] = reflected_error;
+            }
+            continue;
+        }
+        if reflected_error < errors[dimensions - 1] {
+            simplex[dimensions] = reflected;
+            errors[dimensions] = reflected_error;
+            continue;
+        }
+
+        let contracted = if reflected_error < worst {
+            toward(&reflected, 0.5)
+        } else {
+            toward(&simplex[dimensions], 0.5)
+        };
+        let contracted_error = evaluate(slice::from_ref(&contracted))[0];
+        if contracted_error < reflected_error.min(worst) {
+            simplex[dimensions] = contracted;
+            errors[dimensions] = contracted_error;
+            continue;
+        }
+
+        // Nothing along the line through the worst point helps, so shrink
+        // the simplex toward the best point.
+        let best_point = simplex[0].clone();
+        let shrunk = simplex[1..]
+            .iter()
+            .map(|point| {
+                best_point
+                    .iter()
+                    .zip(point)
+                    .map(|(b, x)| b + 0.5 * (x - b))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let shrunk_errors = evaluate(&shrunk);
+        simplex.truncate(1);
+        simplex.extend(shrunk);
+        errors.truncate(1);
+        errors.extend(shrunk_errors);
+    }
+    simplex.swap_remove(0)
+}
+
+fn sum_of_squares(values: &[f64]) -> f64 {
+    values.iter().map(|value| value * value).sum()
+}
+
+/// Invert a small symmetric matrix by Gauss-Jordan elimination. `None` if it
+/// is singular.
+fn invert(mut matrix: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let scale = (0..n).map(|i| matrix[i][i].abs()).fold(0.0, f64::max);
+    let mut inverse = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|a, b| {
+            matrix[*a][column]
+                .abs()
+                .total_cmp(&matrix[*b][column].abs())
+        })?;
+        if matrix[pivot][column].abs() <= scale * 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        inverse.swap(column, pivot);
+        let divisor = matrix[column][column];
+        for j in 0..n {
+            matrix[column][j] /= divisor;
+            inverse[column][j] /= divisor;
+        }
+        for row in 0..n {
+            if row == column {
+                continue;
+            }
+            let factor = matrix[row][column];
+            for j in 0..n {
+                matrix[row][j] -= factor * matrix[column][j];
+                inverse[row][j] -= factor * inverse[column][j];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn assert_matrix_close(actual: &[Vec<f64>], expected: &[Vec<f64>]) {
+        for (actual_row, expected_row) in actual.iter().zip(expected) {
+            for (a, e) in actual_row.iter().zip(expected_row) {
+                assert!((a - e).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn invert_known_matrix() {
+        let matrix = vec![
+            vec![4.0, 2.0, 0.0],
+            vec![2.0, 5.0, 3.0],
+            vec![0.0, 3.0, 6.0],
+        ];
+        // The determinant is 4·21 - 2·12 = 60.
+        let expected = vec![
+            vec![21.0 / 60.0, -12.0 / 60.0, 6.0 / 60.0],
+            vec![-12.0 / 60.0, 24.0 / 60.0, -12.0 / 60.0],
+            vec![6.0 / 60.0, -12.0 / 60.0, 16.0 / 60.0],
+        ];
+        assert_matrix_close(&invert(matrix).unwrap(), &expected);
+    }
+
+    #[test]
+    fn invert_needs_a_pivot_swap() {
+        let matrix = vec![vec![0.0, 1.0], vec![1.0, 0.0]];
+        assert_matrix_close(&invert(matrix.clone()).unwrap(), &matrix);
+    }
+
+    #[test]
+    fn invert_singular_matrix() {
+        assert_eq!(invert(vec![vec![1.0, 2.0], vec![2.0, 4.0]]), None);
+    }
+
+    #[test]
+    fn trajectory_interpolates_between_points() {
+        let trajectory = Trajectory {
+            balloon: "Balloon".to_string(),
+            points: vec![
+                (1.0, Vector::new(0.0, 100.0, 0.0)),
+                (3.0, Vector::new(10.0, 300.0, -4.0)),
+            ],
+        };
+        assert_eq!(trajectory.position_at(2.0), Vector::new(5.0, 200.0, -2.0));
+        assert_eq!(trajectory.position_at(1.0), Vector::new(0.0, 100.0, 0.0));
+        // Before the first point and after the last, the balloon stays put.
+        assert_eq!(trajectory.position_at(0.0), Vector::new(0.0, 100.0, 0.0));
+        assert_eq!(trajectory.position_at(9.0), Vector::new(10.0, 300.0, -4.0));
+    }
+
+    #[test]
+    fn nelder_mead_finds_the_minimum_of_a_quadratic() {
+        let function = |point: &[f64]| {
+            let (x, y) = (point[0] - 3.0, point[1] + 1.0);
+            // Offset so that the tolerance, relative to the minimum, can be met.
+            1.0 + x * x + 10.0 * y * y + x * y
+        };
+        let evaluations = Cell::new(0);
+        let evaluate = |points: &[Vec<f64>]| -> Vec<f64> {
+            evaluations.set(evaluations.get() + points.len());
+            points.iter().map(|point| function(point)).collect()
+        };
+        let simplex = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        let errors = evaluate(&simplex);
+        let minimum = nelder_mead(simplex, errors, 1e-10, evaluate, || {
+            evaluations.get() >= 1000
+        });
+        assert!(evaluations.get() < 1000, "the search did not converge");
+        assert!((minimum[0] - 3.0).abs() < 1e-3, "minimum at {:?}", minimum);
+        assert!((minimum[1] + 1.0).abs() < 1e-3, "minimum at {:?}", minimum);
+    }
+
+    #[test]
+    fn nelder_mead_stops_when_exhausted() {
+        let simplex = vec![vec![0.0], vec![1.0]];
+        let errors = vec![0.0, 1.0];
+        let minimum = nelder_mead(simplex, errors, 0.0, |_| panic!("evaluated"), || true);
+        assert_eq!(minimum, vec![0.0]);
+    }
+}
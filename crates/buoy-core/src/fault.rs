@@ -0,0 +1,255 @@
+//! Faults are events that make the rest of a run suspect: a body leaves the
+//! atmosphere model, a balloon bursts, the state of a body stops being a
+//! finite number, or a payload hits the ground.
+//!
+//! Each fault is sent as a [`SimFault`] event and kept in the [`Faults`]
+//! resource, so it can be reported after the run. The [`FaultPolicy`] of the
+//! scenario decides what each kind of fault does: stop the simulation in
+//! [`SimState::Faulted`], or just log a warning and carry on.
+//!
+//! ```ron
+//! (
+//!     name: "Example",
+//!     // ...
+//!     faults: (burst: Stop, ground_impact: Log),
+//! )
+//! ```
+
+use std::{mem::discriminant, time::Duration};
+
+use avian3d::{
+    math::Scalar,
+    prelude::{LinearVelocity, Physics, PhysicsSet, Position, RigidBody},
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    mass::kilogram,
+    pressure::pascal,
+    thermodynamic_temperature::kelvin,
+};
+
+use crate::{
+    core::SimState,
+    flight_train::BalloonBurst,
+    ideal_gas::IdealGas,
+    scenario::{PayloadLanded, Scenario},
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_event::<SimFault>();
+    app.init_resource::<Faults>();
+    app.add_systems(
+        FixedPostUpdate,
+        (
+            detect_nan_state,
+            report_bursts,
+            report_ground_impacts,
+            handle_faults,
+        )
+            .chain()
+            .after(PhysicsSet::StepSimulation)
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// Sent when something goes wrong with a simulated body.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct SimFault {
+    pub reason: FaultReason,
+    /// The body at fault.
+    pub entity: Entity,
+    /// Physics time when the fault happened.
+    pub time: Duration,
+}
+
+impl std::fmt::Display for SimFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}) at {:.1} s",
+            self.reason,
+            self.entity,
+            self.time.as_secs_f64()
+        )
+    }
+}
+
+/// What went wrong.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FaultReason {
+    /// A body left the range of altitudes (m) covered by the atmosphere model,
+    /// and the atmosphere is set to fail there with [`OutOfRange::Error`].
+    ///
+    /// [`OutOfRange::Error`]: crate::atmosphere::OutOfRange::Error
+    AtmosphereOutOfBounds { altitude: Scalar },
+    /// A balloon grew past its burst diameter at this altitude (m).
+    Burst { altitude: Scalar },
+    /// The position, velocity or lift gas of a body is no longer finite.
+    NanState,
+    /// A payload hit the ground at this speed (m/s).
+    GroundImpact { speed: Scalar },
+}
+
+impl std::fmt::Display for FaultReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaultReason::AtmosphereOutOfBounds { altitude } => {
+                write!(f, "atmosphere out of bounds at {:.0} m", altitude)
+            }
+            FaultReason::Burst { altitude } => write!(f, "envelope burst at {:.0} m", altitude),
+            FaultReason::NanState => write!(f, "state is not a number"),
+            FaultReason::GroundImpact { speed } => {
+                write!(f, "ground impact at {:.1} m/s", speed)
+            }
+        }
+    }
+}
+
+/// What a fault does to the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FaultAction {
+    /// Stop the simulation in [`SimState::Faulted`].
+    Stop,
+    /// Log a warning and keep running.
+    Log,
+}
+
+/// What each kind of fault does. By default only faults that make the rest of
+/// the run meaningless stop it. Bursts and landings are a normal part of a
+/// flight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultPolicy {
+    pub atmosphere_out_of_bounds: FaultAction,
+    pub burst: FaultAction,
+    pub nan_state: FaultAction,
+    pub ground_impact: FaultAction,
+}
+
+impl Default for FaultPolicy {
+    fn default() -> Self {
+        FaultPolicy {
+            atmosphere_out_of_bounds: FaultAction::Stop,
+            burst: FaultAction::Log,
+            nan_state: FaultAction::Stop,
+            ground_impact: FaultAction::Log,
+        }
+    }
+}
+
+impl FaultPolicy {
+    /// What a fault with this reason does.
+    pub fn action(&self, reason: &FaultReason) -> FaultAction {
+        match reason {
+            FaultReason::AtmosphereOutOfBounds { .. } => self.atmosphere_out_of_bounds,
+            FaultReason::Burst { .. } => self.burst,
+            FaultReason::NanState => self.nan_state,
+            FaultReason::GroundImpact { .. } => self.ground_impact,
+        }
+    }
+}
+
+/// Every fault of the run so far, in the order they happened.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Faults(Vec<SimFault>);
+
+impl Faults {
+    pub fn iter(&self) -> impl Iterator<Item = &SimFault> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether an entity has already had a fault of the same kind as this
+    /// reason. Faults that persist from step to step are only kept once.
+    pub fn has(&self, entity: Entity, reason: &FaultReason) -> bool {
+        self.0.iter().any(|fault| {
+            fault.entity == entity && discriminant(&fault.reason) == discriminant(reason)
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+fn detect_nan_state(
+    physics_time: Res<Time<Physics>>,
+    bodies: Query<(Entity, &Position, &LinearVelocity, Option<&IdealGas>), With<RigidBody>>,
+    mut faults: EventWriter<SimFault>,
+) {
+    for (entity, position, velocity, gas) in bodies.iter() {
+        let gas_finite = gas.is_none_or(|gas| {
+            gas.mass.get::<kilogram>().is_finite()
+                && gas.temperature.get::<kelvin>().is_finite()
+                && gas.pressure.get::<pascal>().is_finite()
+        });
+        if !(position.is_finite() && velocity.is_finite() && gas_finite) {
+            faults.send(SimFault {
+                reason: FaultReason::NanState,
+                entity,
+                time: physics_time.elapsed(),
+            });
+        }
+    }
+}
+
+fn report_bursts(
+    physics_time: Res<Time<Physics>>,
+    mut bursts: EventReader<BalloonBurst>,
+    mut faults: EventWriter<SimFault>,
+) {
+    for burst in bursts.read() {
+        faults.send(SimFault {
+            reason: FaultReason::Burst {
+                altitude: burst.position.y,
+            },
+            entity: burst.entity,
+            time: physics_time.elapsed(),
+        });
+    }
+}
+
+fn report_ground_impacts(
+    physics_time: Res<Time<Physics>>,
+    mut landings: EventReader<PayloadLanded>,
+    velocities: Query<&LinearVelocity>,
+    mut faults: EventWriter<SimFault>,
+) {
+    for landing in landings.read() {
+        let speed = velocities
+            .get(landing.entity)
+            .map_or(0.0, |velocity| velocity.length());
+        faults.send(SimFault {
+            reason: FaultReason::GroundImpact { speed },
+            entity: landing.entity,
+            time: physics_time.elapsed(),
+        });
+    }
+}
+
+/// Keep, log and act on new faults. This runs after the stop conditions, so a
+/// fault wins over a normal stop in the same step.
+fn handle_faults(
+    scenario: Res<Scenario>,
+    mut faults: ResMut<Faults>,
+    mut events: EventReader<SimFault>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    for fault in events.read() {
+        if faults.has(fault.entity, &fault.reason) {
+            continue;
+        }
+        match scenario.faults.action(&fault.reason) {
+            FaultAction::Stop => {
+                error!("fault: {}", fault);
+                next_state.set(SimState::Faulted);
+            }
+            FaultAction::Log => warn!("fault: {}", fault),
+        }
+        faults.0.push(fault.clone());
+    }
+}
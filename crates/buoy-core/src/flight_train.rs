@@ -0,0 +1,685 @@
+//! A flight train is the chain of objects that fly together under a balloon.
+//!
+//! A typical high altitude balloon flight train looks like this:
+//!
+//! ```text
+//!      ( balloon )
+//!           |        <- upper line
+//!      [parachute]
+//!           |        <- rigging line
+//!       [payload]
+//! ```
+//!
+//! Each element is its own rigid body. Adjacent elements are connected by a
+//! rope joint that goes slack when the elements move closer together than the
+//! line length and pulls them together (with a configurable stiffness) when
+//! they move farther apart. This lets us study pendulum motion of the payload
+//! and the tension in the lines.
+//!
+//! A balloon bursts when it grows past its burst diameter. The lift gas escapes
+//! and the remnant of the envelope stays tied to the rest of the train as it
+//! falls.
+//!
+//! The envelope stretches freely by default, so the lift gas is always at the
+//! ambient pressure. An envelope with an [`Elasticity`] squeezes the gas above
+//! the ambient pressure as it stretches. Lift gas can also slowly leak through
+//! the envelope.
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{
+        Collider, DistanceJoint, ExternalForce, GravityScale, Joint, NoAutoMass, Position,
+        RigidBody, Rotation,
+    },
+};
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        reflect::ReflectMapEntities,
+    },
+    math::DVec3,
+    prelude::*,
+};
+use big_space::prelude::*;
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    area::square_meter,
+    force::newton,
+    length::meter,
+    mass::kilogram,
+    mass_rate::kilogram_per_second,
+    pressure::pascal,
+    ratio::ratio,
+    thermodynamic_temperature::kelvin,
+    volume::cubic_meter,
+};
+
+use crate::{
+    atmosphere::Atmosphere,
+    ballast::Ballast,
+    constants::PI,
+    core::SimState,
+    forces::Drag,
+    geometry::{sphere_radius_from_volume, sphere_volume},
+    grid::Precision,
+    ideal_gas::{GasSpecies, IdealGas},
+    material_properties::Skin,
+    quantity::*,
+    vent::VentValve,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Balloon>();
+    app.register_type::<Parachute>();
+    app.register_type::<Payload>();
+    app.register_type::<RiggingLine>();
+    app.register_type::<Shape>();
+    app.register_type::<Skin>();
+    app.add_event::<BalloonBurst>();
+    app.add_systems(
+        FixedUpdate,
+        (update_balloons, update_line_tension)
+            .chain()
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// The lifting element of a flight train. The lift gas is stored alongside
+/// this component as an [`IdealGas`].
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, Serialize, Deserialize)]
+pub struct Balloon {
+    /// Mass of the envelope material, not including the lift gas.
+    pub envelope_mass: Mass,
+    /// Diameter where the envelope bursts. The envelope never bursts if this
+    /// is `None`.
+    pub burst_diameter: Option<Length>,
+    /// Rate that lift gas leaks through the envelope.
+    pub leak_rate: MassRate,
+    /// The envelope stretches freely if this is `None`.
+    pub elasticity: Option<Elasticity>,
+}
+
+/// Diameter (m) of what is left of an envelope after it bursts.
+const REMNANT_DIAMETER: Scalar = 0.5;
+
+/// Bisection steps taken to find the size of an elastic envelope.
+const EQUILIBRIUM_ITERATIONS: usize = 40;
+
+/// How the stretched envelope of a balloon squeezes the gas inside it.
+///
+/// The envelope is a thin sphere of neo-Hookean rubber. The pressure it adds
+/// rises quickly as the envelope starts to stretch, peaks at about 1.38 times
+/// its unstretched radius and then falls off slowly as it keeps growing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Elasticity {
+    /// Young's modulus of the envelope material.
+    pub modulus: Pressure,
+    /// Thickness of the envelope material before it is stretched.
+    pub thickness: Length,
+    /// Radius of the envelope when it is full but not stretched.
+    pub unstretched_radius: Length,
+}
+
+impl Elasticity {
+    /// Pressure of the gas above the ambient pressure when the envelope has
+    /// some radius.
+    pub fn superpressure(&self, radius: Length) -> Pressure {
+        let stretch = (radius / self.unstretched_radius).get::<ratio>();
+        if stretch <= 1.0 {
+            return Pressure::default();
+        }
+        // Rubber is incompressible, so its shear modulus is a third of its
+        // Young's modulus.
+        let shear_modulus = self.modulus / 3.0;
+        shear_modulus * self.thickness / self.unstretched_radius
+            * (2.0 * (1.0 / stretch - stretch.powi(-7)))
+    }
+}
+
+/// Sent when a balloon grows past its burst diameter.
+#[derive(Event, Debug, Clone)]
+pub struct BalloonBurst {
+    pub entity: Entity,
+    /// Position (m) of the balloon when it burst.
+    pub position: Vector,
+}
+
+/// A marker component for a parachute in a flight train.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Parachute;
+
+/// A marker component for the payload at the bottom of a flight train.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Payload;
+
+/// The shape of an element of a flight train. Its collider is built from this
+/// shape.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub enum Shape {
+    Sphere { radius: Scalar },
+    /// A cylinder with its axis pointing up.
+    Cylinder { radius: Scalar, height: Scalar },
+    Cube { size: Scalar },
+}
+
+impl Shape {
+    pub fn collider(&self) -> Collider {
+        match *self {
+            Shape::Sphere { radius } => Collider::sphere(radius),
+            Shape::Cylinder { radius, height } => Collider::cylinder(radius, height),
+            Shape::Cube { size } => Collider::cuboid(size, size, size),
+        }
+    }
+}
+
+/// A line that connects two elements of a flight train. This component lives
+/// on the same entity as the joint that models the line.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct RiggingLine {
+    /// Unstretched length (m) of the line.
+    pub length: Scalar,
+    /// Axial stiffness (N/m) of the line.
+    pub stiffness: Scalar,
+    /// Tension (N) in the line. Zero when the line is slack.
+    pub tension: Scalar,
+    /// The element above the line and where the line is tied to it, in the
+    /// element's local coordinates.
+    pub upper: (Entity, Vector),
+    /// The element below the line and where the line is tied to it, in the
+    /// element's local coordinates.
+    pub lower: (Entity, Vector),
+}
+
+impl RiggingLine {
+    pub fn tension(&self) -> Force {
+        Force::new::<newton>(self.tension)
+    }
+
+    /// The rope joint that models this line.
+    pub fn joint(&self) -> DistanceJoint {
+        DistanceJoint::new(self.upper.0, self.lower.0)
+            .with_local_anchor_1(self.upper.1)
+            .with_local_anchor_2(self.lower.1)
+            .with_limits(0.0, self.length)
+            .with_compliance(1.0 / self.stiffness)
+    }
+}
+
+impl MapEntities for RiggingLine {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.upper.0 = entity_mapper.map_entity(self.upper.0);
+        self.lower.0 = entity_mapper.map_entity(self.lower.0);
+    }
+}
+
+/// A balloon filled with lift gas.
+#[derive(Debug, Clone)]
+pub struct BalloonConfig {
+    pub envelope_mass: Mass,
+    /// Diameter where the envelope bursts, if it ever does.
+    pub burst_diameter: Option<Length>,
+    pub drag_coefficient: Scalar,
+    pub lift_gas: IdealGas,
+    /// Rate that lift gas leaks through the envelope.
+    pub leak_rate: MassRate,
+    pub elasticity: Option<Elasticity>,
+    /// Valve for venting lift gas from the top of the envelope.
+    pub vent_valve: Option<VentValve>,
+}
+
+impl Default for BalloonConfig {
+    fn default() -> Self {
+        BalloonConfig {
+            envelope_mass: Mass::new::<kilogram>(1.2),
+            burst_diameter: None,
+            drag_coefficient: 0.3,
+            lift_gas: IdealGas::new(
+                GasSpecies::helium(),
+                ThermodynamicTemperature::new::<kelvin>(288.15),
+                Pressure::new::<pascal>(101325.0),
+                Mass::new::<kilogram>(0.6),
+            ),
+            leak_rate: MassRate::default(),
+            elasticity: None,
+            vent_valve: None,
+        }
+    }
+}
+
+/// A parachute that hangs below the balloon.
+#[derive(Debug, Clone)]
+pub struct ParachuteConfig {
+    pub mass: Mass,
+    /// Diameter of the canopy when it is fully inflated.
+    pub diameter: Length,
+    pub drag_coefficient: Scalar,
+}
+
+impl Default for ParachuteConfig {
+    fn default() -> Self {
+        ParachuteConfig {
+            mass: Mass::new::<kilogram>(0.1),
+            diameter: Length::new::<meter>(1.2),
+            drag_coefficient: 1.5,
+        }
+    }
+}
+
+/// The payload at the bottom of the flight train, modeled as a box.
+#[derive(Debug, Clone)]
+pub struct PayloadConfig {
+    pub mass: Mass,
+    /// Edge length of the payload box.
+    pub size: Length,
+    pub drag_coefficient: Scalar,
+    /// Ballast carried by the payload, in addition to `mass`.
+    pub ballast: Option<Ballast>,
+}
+
+impl Default for PayloadConfig {
+    fn default() -> Self {
+        PayloadConfig {
+            mass: Mass::new::<kilogram>(1.0),
+            size: Length::new::<meter>(0.3),
+            drag_coefficient: 1.05,
+            ballast: None,
+        }
+    }
+}
+
+/// A line that connects two elements of the flight train.
+#[derive(Debug, Clone, Copy)]
+pub struct LineConfig {
+    pub length: Length,
+    /// Axial stiffness (N/m) of the line. Higher values are stiffer.
+    pub stiffness: Scalar,
+}
+
+impl Default for LineConfig {
+    fn default() -> Self {
+        LineConfig {
+            length: Length::new::<meter>(5.0),
+            stiffness: 1.0e4,
+        }
+    }
+}
+
+/// Builder for a flight train. Elements are spawned top to bottom, starting
+/// with the balloon at the given position.
+#[derive(Debug, Clone)]
+pub struct FlightTrain {
+    pub balloon: BalloonConfig,
+    pub parachute: Option<ParachuteConfig>,
+    pub payload: PayloadConfig,
+    /// Line between the balloon and the parachute. This line connects the
+    /// balloon to the payload directly if there is no parachute.
+    pub upper_line: LineConfig,
+    /// Line between the parachute and the payload.
+    pub rigging_line: LineConfig,
+}
+
+impl Default for FlightTrain {
+    fn default() -> Self {
+        FlightTrain {
+            balloon: BalloonConfig::default(),
+            parachute: Some(ParachuteConfig::default()),
+            payload: PayloadConfig::default(),
+            upper_line: LineConfig::default(),
+            rigging_line: LineConfig::default(),
+        }
+    }
+}
+
+/// Entities that make up a spawned flight train.
+#[derive(Debug, Clone)]
+pub struct FlightTrainEntities {
+    pub balloon: Entity,
+    pub parachute: Option<Entity>,
+    pub payload: Entity,
+    /// Joint entities, top to bottom.
+    pub lines: Vec<Entity>,
+}
+
+impl FlightTrain {
+    pub fn with_balloon(self, balloon: BalloonConfig) -> Self {
+        Self { balloon, ..self }
+    }
+
+    pub fn with_parachute(self, parachute: ParachuteConfig) -> Self {
+        Self {
+            parachute: Some(parachute),
+            ..self
+        }
+    }
+
+    pub fn without_parachute(self) -> Self {
+        Self {
+            parachute: None,
+            ..self
+        }
+    }
+
+    pub fn with_payload(self, payload: PayloadConfig) -> Self {
+        Self { payload, ..self }
+    }
+
+    pub fn with_vent_valve(mut self, vent_valve: VentValve) -> Self {
+        self.balloon.vent_valve = Some(vent_valve);
+        self
+    }
+
+    pub fn with_ballast(mut self, ballast: Ballast) -> Self {
+        self.payload.ballast = Some(ballast);
+        self
+    }
+
+    pub fn with_upper_line(self, upper_line: LineConfig) -> Self {
+        Self { upper_line, ..self }
+    }
+
+    pub fn with_rigging_line(self, rigging_line: LineConfig) -> Self {
+        Self {
+            rigging_line,
+            ..self
+        }
+    }
+
+    /// Distance from the center of the balloon to the bottom of the payload
+    /// when every line is pulled taut.
+    pub fn height(&self) -> Length {
+        let balloon_radius = Length::new::<meter>(sphere_radius_from_volume(
+            self.balloon.lift_gas.volume().get::<cubic_meter>(),
+        ));
+        let parachute = self.parachute.as_ref().map_or(Length::default(), |parachute| {
+            parachute.diameter + self.rigging_line.length
+        });
+        balloon_radius + self.upper_line.length + parachute + self.payload.size
+    }
+
+    /// Spawn every element of the flight train as a child of the grid. The
+    /// balloon is placed at `position` and the rest of the train hangs
+    /// straight down from it with every line pulled taut.
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        grid_entity: Entity,
+        grid: &Grid<Precision>,
+        position: DVec3,
+    ) -> FlightTrainEntities {
+        let balloon_radius = sphere_radius_from_volume(
+            self.balloon.lift_gas.volume().get::<cubic_meter>(),
+        );
+        let balloon_mass = self.balloon.envelope_mass + self.balloon.lift_gas.mass;
+        let balloon = spawn_element(
+            commands,
+            grid_entity,
+            grid,
+            position,
+            (
+                Name::new("Balloon"),
+                Balloon {
+                    envelope_mass: self.balloon.envelope_mass,
+                    burst_diameter: self.balloon.burst_diameter,
+                    leak_rate: self.balloon.leak_rate,
+                    elasticity: self.balloon.elasticity,
+                },
+                self.balloon.lift_gas.clone(),
+                Drag::new(
+                    self.balloon.drag_coefficient,
+                    Area::new::<square_meter>(PI * balloon_radius * balloon_radius),
+                ),
+            ),
+            Shape::Sphere {
+                radius: balloon_radius,
+            },
+            balloon_mass,
+        );
+        if let Some(vent_valve) = &self.balloon.vent_valve {
+            commands.entity(balloon).insert(vent_valve.clone());
+        }
+
+        let mut lines = Vec::new();
+        let mut upper = (balloon, Vector::NEG_Y * balloon_radius as Scalar);
+        let mut cursor = position.y - balloon_radius as f64;
+        let mut next_line = self.upper_line;
+
+        let parachute = self.parachute.as_ref().map(|parachute| {
+            let canopy_radius = parachute.diameter.get::<meter>() / 2.0;
+            cursor -= (next_line.length.get::<meter>() + canopy_radius) as f64;
+            let entity = spawn_element(
+                commands,
+                grid_entity,
+                grid,
+                DVec3::new(position.x, cursor, position.z),
+                (
+                    Name::new("Parachute"),
+                    Parachute,
+                    Drag::new(
+                        parachute.drag_coefficient,
+                        Area::new::<square_meter>(PI * canopy_radius * canopy_radius),
+                    ),
+                ),
+                Shape::Cylinder {
+                    radius: canopy_radius,
+                    height: 0.05 * canopy_radius,
+                },
+                parachute.mass,
+            );
+            lines.push(spawn_line(
+                commands,
+                upper,
+                (entity, Vector::Y * canopy_radius as Scalar),
+                next_line,
+            ));
+            cursor -= canopy_radius as f64;
+            upper = (entity, Vector::NEG_Y * canopy_radius as Scalar);
+            next_line = self.rigging_line;
+            entity
+        });
+
+        let half_size = self.payload.size.get::<meter>() / 2.0;
+        cursor -= (next_line.length.get::<meter>() + half_size) as f64;
+        let payload = spawn_element(
+            commands,
+            grid_entity,
+            grid,
+            DVec3::new(position.x, cursor, position.z),
+            (
+                Name::new("Payload"),
+                Payload,
+                Drag::new(
+                    self.payload.drag_coefficient,
+                    self.payload.size * self.payload.size,
+                ),
+            ),
+            Shape::Cube {
+                size: self.payload.size.get::<meter>(),
+            },
+            self.payload.mass
+                + self
+                    .payload
+                    .ballast
+                    .as_ref()
+                    .map_or(Mass::default(), |ballast| ballast.mass),
+        );
+        if let Some(ballast) = &self.payload.ballast {
+            commands.entity(payload).insert(ballast.clone());
+        }
+        lines.push(spawn_line(
+            commands,
+            upper,
+            (payload, Vector::Y * half_size as Scalar),
+            next_line,
+        ));
+
+        FlightTrainEntities {
+            balloon,
+            parachute,
+            payload,
+            lines,
+        }
+    }
+}
+
+/// Spawn a single dynamic rigid body of the flight train in the grid.
+fn spawn_element(
+    commands: &mut Commands,
+    grid_entity: Entity,
+    grid: &Grid<Precision>,
+    position: DVec3,
+    bundle: impl Bundle,
+    shape: Shape,
+    mass: Mass,
+) -> Entity {
+    let (cell, translation) = grid.translation_to_grid(position);
+    commands
+        .spawn((
+            bundle,
+            shape,
+            shape.collider(),
+            RigidBody::Dynamic,
+            // The mass is set explicitly so that colliders only describe the
+            // shape of each element.
+            avian3d::prelude::Mass(mass.get::<kilogram>()),
+            NoAutoMass,
+            GravityScale(1.0),
+            ExternalForce::default().with_persistence(false),
+            cell,
+            Transform::from_translation(translation),
+        ))
+        .set_parent(grid_entity)
+        .id()
+}
+
+/// Spawn a rope joint between two elements. Each element is given as an
+/// entity and the local anchor point where the line attaches to it.
+fn spawn_line(
+    commands: &mut Commands,
+    upper: (Entity, Vector),
+    lower: (Entity, Vector),
+    line: LineConfig,
+) -> Entity {
+    let rigging_line = RiggingLine {
+        length: line.length.get::<meter>() as Scalar,
+        stiffness: line.stiffness,
+        tension: 0.0,
+        upper,
+        lower,
+    };
+    commands
+        .spawn((
+            Name::new("Rigging Line"),
+            rigging_line,
+            rigging_line.joint(),
+        ))
+        .id()
+}
+
+/// Set the pressure of a gas so that it balances the ambient pressure plus the
+/// superpressure of an elastic envelope around it. Returns the radius (m) of
+/// the envelope.
+fn inflate(
+    gas: &mut IdealGas,
+    ambient_pressure: Pressure,
+    elasticity: Option<&Elasticity>,
+) -> Scalar {
+    gas.pressure = ambient_pressure;
+    let free_radius = sphere_radius_from_volume(gas.volume().get::<cubic_meter>());
+    let Some(elasticity) = elasticity else {
+        return free_radius;
+    };
+    // The superpressure can only shrink the envelope, so its radius is
+    // somewhere below the radius it would have without it.
+    let (mut low, mut high) = (0.0, free_radius);
+    for _ in 0..EQUILIBRIUM_ITERATIONS {
+        let radius = 0.5 * (low + high);
+        gas.pressure = ambient_pressure + elasticity.superpressure(Length::new::<meter>(radius));
+        if gas.volume().get::<cubic_meter>() > sphere_volume(radius) {
+            low = radius;
+        } else {
+            high = radius;
+        }
+    }
+    let radius = 0.5 * (low + high);
+    gas.pressure = ambient_pressure + elasticity.superpressure(Length::new::<meter>(radius));
+    radius
+}
+
+/// Keep the balloon's lift gas in equilibrium with the ambient atmosphere and
+/// resize the envelope to match the gas volume. Burst the envelope if it grows
+/// too big.
+fn update_balloons(
+    mut commands: Commands,
+    mut balloons: Query<(
+        Entity,
+        &Balloon,
+        &mut IdealGas,
+        &mut Shape,
+        &mut Collider,
+        &mut Drag,
+        &mut avian3d::prelude::Mass,
+        &Position,
+    )>,
+    atmosphere: Res<Atmosphere>,
+    time: Res<bevy::prelude::Time>,
+    mut bursts: EventWriter<BalloonBurst>,
+) {
+    for (entity, balloon, mut gas, mut shape, mut collider, mut drag, mut mass, position) in
+        balloons.iter_mut()
+    {
+        let leaked = Mass::new::<kilogram>(
+            balloon.leak_rate.get::<kilogram_per_second>() * time.delta_secs_f64() as Scalar,
+        );
+        gas.mass = (gas.mass - leaked).max(Mass::default());
+        gas.temperature = atmosphere.temperature(position.0);
+        let mut radius = inflate(
+            &mut gas,
+            atmosphere.pressure(position.0),
+            balloon.elasticity.as_ref(),
+        );
+        let burst = balloon
+            .burst_diameter
+            .is_some_and(|burst_diameter| 2.0 * radius >= burst_diameter.get::<meter>());
+        if burst {
+            info!("balloon burst at {:.0} m", position.y);
+            bursts.send(BalloonBurst {
+                entity,
+                position: position.0,
+            });
+            // The lift gas escapes, so the balloon no longer floats.
+            commands.entity(entity).remove::<(IdealGas, VentValve)>();
+            radius = REMNANT_DIAMETER / 2.0;
+        }
+        *shape = Shape::Sphere { radius };
+        *collider = shape.collider();
+        drag.area = Area::new::<square_meter>(PI * radius * radius);
+        mass.0 = if burst {
+            balloon.envelope_mass.get::<kilogram>()
+        } else {
+            (balloon.envelope_mass + gas.mass).get::<kilogram>()
+        };
+    }
+}
+
+/// Compute the tension in each line from how far it is stretched.
+fn update_line_tension(
+    mut lines: Query<(&mut RiggingLine, &DistanceJoint)>,
+    bodies: Query<(&Position, &Rotation)>,
+) {
+    for (mut line, joint) in lines.iter_mut() {
+        let Ok([(position1, rotation1), (position2, rotation2)]) =
+            bodies.get_many([joint.entity1, joint.entity2])
+        else {
+            continue;
+        };
+        let anchor1 = position1.0 + rotation1.0 * joint.local_anchor1;
+        let anchor2 = position2.0 + rotation2.0 * joint.local_anchor2;
+        let stretch = anchor1.distance(anchor2) - line.length;
+        line.tension = (line.stiffness * stretch).max(0.0);
+    }
+}
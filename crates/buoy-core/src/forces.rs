@@ -1,8 +1,12 @@
 //! Forces applied to rigid bodies.
-use avian3d::{math::Scalar, prelude::*};
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::*,
+};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use uom::si::{
-    acceleration::meter_per_second_squared, area::square_meter, f32::*, length::meter,
+    acceleration::meter_per_second_squared, area::square_meter, length::meter,
     mass_density::kilogram_per_cubic_meter, ratio::ratio, volume::cubic_meter,
 };
 
@@ -10,21 +14,43 @@ use crate::{
     atmosphere::Atmosphere,
     constants::{EARTH_RADIUS_M, STANDARD_GRAVITY},
     core::SimState,
+    flight_train::Shape,
+    ideal_gas::IdealGas,
+    quantity::*,
+    turbulence::Gust,
+    water::WaterBody,
+    wind::Wind,
 };
 
 pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Drag>();
     app.insert_resource(Gravity(
-        Vec3::NEG_Y * STANDARD_GRAVITY.get::<meter_per_second_squared>(),
+        Vector::NEG_Y * STANDARD_GRAVITY.get::<meter_per_second_squared>(),
     ));
     app.add_systems(
         FixedUpdate,
-        (update_gravity)
+        (update_gravity, apply_buoyancy, apply_drag)
             .chain()
             .in_set(PhysicsStepSet::First)
             .run_if(in_state(SimState::Running)),
     );
 }
 
+/// Aerodynamic properties of a body that moves through the atmosphere.
+#[derive(Component, Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, Serialize, Deserialize)]
+pub struct Drag {
+    pub coefficient: Scalar,
+    /// Reference area (m²) that the drag coefficient is normalized to.
+    pub area: Area,
+}
+
+impl Drag {
+    pub fn new(coefficient: Scalar, area: Area) -> Self {
+        Drag { coefficient, area }
+    }
+}
+
 /// Fraction of standard gravity at an altitude (m) above mean sea level.
 pub fn scale_gravity(altitude_meters: Scalar) -> Scalar {
     let scale = *EARTH_RADIUS_M / (*EARTH_RADIUS_M + Length::new::<meter>(altitude_meters));
@@ -33,11 +59,11 @@ pub fn scale_gravity(altitude_meters: Scalar) -> Scalar {
 
 /// Force (N) due to drag as a solid body moves through a fluid.
 pub fn drag(
-    velocity: Vec3,
+    velocity: Vector,
     ambient_density: MassDensity,
     drag_area: Area,
     drag_coefficient: Scalar,
-) -> Vec3 {
+) -> Vector {
     -0.5 * drag_coefficient
         * ambient_density.get::<kilogram_per_cubic_meter>()
         * drag_area.get::<square_meter>()
@@ -51,8 +77,8 @@ pub fn buoyancy(
     gravity_acceleration: Acceleration,
     displaced_volume: Volume,
     ambient_density: MassDensity,
-) -> Vec3 {
-    Vec3::Y
+) -> Vector {
+    Vector::Y
         * (displaced_volume.get::<cubic_meter>()
             * ambient_density.get::<kilogram_per_cubic_meter>()
             * gravity_acceleration.get::<meter_per_second_squared>())
@@ -64,16 +90,51 @@ fn update_gravity(mut query: Query<(&mut GravityScale, &Position)>) {
     }
 }
 
-// fn apply_buoyancy(
-//     mut query: Query<(&mut ExternalForce, &Position, &GravityScale, &Collider)>,
-//     atmosphere: Res<Atmosphere>,
-//     gravity: Res<Gravity>,
-// ) -> Vec3 {
-//     for (mut external_force, position, gravity_scale, collider) in query.iter_mut() {
-//         let gravity_acceleration = gravity.0 * gravity_scale.0;
-//         let ambient_density = atmosphere.density(position.y);
-//         let displaced_volume = collider.volume();
-//         let buoyancy_force = buoyancy(gravity_acceleration, displaced_volume, ambient_density);
-//         external_force.apply_force(buoyancy_force);
-//     }
-// }
+/// Apply buoyancy to bodies that contain a volume of gas.
+fn apply_buoyancy(
+    mut bodies: Query<(&mut ExternalForce, &Position, &GravityScale, &IdealGas)>,
+    atmosphere: Res<Atmosphere>,
+    gravity: Res<Gravity>,
+) {
+    for (mut external_force, position, gravity_scale, gas) in bodies.iter_mut() {
+        let gravity_acceleration =
+            Acceleration::new::<meter_per_second_squared>(gravity.0.length() * gravity_scale.0);
+        let ambient_density = atmosphere.density(position.0);
+        external_force.apply_force(buoyancy(gravity_acceleration, gas.volume(), ambient_density));
+    }
+}
+
+/// Apply aerodynamic drag to bodies as they move through the atmosphere.
+/// Drag acts on the velocity of each body relative to the wind and its gusts,
+/// on the part of the body that is out of the water.
+fn apply_drag(
+    mut bodies: Query<(
+        &mut ExternalForce,
+        &Position,
+        &LinearVelocity,
+        &Drag,
+        Option<&Gust>,
+        Option<&Shape>,
+    )>,
+    water: Query<&WaterBody>,
+    atmosphere: Res<Atmosphere>,
+    wind: Res<Wind>,
+) {
+    for (mut external_force, position, velocity, body, gust, shape) in bodies.iter_mut() {
+        let ambient_density = atmosphere.density(position.0);
+        let air_velocity =
+            wind.velocity(position.0) + gust.map_or(Vector::ZERO, |gust| gust.velocity);
+        let submerged = shape.map_or(0.0, |shape| {
+            water
+                .iter()
+                .map(|water| water.submerged_fraction(shape, position.0))
+                .fold(0.0, Scalar::max)
+        });
+        external_force.apply_force(drag(
+            velocity.0 - air_velocity,
+            ambient_density,
+            body.area * (1.0 - submerged),
+            body.coefficient,
+        ));
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::Display;
+use avian3d::math::Scalar;
 use bevy::prelude::*;
-use uom::si::{f32::*, Quantity};
+use uom::si::Quantity;
+
+use crate::quantity::*;
 
 pub(crate) fn plugin(app: &mut App) {
     app.register_type::<UomQuantity>();
@@ -8,7 +11,7 @@ pub(crate) fn plugin(app: &mut App) {
 
 #[derive(Component, Debug, Reflect)]
 pub struct UomQuantity {
-    value: f32,
+    value: Scalar,
     unit: String,
 }
 
@@ -17,7 +20,7 @@ impl UomQuantity {
     where
         D: uom::si::Dimension + ?Sized,
         U: uom::si::Units<V> + ?Sized + uom::si::Unit,
-        V: uom::num::Num + uom::Conversion<V> + Into<f32> + Clone,
+        V: uom::num::Num + uom::Conversion<V> + Into<Scalar> + Clone,
     {
         Self {
             value: quantity.value.clone().into(),
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use big_space::prelude::*;
-use uom::si::{f32::*, length::meter, volume::cubic_meter};
+use uom::si::{length::meter, volume::cubic_meter};
+
+use crate::quantity::*;
 
 /// The size of the grid cells in space.
 pub const GRID_CELL_EDGE_LENGTH_METERS: f32 = 10.0;
@@ -42,11 +44,7 @@ pub(crate) fn plugin(app: &mut App) {
 /// With threshold = 0.0, the object triggers an immediate cell switch when crossing
 /// the boundary. A positive threshold allows some movement past the boundary before
 /// switching, preventing jitter for objects that frequently cross cell edges.
-fn setup_worldspace(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
+fn setup_worldspace(mut commands: Commands) {
     // Spawn the root node of the grid hierarchy. This is the grid that
     // contains the entire world.
     let world_grid = Grid::<Precision>::new(
@@ -76,6 +74,7 @@ pub struct StartingSpot;
 #[derive(Component)]
 pub struct RootGrid;
 
-/// A marker component for a grid that contains a fluid volume.
+/// A marker component for a grid that contains a fluid volume, like a
+/// [`WaterBody`](crate::water::WaterBody).
 #[derive(Component)]
 pub struct FluidVolumeGrid;
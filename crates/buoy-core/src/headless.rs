@@ -0,0 +1,235 @@
+//! Run the simulation without a window or renderer.
+//!
+//! A headless simulation is stepped by hand instead of by an event loop. Each
+//! update advances time by exactly one fixed timestep, so the simulation runs
+//! as fast as the CPU allows rather than in real time.
+
+use std::time::Duration;
+
+use avian3d::prelude::Physics;
+use bevy::{
+    app::{PluginGroupBuilder, PluginsState},
+    asset::AssetPlugin,
+    hierarchy::HierarchyPlugin,
+    log::LogPlugin,
+    prelude::*,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+};
+
+use crate::{
+    core::{BuoyPlugin, SimState},
+    fault::{Faults, SimFault},
+    scenario::Scenario,
+    snapshot::{Snapshot, SnapshotError},
+};
+
+/// The plugins needed to run the simulation without a window or renderer.
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add_group(MinimalPlugins)
+            .add(LogPlugin::default())
+            .add(StatesPlugin)
+            .add(HierarchyPlugin)
+            .add(TransformPlugin)
+            .add(AssetPlugin::default())
+            .add(MeshAssetPlugin)
+            .add(BuoyPlugin::default())
+    }
+}
+
+/// Colliders can be built from meshes, so the physics engine expects mesh
+/// assets to exist even though nothing is rendered.
+struct MeshAssetPlugin;
+
+impl Plugin for MeshAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Mesh>();
+    }
+}
+
+/// How a headless run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimOutcome {
+    /// State of the simulation when the run ended.
+    pub state: SimState,
+    /// Physics time elapsed during the run.
+    pub elapsed: Duration,
+    /// Number of fixed steps that were run.
+    pub steps: u64,
+}
+
+impl SimOutcome {
+    /// Process exit code that reflects the final state of the simulation.
+    pub fn exit_code(&self) -> u8 {
+        match self.state {
+            SimState::Stopped => 0,
+            SimState::Faulted => 1,
+            // The run was cut short before the simulation stopped on its own.
+            SimState::Running => 2,
+        }
+    }
+}
+
+/// A simulation that runs without a window or renderer.
+pub struct HeadlessSim {
+    app: App,
+    /// The run stops when this much physics time has elapsed.
+    pub max_duration: Duration,
+}
+
+impl Default for HeadlessSim {
+    fn default() -> Self {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugins);
+        HeadlessSim::from_app(app)
+    }
+}
+
+impl HeadlessSim {
+    /// A simulation that gives bit-identical results every time it is run
+    /// with the same scenario. See [`crate::determinism`].
+    pub fn deterministic() -> Self {
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugins.build().set(BuoyPlugin::deterministic()));
+        HeadlessSim::from_app(app)
+    }
+
+    /// An app for one of many deterministic runs in the same process, like
+    /// the runs of an ensemble. It doesn't log, since only one app in the
+    /// process can own the logger. Add any systems needed to collect results,
+    /// then wrap it with [`HeadlessSim::from_app`].
+    pub fn batch_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(
+            HeadlessPlugins
+                .build()
+                .disable::<LogPlugin>()
+                .set(BuoyPlugin::deterministic()),
+        );
+        app
+    }
+
+    /// Wrap an app that already has [`HeadlessPlugins`] (or equivalent).
+    pub fn from_app(mut app: App) -> Self {
+        // Advance time by exactly one fixed step per update.
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        HeadlessSim {
+            app,
+            max_duration: Duration::from_secs(4 * 60 * 60),
+        }
+    }
+
+    pub fn with_max_duration(self, max_duration: Duration) -> Self {
+        Self {
+            max_duration,
+            ..self
+        }
+    }
+
+    /// Set up the simulation from a scenario instead of the default one.
+    pub fn with_scenario(mut self, scenario: Scenario) -> Self {
+        self.app.insert_resource(scenario);
+        self
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Current state of the simulation.
+    pub fn state(&self) -> SimState {
+        *self.world().resource::<State<SimState>>().get()
+    }
+
+    /// Faults of the run so far. A run that ends in [`SimState::Faulted`]
+    /// has at least one.
+    pub fn faults(&self) -> impl Iterator<Item = &SimFault> {
+        self.world().resource::<Faults>().iter()
+    }
+
+    /// Physics time elapsed so far.
+    pub fn elapsed(&self) -> Duration {
+        self.world().resource::<Time<Physics>>().elapsed()
+    }
+
+    /// Capture the current state of the simulation.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::capture(self.world())
+    }
+
+    /// Replace the state of the simulation with a snapshot. The scenario is
+    /// set up first if the simulation hasn't started yet.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        self.start();
+        snapshot.restore(self.world_mut())?;
+        Ok(())
+    }
+
+    /// Run the simulation until it stops or faults, or until the maximum
+    /// duration has elapsed.
+    pub fn run(&mut self) -> SimOutcome {
+        self.finish_plugins();
+        let mut steps = 0;
+        loop {
+            self.app.update();
+            steps += 1;
+            let state = self.state();
+            if state != SimState::Running {
+                info!("simulation {:?} after {:?}", state, self.elapsed());
+                break;
+            }
+            if self.elapsed() >= self.max_duration {
+                info!("simulation reached its maximum duration {:?}", self.max_duration);
+                break;
+            }
+            if self.app.should_exit().is_some() {
+                break;
+            }
+        }
+        SimOutcome {
+            state: self.state(),
+            elapsed: self.elapsed(),
+            steps,
+        }
+    }
+
+    /// Run the startup schedules without stepping physics. The first update
+    /// has no time delta, so no fixed steps run during it.
+    fn start(&mut self) {
+        self.finish_plugins();
+        if self.world().resource::<Time<Real>>().first_update().is_none() {
+            self.app.update();
+        }
+    }
+
+    /// Plugins may finish building asynchronously. Wait for them, then
+    /// finalize the app, like [`App::run`] does before its first update.
+    fn finish_plugins(&mut self) {
+        if self.app.plugins_state() == PluginsState::Cleaned {
+            return;
+        }
+        while self.app.plugins_state() == PluginsState::Adding {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        self.app.finish();
+        self.app.cleanup();
+    }
+}
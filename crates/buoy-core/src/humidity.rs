@@ -0,0 +1,156 @@
+//! Water vapor in the atmosphere.
+//!
+//! Humid air is lighter than dry air at the same temperature and pressure,
+//! because a molecule of water is lighter than the average molecule of air.
+//! Near the ground this changes the density by a few percent, which shows up
+//! in the free lift of a balloon at launch. The density of moist air is the
+//! density of dry air at the virtual temperature: the temperature dry air
+//! would need to have the same density at the same pressure.
+//!
+//! Humidity is either a standard profile, where the vapor pressure falls off
+//! exponentially with altitude, or a sounding of dewpoints and relative
+//! humidities at a few altitudes:
+//!
+//! ```ron
+//! humidity: Sounding([
+//!     (altitude: 1600.0, moisture: Dewpoint(275.0)),
+//!     (altitude: 5000.0, moisture: RelativeHumidity(0.3)),
+//! ]),
+//! ```
+//!
+//! Between the levels of a sounding the vapor pressure is interpolated
+//! linearly. Above the top level and below the bottom level it is the same as
+//! at that level. The air never holds more vapor than it can at saturation.
+//!
+//! Saturation vapor pressure is over liquid water, from the Magnus formula
+//! with the coefficients of Alduchov and Eskridge (1996).
+
+use avian3d::math::Scalar;
+use serde::{Deserialize, Serialize};
+use uom::si::{pressure::pascal, thermodynamic_temperature::kelvin};
+
+use crate::quantity::{Pressure, ThermodynamicTemperature};
+
+/// Ratio of the molar mass of water to the molar mass of dry air.
+const WATER_AIR_MOLAR_MASS_RATIO: Scalar = 0.622;
+
+/// Altitude (m) over which the vapor pressure of the standard profile falls by
+/// a factor of e (Hann's formula).
+const WATER_VAPOR_SCALE_HEIGHT: Scalar = 2000.0;
+
+/// Coefficients of the Magnus formula over water: pressure (Pa), and the
+/// dimensionless and temperature (°C) coefficients.
+const MAGNUS_PRESSURE: Scalar = 610.94;
+const MAGNUS_A: Scalar = 17.625;
+const MAGNUS_B: Scalar = 243.04;
+
+const KELVIN_OFFSET: Scalar = 273.15;
+
+/// How much water vapor is in the air.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Humidity {
+    /// No water vapor at all.
+    #[default]
+    Dry,
+    /// Relative humidity (0 to 1) at sea level, with the vapor pressure
+    /// falling off exponentially above it.
+    Standard(Scalar),
+    /// Humidity measured at a few altitudes, like a radiosonde sounding.
+    /// Levels are sorted from lowest to highest altitude.
+    Sounding(Vec<HumidityLevel>),
+}
+
+/// Humidity at one altitude of a sounding.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HumidityLevel {
+    /// Altitude (m) above mean sea level.
+    pub altitude: Scalar,
+    pub moisture: Moisture,
+}
+
+/// A measure of the water vapor in the air.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Moisture {
+    /// Vapor pressure as a fraction (0 to 1) of the saturation vapor pressure.
+    RelativeHumidity(Scalar),
+    /// Temperature (K) where the vapor would start to condense.
+    Dewpoint(Scalar),
+}
+
+impl Humidity {
+    /// Sort the levels of a sounding by altitude.
+    pub fn sorted(mut self) -> Self {
+        if let Humidity::Sounding(levels) = &mut self {
+            levels.sort_by(|a, b| a.altitude.total_cmp(&b.altitude));
+        }
+        self
+    }
+
+    /// Vapor pressure (Pa) of water at an altitude (m), given the temperature
+    /// of the air at any altitude.
+    pub fn vapor_pressure(
+        &self,
+        altitude: Scalar,
+        temperature: impl Fn(Scalar) -> ThermodynamicTemperature,
+    ) -> Pressure {
+        let vapor_pressure = match self {
+            Humidity::Dry => return Pressure::default(),
+            Humidity::Standard(relative_humidity) => {
+                *relative_humidity
+                    * saturation_vapor_pressure(temperature(0.0))
+                    * Scalar::exp(-altitude / WATER_VAPOR_SCALE_HEIGHT)
+            }
+            Humidity::Sounding(levels) => {
+                let level_vapor_pressure = |level: &HumidityLevel| match level.moisture {
+                    Moisture::Dewpoint(dewpoint) => {
+                        saturation_vapor_pressure(ThermodynamicTemperature::new::<kelvin>(dewpoint))
+                    }
+                    Moisture::RelativeHumidity(relative_humidity) => {
+                        relative_humidity * saturation_vapor_pressure(temperature(level.altitude))
+                    }
+                };
+                let upper = levels.partition_point(|level| level.altitude < altitude);
+                match (upper.checked_sub(1).map(|i| &levels[i]), levels.get(upper)) {
+                    (None, None) => return Pressure::default(),
+                    (Some(level), None) | (None, Some(level)) => level_vapor_pressure(level),
+                    (Some(below), Some(above)) => {
+                        let t = (altitude - below.altitude) / (above.altitude - below.altitude);
+                        let below = level_vapor_pressure(below);
+                        below + (level_vapor_pressure(above) - below) * t
+                    }
+                }
+            }
+        };
+        vapor_pressure.min(saturation_vapor_pressure(temperature(altitude)))
+    }
+}
+
+/// Vapor pressure (Pa) of water in air saturated at some temperature (K).
+pub fn saturation_vapor_pressure(temperature: ThermodynamicTemperature) -> Pressure {
+    let celsius = temperature.get::<kelvin>() - KELVIN_OFFSET;
+    Pressure::new::<pascal>(
+        MAGNUS_PRESSURE * Scalar::exp(MAGNUS_A * celsius / (celsius + MAGNUS_B)),
+    )
+}
+
+/// Dewpoint (K) of air with some vapor pressure (Pa). Only meaningful in air
+/// that holds some vapor.
+pub fn dewpoint(vapor_pressure: Pressure) -> ThermodynamicTemperature {
+    let ratio = Scalar::ln(vapor_pressure.get::<pascal>() / MAGNUS_PRESSURE);
+    ThermodynamicTemperature::new::<kelvin>(
+        MAGNUS_B * ratio / (MAGNUS_A - ratio) + KELVIN_OFFSET,
+    )
+}
+
+/// Virtual temperature (K) of moist air: the temperature where dry air at the
+/// same pressure (Pa) has the same density.
+pub fn virtual_temperature(
+    temperature: ThermodynamicTemperature,
+    pressure: Pressure,
+    vapor_pressure: Pressure,
+) -> ThermodynamicTemperature {
+    let vapor_fraction = vapor_pressure.get::<pascal>() / pressure.get::<pascal>();
+    ThermodynamicTemperature::new::<kelvin>(
+        temperature.get::<kelvin>() / (1.0 - vapor_fraction * (1.0 - WATER_AIR_MOLAR_MASS_RATIO)),
+    )
+}
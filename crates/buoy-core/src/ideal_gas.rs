@@ -5,11 +5,8 @@ use std::ops::{Div, Mul};
 
 use avian3d::math::Scalar;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use uom::si::{
-    f32::{
-        ThermodynamicTemperature, Pressure, Mass, Volume, 
-        MassDensity, MolarMass
-    },
     thermodynamic_temperature::kelvin,
     pressure::pascal,
     mass::kilogram,
@@ -22,10 +19,11 @@ use crate::{
     constants::{GAS_CONSTANT, STANDARD_GRAVITY},
     core::SimState,
     geometry::sphere_volume,
+    quantity::{Mass, MassDensity, MolarMass, Pressure, ThermodynamicTemperature, Volume},
 };
 
 pub(crate) fn plugin(app: &mut App) {
-    // nothing yet
+    app.register_type::<IdealGas>();
 }
 
 /// Volume (m³) of an ideal gas from its temperature (K), pressure (Pa),
@@ -50,7 +48,7 @@ pub fn ideal_gas_density(
 }
 
 /// Molecular species of a gas.
-#[derive(Component, Debug, Clone, PartialEq)]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GasSpecies {
     pub name: String,
     pub abbreviation: String,
@@ -94,7 +92,8 @@ impl GasSpecies {
 }
 
 /// Properties of an ideal gas per unit mass.
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Component, Default, Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, PartialEq, Serialize, Deserialize)]
 pub struct IdealGas {
     pub species: GasSpecies,
     pub mass: Mass,
@@ -125,7 +124,7 @@ impl IdealGas {
         ideal_gas_density(self.temperature, self.pressure, &self.species)
     }
 
-    pub fn with_mass(self, mass: f32) -> Self {
+    pub fn with_mass(self, mass: Scalar) -> Self {
         Self {
             mass: Mass::new::<kilogram>(mass),
             ..self
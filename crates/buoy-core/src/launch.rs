@@ -0,0 +1,239 @@
+//! Pre-launch calculations.
+//!
+//! Before a launch we need to know how much lift gas to put in the balloon.
+//! [`fill`] works out the fill for a target ascent rate or free lift and
+//! predicts where and when the balloon will burst.
+//!
+//! Lift is given in units of mass, the way it is measured on the launch pad
+//! with a scale hanging from the neck of the balloon:
+//! - neck lift is the lift measured at the neck: payload mass plus free lift.
+//! - free lift is the lift left over after the balloon and payload are lifted.
+//!
+//! Reference:
+//! - https://github.com/cuspaceflight/tawhiri (burst calculator)
+
+use avian3d::math::{Scalar, Vector};
+use uom::si::{
+    acceleration::meter_per_second_squared,
+    length::meter,
+    mass::kilogram,
+    mass_density::kilogram_per_cubic_meter,
+    time::second,
+    velocity::meter_per_second,
+    volume::cubic_meter,
+};
+
+use crate::{
+    atmosphere::Atmosphere,
+    constants::{PI, STANDARD_GRAVITY},
+    forces::scale_gravity,
+    geometry::{sphere_radius_from_volume, sphere_volume},
+    ideal_gas::{ideal_gas_density, ideal_gas_volume, GasSpecies},
+    quantity::{Length, Mass, Time, Velocity, Volume},
+};
+
+/// Steps used to integrate the ascent from launch to burst.
+const ASCENT_STEPS: usize = 500;
+/// Iterations used when solving for the fill or burst altitude.
+const SOLVER_ITERATIONS: usize = 64;
+
+/// What the fill should achieve.
+#[derive(Debug, Clone, Copy)]
+pub enum FillTarget {
+    /// Ascent rate at launch.
+    AscentRate(Velocity),
+    /// Lift left over after the balloon and payload are lifted.
+    FreeLift(Mass),
+}
+
+/// Everything needed to compute a fill.
+#[derive(Debug, Clone)]
+pub struct FillRequest {
+    pub payload_mass: Mass,
+    pub balloon_mass: Mass,
+    /// Diameter of the balloon when it bursts.
+    pub burst_diameter: Length,
+    /// Drag coefficient of the balloon during ascent.
+    pub drag_coefficient: Scalar,
+    pub species: GasSpecies,
+    /// Altitude above mean sea level of the launch site.
+    pub launch_altitude: Length,
+    pub target: FillTarget,
+}
+
+/// How to fill the balloon and what to expect from the flight.
+#[derive(Debug, Clone)]
+pub struct FillSolution {
+    /// Mass of lift gas to put in the balloon.
+    pub gas_mass: Mass,
+    /// Volume of the balloon at the launch site.
+    pub fill_volume: Volume,
+    /// Diameter of the balloon at the launch site.
+    pub fill_diameter: Length,
+    pub neck_lift: Mass,
+    pub free_lift: Mass,
+    /// Ascent rate at launch.
+    pub ascent_rate: Velocity,
+    /// Altitude above mean sea level where the balloon bursts.
+    pub burst_altitude: Length,
+    /// Time from launch until the balloon bursts.
+    pub time_to_burst: Time,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillError {
+    /// The lift gas is not lighter than air at the launch site.
+    NoLift,
+    /// The balloon would be bigger than its burst diameter at launch.
+    BurstAtLaunch,
+    /// The balloon would not burst below the top of the atmosphere model.
+    NoBurst,
+    /// The target can't be reached with a positive free lift.
+    InvalidTarget,
+}
+
+impl std::fmt::Display for FillError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for FillError {}
+
+/// Compute how much lift gas to put in a balloon at the launch site.
+pub fn fill(request: &FillRequest, atmosphere: &Atmosphere) -> Result<FillSolution, FillError> {
+    let launch_altitude = request.launch_altitude.get::<meter>();
+    let site = Vector::Y * launch_altitude;
+    let temperature = atmosphere.temperature(site);
+    let pressure = atmosphere.pressure(site);
+    let air_density = atmosphere.density(site).get::<kilogram_per_cubic_meter>();
+    let gas_density = ideal_gas_density(temperature, pressure, &request.species)
+        .get::<kilogram_per_cubic_meter>();
+    if gas_density >= air_density {
+        return Err(FillError::NoLift);
+    }
+    let gravity = gravity_at(launch_altitude);
+    let system_mass = (request.payload_mass + request.balloon_mass).get::<kilogram>();
+
+    // Volume of gas needed to lift the system plus some free lift (kg).
+    let volume_for = |free_lift: Scalar| (system_mass + free_lift) / (air_density - gas_density);
+    let ascent_rate_for = |free_lift: Scalar| {
+        terminal_velocity(
+            free_lift * gravity,
+            air_density,
+            request.drag_coefficient,
+            volume_for(free_lift),
+        )
+    };
+
+    let free_lift = match request.target {
+        FillTarget::FreeLift(free_lift) => free_lift.get::<kilogram>(),
+        FillTarget::AscentRate(ascent_rate) => {
+            let target = ascent_rate.get::<meter_per_second>();
+            if target <= 0.0 {
+                return Err(FillError::InvalidTarget);
+            }
+            // Ascent rate grows with free lift, so bracket the target and then
+            // bisect.
+            let mut upper = system_mass.max(1.0);
+            while ascent_rate_for(upper) < target {
+                upper *= 2.0;
+                if !upper.is_finite() {
+                    return Err(FillError::InvalidTarget);
+                }
+            }
+            let mut lower = 0.0;
+            for _ in 0..SOLVER_ITERATIONS {
+                let mid = 0.5 * (lower + upper);
+                if ascent_rate_for(mid) < target {
+                    lower = mid;
+                } else {
+                    upper = mid;
+                }
+            }
+            0.5 * (lower + upper)
+        }
+    };
+    if free_lift <= 0.0 {
+        return Err(FillError::InvalidTarget);
+    }
+
+    let fill_volume = volume_for(free_lift);
+    let gas_mass = Mass::new::<kilogram>(fill_volume * gas_density);
+    let burst_volume = sphere_volume(request.burst_diameter.get::<meter>() / 2.0);
+    if fill_volume >= burst_volume {
+        return Err(FillError::BurstAtLaunch);
+    }
+
+    // The gas expands as it rises. Find where it fills the burst volume.
+    let volume_at = |altitude: Scalar| {
+        let position = Vector::Y * altitude;
+        ideal_gas_volume(
+            atmosphere.temperature(position),
+            atmosphere.pressure(position),
+            gas_mass,
+            &request.species,
+        )
+        .get::<cubic_meter>()
+    };
+    if volume_at(Atmosphere::MAX_ALTITUDE) < burst_volume {
+        return Err(FillError::NoBurst);
+    }
+    let mut lower = launch_altitude;
+    let mut upper = Atmosphere::MAX_ALTITUDE;
+    for _ in 0..SOLVER_ITERATIONS {
+        let mid = 0.5 * (lower + upper);
+        if volume_at(mid) < burst_volume {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    let burst_altitude = 0.5 * (lower + upper);
+
+    // Integrate the ascent. The free lift stays the same on the way up, but
+    // the air gets thinner and the balloon gets bigger.
+    let dh = (burst_altitude - launch_altitude) / ASCENT_STEPS as Scalar;
+    let time_to_burst: Scalar = (0..ASCENT_STEPS)
+        .map(|step| {
+            let altitude = launch_altitude + (step as Scalar + 0.5) * dh;
+            let position = Vector::Y * altitude;
+            let ascent_rate = terminal_velocity(
+                free_lift * gravity_at(altitude),
+                atmosphere.density(position).get::<kilogram_per_cubic_meter>(),
+                request.drag_coefficient,
+                volume_at(altitude),
+            );
+            dh / ascent_rate
+        })
+        .sum();
+
+    Ok(FillSolution {
+        gas_mass,
+        fill_volume: Volume::new::<cubic_meter>(fill_volume),
+        fill_diameter: Length::new::<meter>(2.0 * sphere_radius_from_volume(fill_volume)),
+        neck_lift: request.payload_mass + Mass::new::<kilogram>(free_lift),
+        free_lift: Mass::new::<kilogram>(free_lift),
+        ascent_rate: Velocity::new::<meter_per_second>(ascent_rate_for(free_lift)),
+        burst_altitude: Length::new::<meter>(burst_altitude),
+        time_to_burst: Time::new::<second>(time_to_burst),
+    })
+}
+
+/// Acceleration (m/s²) due to gravity at an altitude (m).
+fn gravity_at(altitude: Scalar) -> Scalar {
+    STANDARD_GRAVITY.get::<meter_per_second_squared>() * scale_gravity(altitude)
+}
+
+/// Speed (m/s) where drag on a sphere of the given volume (m³) balances a net
+/// force (N).
+fn terminal_velocity(
+    force: Scalar,
+    air_density: Scalar,
+    drag_coefficient: Scalar,
+    volume: Scalar,
+) -> Scalar {
+    let radius = sphere_radius_from_volume(volume);
+    let area = PI * radius * radius;
+    Scalar::sqrt(2.0 * force / (air_density * drag_coefficient * area))
+}
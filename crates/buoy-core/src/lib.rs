@@ -1,27 +1,103 @@
 #![allow(unused_imports)]
 pub mod atmosphere;
+pub mod atmosphere_table;
+pub mod ballast;
 pub mod constants;
+pub mod control;
 pub mod core;
+pub mod determinism;
+pub mod empirical_atmosphere;
+pub mod ensemble;
+pub mod estimation;
+pub mod fault;
+pub mod flight_train;
 pub mod forces;
 pub mod format;
 pub mod geometry;
 pub mod grid;
+pub mod headless;
+pub mod humidity;
 pub mod ideal_gas;
-pub mod scene;
+pub mod launch;
+pub mod material_properties;
+pub mod properties;
+pub mod replay;
+pub mod scenario;
+pub mod snapshot;
+pub mod telemetry;
 pub mod time;
+pub mod turbulence;
+pub mod vent;
+pub mod water;
+pub mod wind;
 
 pub use uom as units;
 
+#[cfg(all(feature = "f32", feature = "f64"))]
+compile_error!(
+    "the `f32` and `f64` features are mutually exclusive; \
+     build with `--no-default-features --features f64` for double precision"
+);
+#[cfg(not(any(feature = "f32", feature = "f64")))]
+compile_error!("enable one of the `f32` or `f64` features");
+
+/// Quantities with units in the floating point precision of the build:
+/// [`uom::si::f32`], or [`uom::si::f64`] with the `f64` feature. Their values
+/// are the same type as avian's [`Scalar`](avian3d::math::Scalar).
+#[cfg(all(feature = "f32", not(feature = "f64")))]
+pub use uom::si::f32 as quantity;
+#[cfg(all(feature = "f64", not(feature = "f32")))]
+pub use uom::si::f64 as quantity;
+
 pub mod prelude {
     pub use crate::{
-        atmosphere::Atmosphere,
+        atmosphere::{
+            Atmosphere, AtmosphereError, AtmosphereSource, AtmosphericState, MilStd210,
+            OutOfRange, Supplement,
+        },
+        ballast::{Ballast, BallastAction, BallastCommand, BallastDropped, BallastRule},
+        control::{
+            Actuation, BangBangAltitudeHold, ControlLaw, Controller, PidAltitudeHold, Sensors,
+        },
         core::{BuoyPlugin, SimState},
-        forces::{drag, scale_gravity},
+        determinism::SimRng,
+        empirical_atmosphere::SpaceWeather,
+        ensemble::{
+            Dispersion, Ensemble, EnsembleResult, ErrorEllipse, Perturbation, RunResult, Scatter,
+        },
+        estimation::{
+            Estimation, EstimationError, EstimationResult, FittedParameter, Parameter,
+        },
+        fault::{FaultAction, FaultPolicy, FaultReason, Faults, SimFault},
+        flight_train::{
+            Balloon, BalloonBurst, BalloonConfig, Elasticity, FlightTrain, FlightTrainEntities,
+            LineConfig, Parachute, ParachuteConfig, Payload, PayloadConfig, RiggingLine, Shape,
+        },
+        forces::{drag, scale_gravity, Drag},
         grid::{Precision, RootGrid, GRID_CELL_EDGE_LENGTH_METERS},
+        headless::{HeadlessPlugins, HeadlessSim, SimOutcome},
+        humidity::{Humidity, HumidityLevel, Moisture},
         ideal_gas::{GasSpecies, IdealGas},
+        launch::{FillError, FillRequest, FillSolution, FillTarget},
+        material_properties::{MaterialProperties, Skin},
+        properties::Properties,
+        replay::{Ghost, ReplayConfig, ReplayMetrics, Track, TrackError, TrackFormat, TrackPoint},
+        scenario::{
+            Airborne, Landed, PayloadLanded, Scenario, ScenarioError, StopCondition,
+            StopConditionMet,
+        },
+        snapshot::{Snapshot, SnapshotCommand, SnapshotError},
+        telemetry::{Channel, TelemetryChannels, TelemetryConfig, TelemetryLog},
+        time::{SimClock, TimeWarp},
+        turbulence::{Gust, Intensity, Spectrum, Turbulence},
+        vent::{VentAction, VentCommand, VentValve},
+        water::{Afloat, WaterBody, WaterExtent},
+        wind::{Wind, WindLayer},
+    };
+    pub use crate::quantity::{
+        Mass, MassDensity, MolarMass, Pressure, ThermodynamicTemperature, Volume,
     };
     pub use uom::si::{
-        f32::{Mass, MassDensity, MolarMass, Pressure, ThermodynamicTemperature, Volume},
         mass::kilogram,
         mass_density::kilogram_per_cubic_meter,
         molar_mass::kilogram_per_mole,
@@ -1,29 +1,79 @@
+use avian3d::math::Scalar;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Properties of a material that an envelope can be made of.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct MaterialProperties {
+    pub name: String,
+    // temperature (K) where the given material fails
+    pub max_temperature: Scalar,
+    // density (kg/m³) of the envelope material
+    pub density: Scalar,
+    // how much thermal radiation is emitted
+    pub emissivity: Scalar,
+    // how much thermal radiation is absorbed
+    pub absorptivity: Scalar,
+    // thermal conductivity (W/mK) of the material at room temperature
+    pub thermal_conductivity: Scalar,
+    // J/kgK
+    pub specific_heat: Scalar,
+    // ratio of change in width for a given change in length
+    pub poissons_ratio: Scalar,
+    // Youngs Modulus aka Modulus of Elasticity (Pa)
+    pub elasticity: Scalar,
+    // elongation at failure (decimal, unitless) 1 = original size
+    pub max_strain: Scalar,
+    // tangential stress at failure (Pa)
+    pub max_stress: Scalar,
+}
+
 /// The skin is the material that composes the outer surface of the balloon.
 /// TODO: Implement multiple material types, such as latex, polyurethane, etc.
-#[derive(Debug, Clone, Reflect)]
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
 pub struct Skin {
     // temperature (K) where the given material fails
-    pub max_temperature: f32,
+    pub max_temperature: Scalar,
     // density (kg/m³) of the envelope material
-    pub density: f32,
+    pub density: Scalar,
     // how much thermal radiation is emitted
-    pub emissivity: f32,
+    pub emissivity: Scalar,
     // how much thermal radiation is absorbed
-    pub absorptivity: f32,
+    pub absorptivity: Scalar,
     // thermal conductivity (W/mK) of the material at room temperature
-    pub thermal_conductivity: f32,
+    pub thermal_conductivity: Scalar,
     // J/kgK
-    pub specific_heat: f32,
+    pub specific_heat: Scalar,
     // ratio of change in width for a given change in length
-    pub poissons_ratio: f32,
+    pub poissons_ratio: Scalar,
     // Youngs Modulus aka Modulus of Elasticity (Pa)
-    pub elasticity: f32,
+    pub elasticity: Scalar,
     // elongation at failure (decimal, unitless) 1 = original size
-    pub max_strain: f32,
+    pub max_strain: Scalar,
     // tangential stress at failure (Pa)
-    pub max_stress: f32,
+    pub max_stress: Scalar,
     // thickness of the envelope material (m)
-    pub thickness: f32,
+    pub thickness: Scalar,
+}
+
+impl Skin {
+    /// A skin made of a material with some thickness (m).
+    pub fn new(material: &MaterialProperties, thickness: Scalar) -> Self {
+        Skin {
+            max_temperature: material.max_temperature,
+            density: material.density,
+            emissivity: material.emissivity,
+            absorptivity: material.absorptivity,
+            thermal_conductivity: material.thermal_conductivity,
+            specific_heat: material.specific_heat,
+            poissons_ratio: material.poissons_ratio,
+            elasticity: material.elasticity,
+            max_strain: material.max_strain,
+            max_stress: material.max_stress,
+            thickness,
+        }
+    }
 }
 
 impl Default for Skin {
@@ -0,0 +1,53 @@
+//! Physical properties of the gases and materials that scenarios can refer to
+//! by name.
+//!
+//! The built-in properties are compiled in from `assets/configs/properties.ron`.
+
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ideal_gas::GasSpecies, material_properties::MaterialProperties};
+
+static BUILTIN_PROPERTIES: LazyLock<Properties> = LazyLock::new(|| {
+    ron::from_str(include_str!("../../../assets/configs/properties.ron"))
+        .expect("built-in properties should be valid")
+});
+
+/// A registry of gases and materials.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Properties {
+    #[serde(default)]
+    pub gases: Vec<GasSpecies>,
+    #[serde(default)]
+    pub materials: Vec<MaterialProperties>,
+}
+
+impl Properties {
+    /// Properties that are always available.
+    pub fn builtin() -> Self {
+        BUILTIN_PROPERTIES.clone()
+    }
+
+    /// Add more gases and materials. Later entries take precedence over
+    /// earlier ones with the same name.
+    pub fn extend(&mut self, other: Properties) {
+        self.gases.extend(other.gases);
+        self.materials.extend(other.materials);
+    }
+
+    /// Look up a gas by its name or abbreviation, ignoring case.
+    pub fn gas(&self, name: &str) -> Option<&GasSpecies> {
+        self.gases.iter().rev().find(|gas| {
+            gas.name.eq_ignore_ascii_case(name) || gas.abbreviation.eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Look up a material by its name, ignoring case.
+    pub fn material(&self, name: &str) -> Option<&MaterialProperties> {
+        self.materials
+            .iter()
+            .rev()
+            .find(|material| material.name.eq_ignore_ascii_case(name))
+    }
+}
@@ -0,0 +1,635 @@
+//! Replay recorded flights next to the simulation.
+//!
+//! A [`Track`] is the path of a real flight: GPS fixes with a time, latitude,
+//! longitude and altitude. Tracks are imported from:
+//!
+//! - APRS packet logs, like the raw packets exported from aprs.fi. Each line
+//!   starts with the UTC time the packet was received, followed by the packet:
+//!   `2024-06-01 14:32:10 UTC: KD0ABC-11>APRS,WIDE2-1:!4003.50N/10500.25WO/A=012345`.
+//!   Uncompressed and compressed position reports are read. Packets without an
+//!   altitude are skipped.
+//! - IGC flight logs, using the GNSS altitude of each B record (or the pressure
+//!   altitude when there is no GNSS altitude).
+//! - CSV files with a header row and `time`, `latitude`, `longitude` and
+//!   `altitude` columns. Times are RFC 3339 or seconds since the Unix epoch and
+//!   altitudes are in meters.
+//!
+//! Replaying a track spawns a [`Ghost`] that follows the recorded path in step
+//! with the [`SimClock`]. Every physics step the ghost is compared to the
+//! simulated balloon it is paired with and the differences are accumulated in
+//! [`ReplayMetrics`]. Set the scenario's epoch and launch site to those of the
+//! recorded flight (see [`Track::launch_site`]) so the two start together.
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{Physics, PhysicsSet, Position},
+};
+use bevy::{math::DVec3, prelude::*};
+use big_space::prelude::*;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use uom::si::length::{foot, meter};
+
+use crate::{
+    core::SimState,
+    flight_train::Balloon,
+    grid::{Precision, RootGrid},
+    quantity::Length,
+    scenario::{LaunchSite, Scenario},
+    time::SimClock,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    // The root grid is spawned during startup, so wait until it exists.
+    app.add_systems(PostStartup, spawn_ghosts);
+    app.add_systems(
+        FixedPostUpdate,
+        (move_ghosts, compare_ghosts)
+            .chain()
+            .after(PhysicsSet::StepSimulation)
+            .run_if(in_state(SimState::Running)),
+    );
+    app.add_systems(OnEnter(SimState::Stopped), report_metrics);
+}
+
+/// A recorded flight to replay next to a simulated balloon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    /// File that holds the recorded track.
+    pub track: PathBuf,
+    /// Format of the file. Guessed from its extension if omitted.
+    #[serde(default)]
+    pub format: Option<TrackFormat>,
+    /// Name of the balloon to compare the track to.
+    pub balloon: String,
+}
+
+/// File formats that tracks can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackFormat {
+    Aprs,
+    Igc,
+    Csv,
+}
+
+impl TrackFormat {
+    /// Guess the format of a file from its extension. Anything that isn't an
+    /// IGC or CSV file is read as an APRS log.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("igc") => TrackFormat::Igc,
+            Some("csv") => TrackFormat::Csv,
+            _ => TrackFormat::Aprs,
+        }
+    }
+}
+
+/// A GPS fix of a recorded flight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+    /// Geodetic latitude (degrees, north positive).
+    pub latitude: f64,
+    /// Geodetic longitude (degrees, east positive).
+    pub longitude: f64,
+    /// Altitude (m) above mean sea level.
+    pub altitude: Scalar,
+}
+
+/// The recorded path of a real flight, sorted by time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    points: Vec<TrackPoint>,
+}
+
+impl Track {
+    /// A track through some points, in any order. Points recorded at the same
+    /// time as an earlier one are dropped.
+    pub fn new(mut points: Vec<TrackPoint>) -> Result<Self, TrackError> {
+        points.sort_by_key(|point| point.time);
+        points.dedup_by_key(|point| point.time);
+        if points.is_empty() {
+            return Err(TrackError::Empty);
+        }
+        Ok(Track { points })
+    }
+
+    /// Read a track from a file, guessing its format from its extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TrackError> {
+        let format = TrackFormat::from_path(&path);
+        Track::load_as(path, format)
+    }
+
+    pub fn load_as(path: impl AsRef<Path>, format: TrackFormat) -> Result<Self, TrackError> {
+        match format {
+            TrackFormat::Aprs => Track::from_aprs(&std::fs::read_to_string(path)?),
+            TrackFormat::Igc => Track::from_igc(&std::fs::read_to_string(path)?),
+            TrackFormat::Csv => Track::from_csv(std::fs::File::open(path)?),
+        }
+    }
+
+    /// Parse a log of APRS packets, one per line, each starting with the time
+    /// it was received.
+    pub fn from_aprs(text: &str) -> Result<Self, TrackError> {
+        let points = text.lines().filter_map(parse_aprs_line).collect();
+        Track::new(points)
+    }
+
+    /// Parse an IGC flight log.
+    pub fn from_igc(text: &str) -> Result<Self, TrackError> {
+        let mut date = None;
+        let mut last_time = None;
+        let mut points = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if let Some(header) = line.strip_prefix("HFDTE") {
+                date = Some(parse_igc_date(header).ok_or_else(|| TrackError::Invalid {
+                    line: index + 1,
+                    reason: "date is not DDMMYY".to_string(),
+                })?);
+            } else if line.starts_with('B') {
+                let mut day = date.ok_or(TrackError::MissingDate)?;
+                let (time, latitude, longitude, altitude) =
+                    parse_igc_fix(line).ok_or_else(|| TrackError::Invalid {
+                        line: index + 1,
+                        reason: "B record is malformed".to_string(),
+                    })?;
+                // Fixes are in time order, so an earlier time is the next day.
+                if last_time.is_some_and(|last_time| time < last_time) {
+                    day = day.succ_opt().ok_or(TrackError::MissingDate)?;
+                    date = Some(day);
+                }
+                last_time = Some(time);
+                points.push(TrackPoint {
+                    time: day.and_time(time).and_utc(),
+                    latitude,
+                    longitude,
+                    altitude,
+                });
+            }
+        }
+        Track::new(points)
+    }
+
+    /// Parse a CSV file with `time`, `latitude`, `longitude` and `altitude`
+    /// columns. Column names are matched without regard to case or a unit in
+    /// parentheses, and `lat`, `lon`, `alt` and a few other short names work
+    /// too.
+    pub fn from_csv(reader: impl Read) -> Result<Self, TrackError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let header = reader
+            .headers()?
+            .iter()
+            .map(|name| {
+                name.split('(')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_ascii_lowercase()
+            })
+            .collect::<Vec<_>>();
+        let column = |names: &[&str], field: &'static str| {
+            header
+                .iter()
+                .position(|name| names.contains(&name.as_str()))
+                .ok_or(TrackError::MissingColumn(field))
+        };
+        let time = column(&["time", "timestamp", "utc", "datetime"], "time")?;
+        let latitude = column(&["latitude", "lat"], "latitude")?;
+        let longitude = column(&["longitude", "lon", "lng", "long"], "longitude")?;
+        let altitude = column(&["altitude", "alt", "elevation", "ele"], "altitude")?;
+
+        let mut points = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let record = record?;
+            // The header is the first line.
+            let line = index + 2;
+            let invalid = |field: &str| TrackError::Invalid {
+                line,
+                reason: format!("{} is not valid", field),
+            };
+            let number = |column: usize, field: &str| {
+                record
+                    .get(column)
+                    .and_then(|value| value.trim().parse::<f64>().ok())
+                    .ok_or_else(|| invalid(field))
+            };
+            points.push(TrackPoint {
+                time: record
+                    .get(time)
+                    .and_then(parse_csv_time)
+                    .ok_or_else(|| invalid("time"))?,
+                latitude: number(latitude, "latitude")?,
+                longitude: number(longitude, "longitude")?,
+                altitude: number(altitude, "altitude")? as Scalar,
+            });
+        }
+        Track::new(points)
+    }
+
+    pub fn points(&self) -> &[TrackPoint] {
+        &self.points
+    }
+
+    /// The first fix of the track.
+    pub fn start(&self) -> &TrackPoint {
+        &self.points[0]
+    }
+
+    /// The last fix of the track.
+    pub fn end(&self) -> &TrackPoint {
+        &self.points[self.points.len() - 1]
+    }
+
+    /// Where the recorded flight started.
+    pub fn launch_site(&self) -> LaunchSite {
+        let start = self.start();
+        LaunchSite {
+            latitude: start.latitude,
+            longitude: start.longitude,
+            altitude: start.altitude,
+        }
+    }
+}
+
+/// Split the UTC time at the start of a line from the rest of the line.
+/// Accepts `2024-06-01 14:32:10 UTC` and RFC 3339 times.
+fn split_timestamp(line: &str) -> Option<(DateTime<Utc>, &str)> {
+    let separators = |c: char| c == ':' || c.is_whitespace();
+    if let Ok((time, rest)) = NaiveDateTime::parse_and_remainder(line, "%Y-%m-%d %H:%M:%S") {
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix("UTC").unwrap_or(rest);
+        return Some((time.and_utc(), rest.trim_start_matches(separators)));
+    }
+    let (token, rest) = line.split_once(char::is_whitespace)?;
+    let time = DateTime::parse_from_rfc3339(token.trim_end_matches(':')).ok()?;
+    Some((time.with_timezone(&Utc), rest.trim_start()))
+}
+
+fn parse_aprs_line(line: &str) -> Option<TrackPoint> {
+    let (time, packet) = split_timestamp(line.trim())?;
+    // The information field follows the first colon, after the path.
+    let (_, information) = packet.split_once(':')?;
+    let (latitude, longitude, altitude) = parse_aprs_position(information)?;
+    Some(TrackPoint {
+        time,
+        latitude,
+        longitude,
+        altitude,
+    })
+}
+
+/// Latitude, longitude and altitude (m) of an APRS position report.
+fn parse_aprs_position(information: &str) -> Option<(f64, f64, Scalar)> {
+    let body = match information.chars().next()? {
+        '!' | '=' => information.get(1..)?,
+        // These reports have a timestamp before the position.
+        '/' | '@' => information.get(8..)?,
+        _ => return None,
+    };
+    if body.starts_with(|c: char| c.is_ascii_digit()) {
+        let latitude = parse_degrees_minutes(body.get(0..2)?, body.get(2..7)?, body.get(7..8)?)?;
+        let longitude =
+            parse_degrees_minutes(body.get(9..12)?, body.get(12..17)?, body.get(17..18)?)?;
+        let altitude = parse_aprs_altitude(body.get(19..).unwrap_or_default())?;
+        Some((latitude, longitude, altitude))
+    } else {
+        let latitude = 90.0 - base91(body.get(1..5)?)? as f64 / 380926.0;
+        let longitude = -180.0 + base91(body.get(5..9)?)? as f64 / 190463.0;
+        let altitude = parse_aprs_altitude(body.get(13..).unwrap_or_default())
+            .or_else(|| parse_compressed_altitude(body.get(10..13)?))?;
+        Some((latitude, longitude, altitude))
+    }
+}
+
+/// Degrees from `DD(D)` degrees and `MM.mm` minutes with an N, S, E or W.
+fn parse_degrees_minutes(degrees: &str, minutes: &str, hemisphere: &str) -> Option<f64> {
+    let value = degrees.parse::<f64>().ok()? + minutes.parse::<f64>().ok()? / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(value),
+        "S" | "W" => Some(-value),
+        _ => None,
+    }
+}
+
+/// Altitude (m) from an `/A=001234` (feet) extension in a comment.
+fn parse_aprs_altitude(comment: &str) -> Option<Scalar> {
+    let (_, rest) = comment.split_once("/A=")?;
+    let feet = rest.get(0..6)?.parse::<Scalar>().ok()?;
+    Some(Length::new::<foot>(feet).get::<meter>())
+}
+
+/// Altitude (m) from the `cs` bytes of a compressed position, if the
+/// compression type says they hold an altitude.
+fn parse_compressed_altitude(cst: &str) -> Option<Scalar> {
+    let compression_type = cst.as_bytes().get(2)?.checked_sub(33)?;
+    if compression_type & 0x18 != 0x10 {
+        return None;
+    }
+    let exponent = base91(cst.get(0..2)?)?;
+    let feet = Scalar::powi(1.002, exponent as i32);
+    Some(Length::new::<foot>(feet).get::<meter>())
+}
+
+fn base91(digits: &str) -> Option<u32> {
+    digits.bytes().try_fold(0, |value, digit| {
+        Some(value * 91 + digit.checked_sub(33).filter(|digit| *digit < 91)? as u32)
+    })
+}
+
+/// Date of an IGC file from the rest of its `HFDTE` header, which is either
+/// `DDMMYY` or `DATE:DDMMYY,NN`.
+fn parse_igc_date(header: &str) -> Option<NaiveDate> {
+    let header = header.strip_prefix("DATE:").unwrap_or(header);
+    let day = header.get(0..2)?.parse().ok()?;
+    let month = header.get(2..4)?.parse().ok()?;
+    let year = header.get(4..6)?.parse::<i32>().ok()?;
+    NaiveDate::from_ymd_opt(2000 + year, month, day)
+}
+
+/// Time, latitude, longitude and altitude (m) of an IGC B record, like
+/// `B1101355206343N00006198WA0058700558`.
+fn parse_igc_fix(line: &str) -> Option<(NaiveTime, f64, f64, Scalar)> {
+    let time = NaiveTime::parse_from_str(line.get(1..7)?, "%H%M%S").ok()?;
+    let latitude = parse_degrees_minutes(
+        line.get(7..9)?,
+        &format!("{}.{}", line.get(9..11)?, line.get(11..14)?),
+        line.get(14..15)?,
+    )?;
+    let longitude = parse_degrees_minutes(
+        line.get(15..18)?,
+        &format!("{}.{}", line.get(18..20)?, line.get(20..23)?),
+        line.get(23..24)?,
+    )?;
+    let pressure_altitude = line.get(25..30)?.parse::<Scalar>().ok()?;
+    let gnss_altitude = line.get(30..35)?.parse::<Scalar>().ok()?;
+    let altitude = if gnss_altitude != 0.0 {
+        gnss_altitude
+    } else {
+        pressure_altitude
+    };
+    Some((time, latitude, longitude, altitude))
+}
+
+fn parse_csv_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f") {
+        return Some(time.and_utc());
+    }
+    let seconds = value.parse::<f64>().ok()?;
+    DateTime::from_timestamp_micros((seconds * 1e6).round() as i64)
+}
+
+#[derive(Debug)]
+pub enum TrackError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    /// A CSV file has no column for a field.
+    MissingColumn(&'static str),
+    /// An IGC file has a fix before its date.
+    MissingDate,
+    Invalid {
+        line: usize,
+        reason: String,
+    },
+    /// The file has no fixes with a position and altitude.
+    Empty,
+}
+
+impl std::fmt::Display for TrackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackError::Io(e) => write!(f, "could not read track: {}", e),
+            TrackError::Csv(e) => write!(f, "could not read track: {}", e),
+            TrackError::MissingColumn(field) => write!(f, "track has no {} column", field),
+            TrackError::MissingDate => write!(f, "track has no date"),
+            TrackError::Invalid { line, reason } => write!(f, "line {}: {}", line, reason),
+            TrackError::Empty => write!(f, "track has no fixes"),
+        }
+    }
+}
+
+impl std::error::Error for TrackError {}
+
+impl From<std::io::Error> for TrackError {
+    fn from(e: std::io::Error) -> Self {
+        TrackError::Io(e)
+    }
+}
+
+impl From<csv::Error> for TrackError {
+    fn from(e: csv::Error) -> Self {
+        TrackError::Csv(e)
+    }
+}
+
+/// How far a simulated balloon strays from a recorded flight.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayMetrics {
+    /// Number of times the two were compared.
+    pub samples: usize,
+    altitude_squared_error: f64,
+    horizontal_drift: f64,
+    /// Largest horizontal distance (m) between the two.
+    pub max_horizontal_drift: Scalar,
+    /// Horizontal distance (m) between the two when they were last compared.
+    pub last_horizontal_drift: Scalar,
+}
+
+impl ReplayMetrics {
+    /// Compare a simulated position (m) to the recorded one at the same time.
+    pub fn add(&mut self, simulated: Vector, recorded: Vector) {
+        let altitude_error = (simulated.y - recorded.y) as f64;
+        let horizontal_drift = simulated.xz().distance(recorded.xz());
+        self.samples += 1;
+        self.altitude_squared_error += altitude_error * altitude_error;
+        self.horizontal_drift += horizontal_drift as f64;
+        self.max_horizontal_drift = self.max_horizontal_drift.max(horizontal_drift);
+        self.last_horizontal_drift = horizontal_drift;
+    }
+
+    /// Root mean square (m) of the altitude error.
+    pub fn altitude_rms(&self) -> Option<Scalar> {
+        (self.samples > 0)
+            .then(|| (self.altitude_squared_error / self.samples as f64).sqrt() as Scalar)
+    }
+
+    /// Mean horizontal distance (m) between the two.
+    pub fn mean_horizontal_drift(&self) -> Option<Scalar> {
+        (self.samples > 0).then(|| (self.horizontal_drift / self.samples as f64) as Scalar)
+    }
+}
+
+impl std::fmt::Display for ReplayMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.altitude_rms(), self.mean_horizontal_drift()) {
+            (Some(altitude_rms), Some(mean_drift)) => write!(
+                f,
+                "altitude RMS {:.0} m, horizontal drift mean {:.0} m, max {:.0} m, last {:.0} m",
+                altitude_rms, mean_drift, self.max_horizontal_drift, self.last_horizontal_drift
+            ),
+            _ => write!(f, "no overlap with the recorded track"),
+        }
+    }
+}
+
+/// An entity that follows a recorded track. It has no rigid body, so it does
+/// not take part in physics.
+#[derive(Component, Debug, Clone)]
+pub struct Ghost {
+    /// Name of the balloon the ghost is compared to.
+    pub balloon: String,
+    /// Recorded positions (m) in world coordinates and when they were reached.
+    path: Vec<(DateTime<Utc>, Vector)>,
+    pub metrics: ReplayMetrics,
+}
+
+impl Ghost {
+    /// A ghost that follows a track, placed relative to a launch site.
+    pub fn new(track: &Track, launch_site: &LaunchSite, balloon: impl Into<String>) -> Self {
+        Ghost {
+            balloon: balloon.into(),
+            path: track
+                .points()
+                .iter()
+                .map(|point| {
+                    (
+                        point.time,
+                        launch_site.local_position(point.latitude, point.longitude, point.altitude),
+                    )
+                })
+                .collect(),
+            metrics: ReplayMetrics::default(),
+        }
+    }
+
+    /// Recorded position (m) at a time, interpolated between fixes. `None`
+    /// before the track starts or after it ends.
+    pub fn position_at(&self, time: DateTime<Utc>) -> Option<Vector> {
+        let next = self.path.partition_point(|(fix_time, _)| *fix_time < time);
+        let (next_time, next_position) = *self.path.get(next)?;
+        if next_time == time {
+            return Some(next_position);
+        }
+        let (previous_time, previous_position) = *self.path.get(next.checked_sub(1)?)?;
+        let fraction = seconds(time - previous_time) / seconds(next_time - previous_time);
+        Some(previous_position.lerp(next_position, fraction as Scalar))
+    }
+
+    /// Recorded position (m) at a time, or the nearest end of the track when
+    /// the time is outside it.
+    pub fn clamped_position_at(&self, time: DateTime<Utc>) -> Vector {
+        self.position_at(time).unwrap_or_else(|| {
+            let (start_time, start) = self.path[0];
+            let (_, end) = self.path[self.path.len() - 1];
+            if time < start_time {
+                start
+            } else {
+                end
+            }
+        })
+    }
+}
+
+fn seconds(delta: TimeDelta) -> f64 {
+    delta.num_microseconds().map_or(f64::MAX, |micros| micros as f64 * 1e-6)
+}
+
+fn spawn_ghosts(
+    mut commands: Commands,
+    scenario: Res<Scenario>,
+    clock: Res<SimClock>,
+    root_grid: Query<(Entity, &Grid<Precision>), With<RootGrid>>,
+) {
+    let (root_grid_id, root_grid) = root_grid.single();
+    for replay in &scenario.replays {
+        let format = replay
+            .format
+            .unwrap_or_else(|| TrackFormat::from_path(&replay.track));
+        let track = match Track::load_as(&replay.track, format) {
+            Ok(track) => track,
+            Err(e) => {
+                error!("skipping replay of {}: {}", replay.track.display(), e);
+                continue;
+            }
+        };
+        info!(
+            "replaying {} ({} fixes) next to '{}'",
+            replay.track.display(),
+            track.points().len(),
+            replay.balloon
+        );
+        let ghost = Ghost::new(&track, &scenario.launch_site, replay.balloon.clone());
+        let position = ghost.clamped_position_at(clock.now());
+        let (cell, translation) = root_grid.translation_to_grid(DVec3::from(position));
+        commands
+            .spawn((
+                Name::new(format!("{} (recorded)", replay.balloon)),
+                ghost,
+                cell,
+                Transform::from_translation(translation),
+            ))
+            .set_parent(root_grid_id);
+    }
+}
+
+/// Move each ghost to where the recorded flight was at the current time.
+fn move_ghosts(
+    physics_time: Res<Time<Physics>>,
+    clock: Res<SimClock>,
+    mut ghosts: Query<(&Ghost, &mut GridCell<Precision>, &mut Transform)>,
+    root_grid: Query<&Grid<Precision>, With<RootGrid>>,
+) {
+    let Ok(root_grid) = root_grid.get_single() else {
+        return;
+    };
+    let now = clock.at(physics_time.elapsed());
+    for (ghost, mut cell, mut transform) in ghosts.iter_mut() {
+        let position = ghost.clamped_position_at(now);
+        let (new_cell, translation) = root_grid.translation_to_grid(DVec3::from(position));
+        *cell = new_cell;
+        transform.translation = translation;
+    }
+}
+
+/// Compare each ghost to its balloon while the recorded track covers the
+/// current time.
+fn compare_ghosts(
+    physics_time: Res<Time<Physics>>,
+    clock: Res<SimClock>,
+    mut ghosts: Query<&mut Ghost>,
+    balloons: Query<(&Name, &Position), With<Balloon>>,
+) {
+    let now = clock.at(physics_time.elapsed());
+    for mut ghost in ghosts.iter_mut() {
+        let Some(recorded) = ghost.position_at(now) else {
+            continue;
+        };
+        let Some((_, simulated)) = balloons
+            .iter()
+            .find(|(name, _)| name.as_str() == ghost.balloon)
+        else {
+            continue;
+        };
+        ghost.metrics.add(simulated.0, recorded);
+    }
+}
+
+fn report_metrics(ghosts: Query<&Ghost>) {
+    for ghost in ghosts.iter() {
+        info!("'{}' compared to its recorded flight: {}", ghost.balloon, ghost.metrics);
+    }
+}
@@ -0,0 +1,879 @@
+//! Scenarios describe everything needed to set up and run a simulation.
+//!
+//! Scenarios are written in [RON](https://github.com/ron-rs/ron). Quantities
+//! are plain numbers in SI units. Gases and envelope materials are referred to
+//! by name, either from the built-in [`Properties`] or from the `gases` and
+//! `materials` defined in the scenario itself.
+//!
+//! ```ron
+//! (
+//!     name: "Example",
+//!     epoch: "2024-06-01T14:00:00Z",
+//!     launch_site: (latitude: 40.0, longitude: -105.0, altitude: 1600.0),
+//!     balloons: [
+//!         (
+//!             name: "HAB",
+//!             envelope: (material: "Rubber", mass: 1.2),
+//!             lift_gas: (species: "Helium", mass: 0.6),
+//!             parachute: Some((mass: 0.1, diameter: 1.2)),
+//!             payload: (mass: 1.0),
+//!         ),
+//!     ],
+//!     stop_conditions: [Landed, Duration(14400.0)],
+//! )
+//! ```
+//!
+//! The scenario is kept as a [`Scenario`] resource. Its flight trains are
+//! spawned in the root grid at startup.
+
+use std::path::{Path, PathBuf};
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{LinearVelocity, Physics, Position},
+};
+use bevy::{math::DVec3, prelude::*};
+use big_space::prelude::*;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    area::square_meter,
+    length::meter,
+    mass::kilogram,
+    mass_rate::kilogram_per_second,
+    pressure::pascal,
+    ratio::ratio,
+};
+
+use crate::{
+    atmosphere::{Atmosphere, AtmosphereSource, OutOfRange},
+    ballast::{Ballast, BallastRule},
+    constants::{EARTH_RADIUS_M, PI},
+    core::SimState,
+    fault::FaultPolicy,
+    flight_train::{
+        Balloon, BalloonConfig, Elasticity, FlightTrain, LineConfig, ParachuteConfig, Payload,
+        PayloadConfig,
+    },
+    grid::{Precision, RootGrid},
+    humidity::{Humidity, Moisture},
+    ideal_gas::{GasSpecies, IdealGas},
+    material_properties::{MaterialProperties, Skin},
+    properties::Properties,
+    quantity::{Area, Length, Mass, MassRate, Pressure},
+    replay::ReplayConfig,
+    telemetry::TelemetryConfig,
+    turbulence::Turbulence,
+    vent::VentValve,
+    water::{Afloat, WaterBody, WaterExtent},
+    wind::Wind,
+};
+
+/// Payloads must climb this far (m) above the launch site before they count
+/// as landed when they come back down.
+const LANDING_CLEARANCE: Scalar = 10.0;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Airborne>();
+    app.register_type::<Landed>();
+    app.init_resource::<Scenario>();
+    // The root grid is spawned during startup, so wait until it exists.
+    app.add_systems(PostStartup, spawn_scenario);
+    app.add_event::<PayloadLanded>();
+    app.add_event::<StopConditionMet>();
+    app.add_systems(
+        FixedUpdate,
+        (detect_landings, check_stop_conditions)
+            .chain()
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// A marker component for a payload that has climbed clear of the launch site.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Airborne;
+
+/// A marker component for a payload that has come back down to the ground.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Landed;
+
+/// Sent when a payload comes back down to the altitude of the launch site.
+#[derive(Event, Debug, Clone)]
+pub struct PayloadLanded {
+    pub entity: Entity,
+    /// Position (m) of the payload when it landed.
+    pub position: Vector,
+}
+
+/// Sent when a stop condition ends the run.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StopConditionMet(pub StopCondition);
+
+/// A complete description of a simulation.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    /// Calendar date and time (UTC) when the simulation starts.
+    pub epoch: DateTime<Utc>,
+    pub launch_site: LaunchSite,
+    /// Seed for every random number drawn during the simulation.
+    #[serde(default)]
+    pub seed: u64,
+    /// The standard atmosphere, or a warmer or colder day like
+    /// `StandardOffset(15.0)`, `Supplement(SubarcticWinter)` or
+    /// `MilStd210(Hot)`. `Empirical((f107: 150.0, f107_average: 150.0, ap: 4.0))`
+    /// changes with the place and date of the flight.
+    #[serde(default)]
+    pub atmosphere: AtmosphereSource,
+    /// What the atmosphere gives above and below the altitudes it covers.
+    #[serde(default)]
+    pub atmosphere_out_of_range: OutOfRange,
+    /// Bake the atmosphere into a table with a sample every this many meters
+    /// of altitude, like `Some(10.0)`, to make queries faster. Not for the
+    /// `Empirical` atmosphere, which changes with place and time.
+    #[serde(default)]
+    pub atmosphere_table: Option<Scalar>,
+    /// Water vapor in the air. Dry unless given.
+    #[serde(default)]
+    pub humidity: Humidity,
+    #[serde(default)]
+    pub wind: Wind,
+    /// Gusts on top of the wind, like `Some((spectrum: Dryden, intensity: Light))`.
+    /// Calm unless given.
+    #[serde(default)]
+    pub turbulence: Option<Turbulence>,
+    /// Oceans and lakes. A launch site at sea level has no water unless it is
+    /// given here.
+    #[serde(default)]
+    pub water: Vec<WaterBody>,
+    /// Gases in addition to the built-in ones.
+    #[serde(default)]
+    pub gases: Vec<GasSpecies>,
+    /// Materials in addition to the built-in ones.
+    #[serde(default)]
+    pub materials: Vec<MaterialProperties>,
+    pub balloons: Vec<BalloonScenario>,
+    /// The simulation stops when any of these conditions is met.
+    #[serde(default)]
+    pub stop_conditions: Vec<StopCondition>,
+    /// Whether each kind of fault stops the simulation or is only logged.
+    #[serde(default)]
+    pub faults: FaultPolicy,
+    #[serde(default)]
+    pub outputs: Outputs,
+    /// Recorded flights to replay next to the simulated balloons.
+    #[serde(default)]
+    pub replays: Vec<ReplayConfig>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            name: "Default".to_string(),
+            epoch: Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+            launch_site: LaunchSite::default(),
+            seed: 0,
+            atmosphere: AtmosphereSource::default(),
+            atmosphere_out_of_range: OutOfRange::default(),
+            atmosphere_table: None,
+            humidity: Humidity::default(),
+            wind: Wind::default(),
+            turbulence: None,
+            water: Vec::new(),
+            gases: Vec::new(),
+            materials: Vec::new(),
+            balloons: vec![BalloonScenario::default()],
+            stop_conditions: vec![StopCondition::Landed],
+            faults: FaultPolicy::default(),
+            outputs: Outputs::default(),
+            replays: Vec::new(),
+        }
+    }
+}
+
+/// Where the flight starts.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LaunchSite {
+    /// Geodetic latitude (degrees, north positive).
+    pub latitude: f64,
+    /// Geodetic longitude (degrees, east positive).
+    pub longitude: f64,
+    /// Altitude (m) above mean sea level.
+    pub altitude: Scalar,
+}
+
+impl LaunchSite {
+    /// Position (m) in world coordinates of a point given by its latitude and
+    /// longitude (degrees) and its altitude (m) above mean sea level. The
+    /// Earth is treated as flat around the launch site, which is accurate to
+    /// well within a GPS fix over a few hundred kilometers.
+    pub fn local_position(&self, latitude: f64, longitude: f64, altitude: Scalar) -> Vector {
+        let radius = EARTH_RADIUS_M.get::<meter>() as f64;
+        let north = (latitude - self.latitude).to_radians() * radius;
+        // Take the short way around the antimeridian.
+        let longitude = (longitude - self.longitude + 180.0).rem_euclid(360.0) - 180.0;
+        let east = longitude.to_radians() * radius * self.latitude.to_radians().cos();
+        Vector::new(east as Scalar, altitude, -north as Scalar)
+    }
+
+    /// Latitude and longitude (degrees) of a position (m) in world
+    /// coordinates. The inverse of [`LaunchSite::local_position`].
+    pub fn coordinates(&self, position: Vector) -> (f64, f64) {
+        let radius = f64::from(EARTH_RADIUS_M.get::<meter>());
+        let latitude = self.latitude + (-f64::from(position.z) / radius).to_degrees();
+        let longitude = self.longitude
+            + (f64::from(position.x) / (radius * self.latitude.to_radians().cos())).to_degrees();
+        (latitude, (longitude + 180.0).rem_euclid(360.0) - 180.0)
+    }
+}
+
+/// A flight train: a balloon, an optional parachute and a payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalloonScenario {
+    pub name: String,
+    pub envelope: EnvelopeScenario,
+    pub lift_gas: LiftGasScenario,
+    #[serde(default)]
+    pub parachute: Option<ParachuteScenario>,
+    pub payload: PayloadScenario,
+    #[serde(default)]
+    pub ballast: Option<BallastScenario>,
+    #[serde(default)]
+    pub vent_valve: Option<VentValveScenario>,
+    #[serde(default)]
+    pub upper_line: LineScenario,
+    #[serde(default)]
+    pub rigging_line: LineScenario,
+    /// Offset (m) of the flight train from the launch site.
+    #[serde(default)]
+    pub offset: [Scalar; 3],
+}
+
+impl Default for BalloonScenario {
+    fn default() -> Self {
+        BalloonScenario {
+            name: "Balloon".to_string(),
+            envelope: EnvelopeScenario::default(),
+            lift_gas: LiftGasScenario::default(),
+            parachute: Some(ParachuteScenario::default()),
+            payload: PayloadScenario::default(),
+            ballast: None,
+            vent_valve: None,
+            upper_line: LineScenario::default(),
+            rigging_line: LineScenario::default(),
+            offset: [0.0; 3],
+        }
+    }
+}
+
+impl BalloonScenario {
+    /// Mass (kg) that the lift gas can carry per kilogram of gas. `None` if
+    /// the gas is unknown or no lighter than air.
+    fn lift_per_mass(&self, properties: &Properties) -> Option<Scalar> {
+        let species = properties.gas(&self.lift_gas.species)?;
+        let air = GasSpecies::air();
+        // Lift and gas mass both scale with the volume, at any temperature and
+        // pressure shared by the gas and the air around it.
+        let lift_per_mass = (air.molar_mass / species.molar_mass).get::<ratio>() - 1.0;
+        (lift_per_mass > 0.0).then_some(lift_per_mass)
+    }
+
+    /// Mass (kg) of everything the lift gas carries.
+    pub fn system_mass(&self) -> Scalar {
+        self.envelope.mass
+            + self.payload.mass
+            + self.parachute.as_ref().map_or(0.0, |parachute| parachute.mass)
+            + self.ballast.as_ref().map_or(0.0, |ballast| ballast.mass)
+    }
+
+    /// Free lift (kg) at launch: the lift of the gas beyond what it takes to
+    /// carry the flight train. `None` if the gas is unknown.
+    pub fn free_lift(&self, properties: &Properties) -> Option<Scalar> {
+        let lift_per_mass = self.lift_per_mass(properties)?;
+        Some(self.lift_gas.mass * lift_per_mass - self.system_mass())
+    }
+
+    /// Change the mass of lift gas to give some free lift (kg). Does nothing
+    /// if the gas is unknown.
+    pub fn set_free_lift(&mut self, free_lift: Scalar, properties: &Properties) {
+        if let Some(lift_per_mass) = self.lift_per_mass(properties) {
+            self.lift_gas.mass = (self.system_mass() + free_lift) / lift_per_mass;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvelopeScenario {
+    /// Name of the envelope material.
+    pub material: String,
+    /// Thickness (m) of the envelope material.
+    #[serde(default = "EnvelopeScenario::default_thickness")]
+    pub thickness: Scalar,
+    /// Mass (kg) of the envelope.
+    pub mass: Scalar,
+    /// Diameter (m) where the envelope bursts. It never bursts if omitted.
+    #[serde(default)]
+    pub burst_diameter: Option<Scalar>,
+    #[serde(default = "EnvelopeScenario::default_drag_coefficient")]
+    pub drag_coefficient: Scalar,
+    /// Young's modulus (Pa) of the envelope as it stretches. The envelope
+    /// stretches freely, without squeezing the gas, if omitted.
+    #[serde(default)]
+    pub elasticity: Option<Scalar>,
+}
+
+impl EnvelopeScenario {
+    fn default_thickness() -> Scalar {
+        0.0001
+    }
+
+    fn default_drag_coefficient() -> Scalar {
+        0.3
+    }
+
+    /// Elasticity of this envelope with some Young's modulus (Pa). The
+    /// unstretched size of the envelope comes from its mass, thickness and
+    /// the density of its material.
+    pub fn elasticity(&self, modulus: Scalar, material: &MaterialProperties) -> Elasticity {
+        let area = self.mass / (material.density * self.thickness);
+        Elasticity {
+            modulus: Pressure::new::<pascal>(modulus),
+            thickness: Length::new::<meter>(self.thickness),
+            unstretched_radius: Length::new::<meter>((area / (4.0 * PI)).sqrt()),
+        }
+    }
+}
+
+impl Default for EnvelopeScenario {
+    fn default() -> Self {
+        EnvelopeScenario {
+            material: "Rubber".to_string(),
+            thickness: EnvelopeScenario::default_thickness(),
+            mass: 1.2,
+            burst_diameter: None,
+            drag_coefficient: EnvelopeScenario::default_drag_coefficient(),
+            elasticity: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiftGasScenario {
+    /// Name or abbreviation of the gas species.
+    pub species: String,
+    /// Mass (kg) of gas in the balloon at launch.
+    pub mass: Scalar,
+    /// Rate (kg/s) that gas leaks through the envelope.
+    #[serde(default)]
+    pub leak_rate: Scalar,
+}
+
+impl Default for LiftGasScenario {
+    fn default() -> Self {
+        LiftGasScenario {
+            species: "Helium".to_string(),
+            mass: 0.6,
+            leak_rate: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParachuteScenario {
+    /// Mass (kg) of the parachute.
+    pub mass: Scalar,
+    /// Diameter (m) of the inflated canopy.
+    pub diameter: Scalar,
+    #[serde(default = "ParachuteScenario::default_drag_coefficient")]
+    pub drag_coefficient: Scalar,
+}
+
+impl ParachuteScenario {
+    fn default_drag_coefficient() -> Scalar {
+        1.5
+    }
+}
+
+impl Default for ParachuteScenario {
+    fn default() -> Self {
+        ParachuteScenario {
+            mass: 0.1,
+            diameter: 1.2,
+            drag_coefficient: ParachuteScenario::default_drag_coefficient(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadScenario {
+    /// Mass (kg) of the payload, not including ballast.
+    pub mass: Scalar,
+    /// Edge length (m) of the payload box.
+    #[serde(default = "PayloadScenario::default_size")]
+    pub size: Scalar,
+    #[serde(default = "PayloadScenario::default_drag_coefficient")]
+    pub drag_coefficient: Scalar,
+}
+
+impl PayloadScenario {
+    fn default_size() -> Scalar {
+        0.3
+    }
+
+    fn default_drag_coefficient() -> Scalar {
+        1.05
+    }
+}
+
+impl Default for PayloadScenario {
+    fn default() -> Self {
+        PayloadScenario {
+            mass: 1.0,
+            size: PayloadScenario::default_size(),
+            drag_coefficient: PayloadScenario::default_drag_coefficient(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BallastScenario {
+    /// Mass (kg) of ballast at launch.
+    pub mass: Scalar,
+    /// Maximum rate (kg/s) that ballast can be released.
+    pub flow_rate: Scalar,
+    /// Rules that release ballast automatically.
+    #[serde(default)]
+    pub rules: Vec<BallastRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VentValveScenario {
+    /// Area (m²) of the valve orifice.
+    pub orifice_area: Scalar,
+    pub discharge_coefficient: Scalar,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineScenario {
+    /// Length (m) of the line.
+    pub length: Scalar,
+    /// Axial stiffness (N/m) of the line.
+    pub stiffness: Scalar,
+}
+
+impl Default for LineScenario {
+    fn default() -> Self {
+        let line = LineConfig::default();
+        LineScenario {
+            length: line.length.get::<meter>(),
+            stiffness: line.stiffness,
+        }
+    }
+}
+
+/// Conditions that stop the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StopCondition {
+    /// Stop after this much simulated time (s).
+    Duration(Scalar),
+    /// Stop when any balloon rises above this altitude (m).
+    AltitudeAbove(Scalar),
+    /// Stop when every payload has come back down to the launch site
+    /// altitude.
+    Landed,
+}
+
+/// Where the results of the simulation are written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outputs {
+    pub directory: PathBuf,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+}
+
+impl Default for Outputs {
+    fn default() -> Self {
+        Outputs {
+            directory: PathBuf::from("output"),
+            telemetry: TelemetryConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    UnknownGas { balloon: String, gas: String },
+    UnknownMaterial { balloon: String, material: String },
+    /// A value is outside the range that makes sense for it.
+    Invalid { field: String, reason: String },
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioError::Io(e) => write!(f, "could not read scenario: {}", e),
+            ScenarioError::Parse(e) => write!(f, "could not parse scenario: {}", e),
+            ScenarioError::UnknownGas { balloon, gas } => {
+                write!(f, "balloon '{}' uses unknown gas '{}'", balloon, gas)
+            }
+            ScenarioError::UnknownMaterial { balloon, material } => {
+                write!(f, "balloon '{}' uses unknown material '{}'", balloon, material)
+            }
+            ScenarioError::Invalid { field, reason } => write!(f, "{} {}", field, reason),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(e: std::io::Error) -> Self {
+        ScenarioError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for ScenarioError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        ScenarioError::Parse(e)
+    }
+}
+
+impl Scenario {
+    /// Read a scenario from a RON file and check that it is valid.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let text = std::fs::read_to_string(path)?;
+        Scenario::from_ron(&text)
+    }
+
+    /// Parse a scenario from RON text and check that it is valid.
+    pub fn from_ron(text: &str) -> Result<Self, ScenarioError> {
+        let scenario: Scenario = ron::from_str(text)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Write the scenario as RON text.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    /// Built-in gases and materials plus the ones defined by this scenario.
+    pub fn properties(&self) -> Properties {
+        let mut properties = Properties::builtin();
+        properties.extend(Properties {
+            gases: self.gases.clone(),
+            materials: self.materials.clone(),
+        });
+        properties
+    }
+
+    /// The atmosphere described by this scenario, baked into a table if the
+    /// scenario asks for one.
+    pub fn atmosphere(&self) -> Atmosphere {
+        let atmosphere = self.untabulated_atmosphere();
+        match self.atmosphere_table {
+            Some(step) => atmosphere.with_table(step),
+            None => atmosphere,
+        }
+    }
+
+    /// The atmosphere without a table, for the few queries made while
+    /// building flight trains.
+    fn untabulated_atmosphere(&self) -> Atmosphere {
+        Atmosphere::new(self.atmosphere)
+            .with_out_of_range(self.atmosphere_out_of_range)
+            .with_humidity(self.humidity.clone())
+            .with_origin(self.launch_site.latitude, self.launch_site.longitude)
+            .with_time(self.epoch)
+    }
+
+    /// Check that every reference resolves and every value makes sense.
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        let properties = self.properties();
+        if !(Atmosphere::MIN_ALTITUDE..Atmosphere::MAX_ALTITUDE)
+            .contains(&self.launch_site.altitude)
+        {
+            return Err(invalid("launch_site.altitude", "is outside the atmosphere"));
+        }
+        if let Some(step) = self.atmosphere_table {
+            positive("atmosphere_table", step)?;
+            if self.atmosphere.varies_with_place_and_time() {
+                return Err(invalid(
+                    "atmosphere_table",
+                    "can't hold an atmosphere that changes with place and time",
+                ));
+            }
+        }
+        match &self.humidity {
+            Humidity::Dry => {}
+            Humidity::Standard(relative_humidity) => {
+                fraction("humidity.Standard", *relative_humidity)?;
+            }
+            Humidity::Sounding(levels) => {
+                for level in levels {
+                    match level.moisture {
+                        Moisture::RelativeHumidity(relative_humidity) => {
+                            fraction("humidity.RelativeHumidity", relative_humidity)?;
+                        }
+                        Moisture::Dewpoint(dewpoint) => positive("humidity.Dewpoint", dewpoint)?,
+                    }
+                }
+            }
+        }
+        for water in &self.water {
+            positive("water.density", water.density)?;
+            match water.extent {
+                // Water everywhere at any other altitude would flood the
+                // world, or lie under all of it. A lake needs an extent.
+                WaterExtent::Everywhere if water.surface != 0.0 => {
+                    return Err(invalid(
+                        "water.extent",
+                        "must be given for water whose surface is not at sea level",
+                    ));
+                }
+                WaterExtent::Circle { radius, .. } => positive("water.Circle.radius", radius)?,
+                _ => {}
+            }
+        }
+        for stop_condition in &self.stop_conditions {
+            if let StopCondition::Duration(duration) = stop_condition {
+                positive("stop_conditions.Duration", *duration)?;
+            }
+        }
+        for balloon in &self.balloons {
+            self.flight_train(balloon, &properties)?;
+        }
+        for replay in &self.replays {
+            if !self.balloons.iter().any(|balloon| balloon.name == replay.balloon) {
+                return Err(invalid("replays.balloon", "does not name a balloon"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the flight train described by one of this scenario's balloons.
+    pub fn flight_train(
+        &self,
+        balloon: &BalloonScenario,
+        properties: &Properties,
+    ) -> Result<FlightTrain, ScenarioError> {
+        let field = |name: &str| format!("balloons[{}].{}", balloon.name, name);
+        let species = properties
+            .gas(&balloon.lift_gas.species)
+            .ok_or_else(|| ScenarioError::UnknownGas {
+                balloon: balloon.name.clone(),
+                gas: balloon.lift_gas.species.clone(),
+            })?;
+        let material = properties
+            .material(&balloon.envelope.material)
+            .ok_or_else(|| ScenarioError::UnknownMaterial {
+                balloon: balloon.name.clone(),
+                material: balloon.envelope.material.clone(),
+            })?;
+        positive(&field("envelope.mass"), balloon.envelope.mass)?;
+        positive(&field("envelope.thickness"), balloon.envelope.thickness)?;
+        if let Some(burst_diameter) = balloon.envelope.burst_diameter {
+            positive(&field("envelope.burst_diameter"), burst_diameter)?;
+        }
+        positive(&field("lift_gas.mass"), balloon.lift_gas.mass)?;
+        if balloon.lift_gas.leak_rate < 0.0 || !balloon.lift_gas.leak_rate.is_finite() {
+            return Err(invalid(&field("lift_gas.leak_rate"), "must not be negative"));
+        }
+        let elasticity = match balloon.envelope.elasticity {
+            Some(modulus) => {
+                positive(&field("envelope.elasticity"), modulus)?;
+                positive(&field("material.density"), material.density)?;
+                Some(balloon.envelope.elasticity(modulus, material))
+            }
+            None => None,
+        };
+        positive(&field("payload.mass"), balloon.payload.mass)?;
+        positive(&field("payload.size"), balloon.payload.size)?;
+        positive(&field("upper_line.length"), balloon.upper_line.length)?;
+        positive(&field("upper_line.stiffness"), balloon.upper_line.stiffness)?;
+        positive(&field("rigging_line.length"), balloon.rigging_line.length)?;
+        positive(&field("rigging_line.stiffness"), balloon.rigging_line.stiffness)?;
+
+        let site = Vector::Y * self.launch_site.altitude;
+        let atmosphere = self.untabulated_atmosphere();
+        let mut train = FlightTrain::default()
+            .with_balloon(BalloonConfig {
+                envelope_mass: Mass::new::<kilogram>(balloon.envelope.mass),
+                burst_diameter: balloon.envelope.burst_diameter.map(Length::new::<meter>),
+                drag_coefficient: balloon.envelope.drag_coefficient,
+                lift_gas: IdealGas::new(
+                    species.clone(),
+                    atmosphere.temperature(site),
+                    atmosphere.pressure(site),
+                    Mass::new::<kilogram>(balloon.lift_gas.mass),
+                ),
+                leak_rate: MassRate::new::<kilogram_per_second>(balloon.lift_gas.leak_rate),
+                elasticity,
+                vent_valve: None,
+            })
+            .with_payload(PayloadConfig {
+                mass: Mass::new::<kilogram>(balloon.payload.mass),
+                size: Length::new::<meter>(balloon.payload.size),
+                drag_coefficient: balloon.payload.drag_coefficient,
+                ballast: None,
+            })
+            .with_upper_line(line(&balloon.upper_line))
+            .with_rigging_line(line(&balloon.rigging_line))
+            .without_parachute();
+        if let Some(parachute) = &balloon.parachute {
+            positive(&field("parachute.mass"), parachute.mass)?;
+            positive(&field("parachute.diameter"), parachute.diameter)?;
+            train = train.with_parachute(ParachuteConfig {
+                mass: Mass::new::<kilogram>(parachute.mass),
+                diameter: Length::new::<meter>(parachute.diameter),
+                drag_coefficient: parachute.drag_coefficient,
+            });
+        }
+        if let Some(ballast) = &balloon.ballast {
+            positive(&field("ballast.mass"), ballast.mass)?;
+            positive(&field("ballast.flow_rate"), ballast.flow_rate)?;
+            let mut carried = Ballast::new(
+                Mass::new::<kilogram>(ballast.mass),
+                MassRate::new::<kilogram_per_second>(ballast.flow_rate),
+            );
+            carried.rules = ballast.rules.clone();
+            train = train.with_ballast(carried);
+        }
+        if let Some(vent_valve) = &balloon.vent_valve {
+            positive(&field("vent_valve.orifice_area"), vent_valve.orifice_area)?;
+            positive(
+                &field("vent_valve.discharge_coefficient"),
+                vent_valve.discharge_coefficient,
+            )?;
+            train = train.with_vent_valve(VentValve::new(
+                Area::new::<square_meter>(vent_valve.orifice_area),
+                vent_valve.discharge_coefficient,
+            ));
+        }
+        Ok(train)
+    }
+}
+
+fn line(line: &LineScenario) -> LineConfig {
+    LineConfig {
+        length: Length::new::<meter>(line.length),
+        stiffness: line.stiffness,
+    }
+}
+
+fn invalid(field: &str, reason: &str) -> ScenarioError {
+    ScenarioError::Invalid {
+        field: field.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn positive(field: &str, value: Scalar) -> Result<(), ScenarioError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(field, "must be positive"))
+    }
+}
+
+fn fraction(field: &str, value: Scalar) -> Result<(), ScenarioError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(invalid(field, "must be between 0 and 1"))
+    }
+}
+
+/// Spawn the flight trains of the scenario in the root grid. The bottom of
+/// each payload starts at the altitude of the launch site.
+fn spawn_scenario(
+    mut commands: Commands,
+    scenario: Res<Scenario>,
+    root_grid: Query<(Entity, &Grid<Precision>), With<RootGrid>>,
+) {
+    let (root_grid_id, root_grid) = root_grid.single();
+    let properties = scenario.properties();
+    info!("loading scenario '{}'", scenario.name);
+    for balloon in &scenario.balloons {
+        let train = match scenario.flight_train(balloon, &properties) {
+            Ok(train) => train,
+            Err(e) => {
+                error!("skipping balloon: {}", e);
+                continue;
+            }
+        };
+        let [x, y, z] = balloon.offset;
+        let position = DVec3::new(
+            x as f64,
+            (scenario.launch_site.altitude + y + train.height().get::<meter>()) as f64,
+            z as f64,
+        );
+        let entities = train.spawn(&mut commands, root_grid_id, root_grid, position);
+        if let Some(material) = properties.material(&balloon.envelope.material) {
+            commands
+                .entity(entities.balloon)
+                .insert(Skin::new(material, balloon.envelope.thickness));
+        }
+        commands
+            .entity(entities.balloon)
+            .insert(Name::new(balloon.name.clone()));
+    }
+}
+
+/// Mark payloads that have come back down to the launch site altitude, or
+/// onto water, after climbing away from the launch site.
+fn detect_landings(
+    mut commands: Commands,
+    scenario: Res<Scenario>,
+    payloads: Query<
+        (Entity, &Position, &LinearVelocity, Has<Airborne>, Has<Afloat>),
+        (With<Payload>, Without<Landed>),
+    >,
+    mut landings: EventWriter<PayloadLanded>,
+) {
+    let ground = scenario.launch_site.altitude;
+    for (entity, position, velocity, airborne, afloat) in payloads.iter() {
+        // Water can be above the launch site, like a lake in the hills.
+        if airborne && (position.y <= ground || afloat) && velocity.y <= 0.0 {
+            info!("payload landed at ({:.0}, {:.0})", position.x, position.z);
+            commands.entity(entity).insert(Landed);
+            landings.send(PayloadLanded {
+                entity,
+                position: position.0,
+            });
+        } else if !airborne && position.y > ground + LANDING_CLEARANCE {
+            commands.entity(entity).insert(Airborne);
+        }
+    }
+}
+
+fn check_stop_conditions(
+    scenario: Res<Scenario>,
+    physics_time: Res<Time<Physics>>,
+    balloons: Query<&Position, With<Balloon>>,
+    payloads: Query<Has<Landed>, With<Payload>>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut stops: EventWriter<StopConditionMet>,
+) {
+    for stop_condition in &scenario.stop_conditions {
+        let stop = match stop_condition {
+            StopCondition::Duration(duration) => {
+                physics_time.elapsed_secs_f64() >= *duration as f64
+            }
+            StopCondition::AltitudeAbove(altitude) => {
+                balloons.iter().any(|position| position.y > *altitude)
+            }
+            StopCondition::Landed => !payloads.is_empty() && payloads.iter().all(|landed| landed),
+        };
+        if stop {
+            info!("stop condition met: {:?}", stop_condition);
+            next_state.set(SimState::Stopped);
+            stops.send(StopConditionMet(*stop_condition));
+            return;
+        }
+    }
+}
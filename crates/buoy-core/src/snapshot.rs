@@ -0,0 +1,350 @@
+//! Save the state of a running simulation and restore it later.
+//!
+//! A [`Snapshot`] holds every simulated entity (balloons, parachutes, payloads
+//! and the lines between them) with its rigid body state, lift gas, ballast,
+//! valves and grid cell, along with the [`SimClock`]. It is written to a file
+//! as a Bevy scene in RON, so it can be read and edited by hand.
+//!
+//! Restoring a snapshot replaces the flight trains in the world with the ones
+//! from the snapshot and rewinds physics time to when the snapshot was taken.
+//! This makes it possible to branch experiments from the middle of a flight:
+//! save once, then restore and try something different each time.
+//!
+//! Colliders and joints can't be serialized, so they are rebuilt from the
+//! [`Shape`] of each element and from each [`RiggingLine`]. Along with the
+//! entities, a snapshot holds the time left over toward the next fixed step,
+//! the state of the [`SimRng`] and the [`Gust`] of each body, so a restored run
+//! continues exactly like the original one would have. That holds for runs
+//! that only depend on the state listed here. Some state is not part of a
+//! snapshot:
+//!
+//! - The [`Scenario`] and the [`Wind`](crate::wind::Wind) it describes. Restore
+//!   a snapshot in a simulation that runs the same scenario.
+//! - [`Controller`]s that run a custom control law, which is a trait object.
+//!   They are left behind, so the balloon flies on uncontrolled after a
+//!   restore. Controllers that run a built-in law are saved as a
+//!   [`SavedController`] and pick up where they left off.
+//! - The [`TelemetryLog`]. Recording starts over from the restored state.
+//! - The [`Faults`] of the run so far, which are cleared. A faulted simulation
+//!   runs again from the restored state.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use avian3d::prelude::*;
+use bevy::{
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    reflect::Struct,
+    scene::{serde::SceneDeserializer, DynamicScene, DynamicSceneBuilder, SceneSpawnError},
+};
+use big_space::prelude::*;
+use serde::{de::DeserializeSeed, Deserialize, Serialize};
+
+use crate::{
+    ballast::Ballast,
+    control::{Controller, SavedController},
+    core::SimState,
+    determinism::{SimRng, SimRngState},
+    fault::Faults,
+    flight_train::{Balloon, Parachute, Payload, RiggingLine, Shape},
+    forces::Drag,
+    grid::{Precision, RootGrid},
+    ideal_gas::IdealGas,
+    material_properties::Skin,
+    scenario::{Airborne, Landed, Scenario},
+    telemetry::{file_stem, TelemetryLog},
+    time::SimClock,
+    turbulence::Gust,
+    vent::VentValve,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<GridCell<Precision>>();
+    app.register_type::<FixedTimeState>();
+    app.add_event::<SnapshotCommand>();
+    // Restoring replaces entities, so wait until every other system is done
+    // with them for this frame.
+    app.add_systems(Last, handle_snapshot_commands);
+}
+
+/// Save or restore the simulation. Handled at the end of the frame.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotCommand {
+    Save(PathBuf),
+    Load(PathBuf),
+}
+
+/// The saved state of a simulation.
+pub struct Snapshot {
+    pub scene: DynamicScene,
+}
+
+/// The fixed timestep clock when a snapshot was taken.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Resource, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FixedTimeState {
+    pub elapsed: Duration,
+    /// Time left over toward the next fixed step.
+    pub overstep: Duration,
+}
+
+impl Snapshot {
+    /// Capture the current state of the simulation.
+    pub fn capture(world: &World) -> Self {
+        let mut scene = DynamicSceneBuilder::from_world(world)
+            .deny_all()
+            .allow_component::<Name>()
+            .allow_component::<Transform>()
+            .allow_component::<GridCell<Precision>>()
+            .allow_component::<RigidBody>()
+            .allow_component::<Position>()
+            .allow_component::<Rotation>()
+            .allow_component::<LinearVelocity>()
+            .allow_component::<AngularVelocity>()
+            .allow_component::<GravityScale>()
+            .allow_component::<Mass>()
+            .allow_component::<Shape>()
+            .allow_component::<Balloon>()
+            .allow_component::<Parachute>()
+            .allow_component::<Payload>()
+            .allow_component::<RiggingLine>()
+            .allow_component::<IdealGas>()
+            .allow_component::<Drag>()
+            .allow_component::<Ballast>()
+            .allow_component::<VentValve>()
+            .allow_component::<Skin>()
+            .allow_component::<Airborne>()
+            .allow_component::<Landed>()
+            .allow_component::<Gust>()
+            .allow_resource::<SimClock>()
+            .extract_entities(sim_entities(world).into_iter())
+            .extract_resources()
+            .build();
+        for entity in &mut scene.entities {
+            if let Some(saved) = world
+                .get::<Controller>(entity.entity)
+                .and_then(Controller::save)
+            {
+                entity.components.push(Box::new(saved));
+            }
+        }
+        // These are saved in a different form than they live in the world.
+        let fixed_time = world.resource::<Time<Fixed>>();
+        scene.resources.push(Box::new(FixedTimeState {
+            elapsed: fixed_time.elapsed(),
+            overstep: fixed_time.overstep(),
+        }));
+        scene
+            .resources
+            .push(Box::new(world.resource::<SimRng>().state()));
+        Snapshot { scene }
+    }
+
+    /// Physics time when the snapshot was taken.
+    pub fn clock(&self) -> Option<SimClock> {
+        self.resource::<SimClock>()
+    }
+
+    /// A resource saved in the snapshot.
+    fn resource<R: Resource + Reflect + Copy>(&self) -> Option<R> {
+        self.scene
+            .resources
+            .iter()
+            .find_map(|resource| resource.try_downcast_ref::<R>().copied())
+    }
+
+    /// Write the snapshot as a RON scene.
+    pub fn to_ron(&self, world: &World) -> Result<String, SnapshotError> {
+        let registry = world.resource::<AppTypeRegistry>().read();
+        Ok(self.scene.serialize(&registry)?)
+    }
+
+    /// Read a snapshot from a RON scene.
+    pub fn from_ron(text: &str, world: &World) -> Result<Self, SnapshotError> {
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let mut deserializer = ron::de::Deserializer::from_str(text)?;
+        let scene = SceneDeserializer {
+            type_registry: &registry,
+        }
+        .deserialize(&mut deserializer)?;
+        Ok(Snapshot { scene })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, world: &World) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, self.to_ron(world)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>, world: &World) -> Result<Self, SnapshotError> {
+        let text = std::fs::read_to_string(path)?;
+        Snapshot::from_ron(&text, world)
+    }
+
+    /// Replace the simulated entities in the world with the ones in the
+    /// snapshot and rewind physics time to when it was taken. Returns the
+    /// entity that each entity in the snapshot was restored as.
+    pub fn restore(&self, world: &mut World) -> Result<EntityHashMap<Entity>, SnapshotError> {
+        let clock = self.clock().ok_or(SnapshotError::MissingClock)?;
+        let root_grid = world
+            .query_filtered::<Entity, With<RootGrid>>()
+            .get_single(world)
+            .map_err(|_| SnapshotError::MissingRootGrid)?;
+
+        for entity in sim_entities(world) {
+            world.entity_mut(entity).despawn_recursive();
+        }
+
+        let mut entity_map = EntityHashMap::default();
+        self.scene.write_to_world(world, &mut entity_map)?;
+
+        // Rebuild the components that can't be serialized.
+        for scene_entity in &self.scene.entities {
+            let mut entity = world.entity_mut(entity_map[&scene_entity.entity]);
+            if let Some(shape) = entity.get::<Shape>().copied() {
+                entity.insert((
+                    shape.collider(),
+                    NoAutoMass,
+                    ExternalForce::default().with_persistence(false),
+                ));
+            }
+            if let Some(line) = entity.get::<RiggingLine>().copied() {
+                entity.insert(line.joint());
+            }
+            if let Some(saved) = entity.take::<SavedController>() {
+                entity.insert(Controller::from(saved));
+            }
+            if entity.contains::<RigidBody>() {
+                entity.set_parent(root_grid);
+            }
+        }
+
+        let mut physics_time = world.resource_mut::<Time<Physics>>();
+        let mut restored = Time::new_with(physics_time.context().clone());
+        restored.advance_to(clock.elapsed);
+        *physics_time = restored;
+
+        // Writing the scene inserted the saved states as resources of their
+        // own. Move them to where they belong.
+        if let Some(state) = world.remove_resource::<FixedTimeState>() {
+            let mut fixed_time = world.resource_mut::<Time<Fixed>>();
+            let mut restored = Time::new_with(*fixed_time.context());
+            restored.advance_to(state.elapsed);
+            // The overstep only grows as frames pass, so set it directly.
+            if let Some(overstep) = restored.context_mut().field_mut("overstep") {
+                overstep.apply(&state.overstep);
+            }
+            *fixed_time = restored;
+        }
+        if let Some(state) = world.remove_resource::<SimRngState>() {
+            world.insert_resource(SimRng::from_state(&state));
+        }
+
+        *world.resource_mut::<TelemetryLog>() = TelemetryLog::default();
+        world.resource_mut::<Faults>().clear();
+        // The faults that stopped the run are gone with the state they came
+        // from, so the restored run goes on.
+        if *world.resource::<State<SimState>>().get() == SimState::Faulted {
+            world
+                .resource_mut::<NextState<SimState>>()
+                .set(SimState::Running);
+        }
+
+        info!("restored snapshot taken at {}", clock.format());
+        Ok(entity_map)
+    }
+
+    /// Where snapshots of a scenario are saved by default.
+    pub fn default_path(scenario: &Scenario) -> PathBuf {
+        scenario
+            .outputs
+            .directory
+            .join(format!("{}.snapshot.ron", file_stem(&scenario.name)))
+    }
+}
+
+/// Entities that make up the simulation: rigid bodies and the lines between
+/// them.
+fn sim_entities(world: &World) -> Vec<Entity> {
+    world
+        .iter_entities()
+        .filter(|entity| entity.contains::<RigidBody>() || entity.contains::<RiggingLine>())
+        .map(|entity| entity.id())
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Ron(ron::Error),
+    Spawn(SceneSpawnError),
+    /// The snapshot does not say when it was taken.
+    MissingClock,
+    /// There is no root grid to restore the entities into.
+    MissingRootGrid,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "could not access snapshot: {}", e),
+            SnapshotError::Parse(e) => write!(f, "could not parse snapshot: {}", e),
+            SnapshotError::Ron(e) => write!(f, "could not convert snapshot: {}", e),
+            SnapshotError::Spawn(e) => write!(f, "could not restore snapshot: {}", e),
+            SnapshotError::MissingClock => write!(f, "snapshot has no simulation clock"),
+            SnapshotError::MissingRootGrid => write!(f, "world has no root grid"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SnapshotError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        SnapshotError::Parse(e)
+    }
+}
+
+impl From<ron::Error> for SnapshotError {
+    fn from(e: ron::Error) -> Self {
+        SnapshotError::Ron(e)
+    }
+}
+
+impl From<SceneSpawnError> for SnapshotError {
+    fn from(e: SceneSpawnError) -> Self {
+        SnapshotError::Spawn(e)
+    }
+}
+
+fn handle_snapshot_commands(world: &mut World) {
+    let commands = world
+        .resource_mut::<Events<SnapshotCommand>>()
+        .drain()
+        .collect::<Vec<_>>();
+    for command in commands {
+        let result = match &command {
+            SnapshotCommand::Save(path) => Snapshot::capture(world).save(path, world),
+            SnapshotCommand::Load(path) => Snapshot::load(path, world)
+                .and_then(|snapshot| snapshot.restore(world))
+                .map(|_| ()),
+        };
+        match result {
+            Ok(()) => info!("{:?}", command),
+            Err(e) => error!("{:?} failed: {}", command, e),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Record time series of simulation data and write them to files.
+//!
+//! Telemetry is made of channels. A [`Channel`] samples one value from an
+//! entity, in a unit taken from `uom`. Every channel is sampled from every
+//! named rigid body at a fixed rate of simulated time, giving one column per
+//! entity and channel, like `Balloon.position_y`.
+//!
+//! When the run ends, because a stop condition is met, the simulation faults
+//! or the app exits, the recorded time series is written to CSV and
+//! (with the `parquet` feature) Parquet files. CSV columns have the unit in
+//! their header. Parquet columns have the unit in their field metadata under
+//! the `unit` key.
+//!
+//! Each sample is stamped with both the physics time and the UTC date and time
+//! from the [`SimClock`].
+
+use std::path::{Path, PathBuf};
+
+use avian3d::{
+    math::Scalar,
+    prelude::{ExternalForce, LinearVelocity, Physics, PhysicsSet, Position, RigidBody},
+};
+use bevy::{ecs::world::EntityRef, prelude::*};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    force::newton,
+    length::meter,
+    mass::kilogram,
+    pressure::pascal,
+    thermodynamic_temperature::kelvin,
+    time::second,
+    velocity::meter_per_second,
+    volume::cubic_meter,
+    Unit,
+};
+
+use crate::{
+    core::SimState,
+    ideal_gas::IdealGas,
+    scenario::{Scenario, StopConditionMet},
+    time::SimClock,
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<TelemetryChannels>();
+    app.init_resource::<TelemetryLog>();
+    app.add_systems(
+        FixedPostUpdate,
+        sample_telemetry
+            .before(PhysicsSet::Prepare)
+            .run_if(in_state(SimState::Running)),
+    );
+    // Pausing also enters `SimState::Stopped`, so the state alone doesn't
+    // say that the run is over.
+    app.add_systems(Update, write_telemetry.run_if(on_event::<StopConditionMet>));
+    app.add_systems(OnEnter(SimState::Faulted), write_telemetry);
+    app.add_systems(Last, write_telemetry.run_if(on_event::<AppExit>));
+}
+
+/// How telemetry is recorded and written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// Samples per second of simulated time.
+    pub sample_rate: f32,
+    pub csv: bool,
+    pub parquet: bool,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            sample_rate: 1.0,
+            csv: true,
+            parquet: cfg!(feature = "parquet"),
+        }
+    }
+}
+
+/// A value that can be sampled from an entity.
+pub struct Channel {
+    pub name: String,
+    /// Abbreviation of the unit that samples are in.
+    pub unit: &'static str,
+    sampler: Box<dyn Fn(&EntityRef) -> Option<Scalar> + Send + Sync>,
+}
+
+impl Channel {
+    /// A channel whose sampler returns values in the unit `N`.
+    pub fn new<N: Unit>(
+        name: impl Into<String>,
+        sampler: impl Fn(&EntityRef) -> Option<Scalar> + Send + Sync + 'static,
+    ) -> Self {
+        Channel {
+            name: name.into(),
+            unit: N::abbreviation(),
+            sampler: Box::new(sampler),
+        }
+    }
+
+    pub fn sample(&self, entity: &EntityRef) -> Option<Scalar> {
+        (self.sampler)(entity)
+    }
+}
+
+/// The channels that are recorded. Register more channels by pushing them
+/// into this resource before the simulation starts.
+#[derive(Resource)]
+pub struct TelemetryChannels(pub Vec<Channel>);
+
+impl TelemetryChannels {
+    pub fn register(&mut self, channel: Channel) {
+        self.0.push(channel);
+    }
+}
+
+impl Default for TelemetryChannels {
+    fn default() -> Self {
+        TelemetryChannels(vec![
+            Channel::new::<meter>("position_x", |e| e.get::<Position>().map(|p| p.x)),
+            Channel::new::<meter>("position_y", |e| e.get::<Position>().map(|p| p.y)),
+            Channel::new::<meter>("position_z", |e| e.get::<Position>().map(|p| p.z)),
+            Channel::new::<meter_per_second>("velocity_x", |e| {
+                e.get::<LinearVelocity>().map(|v| v.x)
+            }),
+            Channel::new::<meter_per_second>("velocity_y", |e| {
+                e.get::<LinearVelocity>().map(|v| v.y)
+            }),
+            Channel::new::<meter_per_second>("velocity_z", |e| {
+                e.get::<LinearVelocity>().map(|v| v.z)
+            }),
+            Channel::new::<kelvin>("gas_temperature", |e| {
+                e.get::<IdealGas>().map(|gas| gas.temperature.get::<kelvin>())
+            }),
+            Channel::new::<pascal>("gas_pressure", |e| {
+                e.get::<IdealGas>().map(|gas| gas.pressure.get::<pascal>())
+            }),
+            Channel::new::<cubic_meter>("gas_volume", |e| {
+                e.get::<IdealGas>().map(|gas| gas.volume().get::<cubic_meter>())
+            }),
+            Channel::new::<kilogram>("gas_mass", |e| {
+                e.get::<IdealGas>().map(|gas| gas.mass.get::<kilogram>())
+            }),
+            Channel::new::<newton>("force_x", |e| e.get::<ExternalForce>().map(|f| f.x)),
+            Channel::new::<newton>("force_y", |e| e.get::<ExternalForce>().map(|f| f.y)),
+            Channel::new::<newton>("force_z", |e| e.get::<ExternalForce>().map(|f| f.z)),
+        ])
+    }
+}
+
+/// A column of the recorded time series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    /// Name of the column, like `Balloon.position_y`.
+    pub name: String,
+    pub unit: &'static str,
+    pub values: Vec<Option<Scalar>>,
+}
+
+/// The recorded time series.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct TelemetryLog {
+    /// Simulated time (s) of each sample.
+    pub time: Vec<f64>,
+    /// Date and time of each sample.
+    pub utc: Vec<DateTime<Utc>>,
+    pub columns: Vec<Column>,
+    /// Entity and channel index that fill each column.
+    sources: Vec<(Entity, usize)>,
+    next_sample: f64,
+    /// Number of samples when the log was last written to files.
+    written: usize,
+}
+
+impl TelemetryLog {
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    /// Find a column by name.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Write the time series as CSV. The unit of each column is in its header.
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<(), TelemetryError> {
+        let mut writer = csv::Writer::from_path(path)?;
+        let mut header = vec![
+            format!("time ({})", second::abbreviation()),
+            "utc".to_string(),
+        ];
+        header.extend(
+            self.columns
+                .iter()
+                .map(|column| format!("{} ({})", column.name, column.unit)),
+        );
+        writer.write_record(&header)?;
+        for (row, time) in self.time.iter().enumerate() {
+            let mut record = vec![
+                time.to_string(),
+                self.utc[row].to_rfc3339_opts(SecondsFormat::Millis, true),
+            ];
+            record.extend(self.columns.iter().map(|column| {
+                column.values[row].map_or(String::new(), |value| value.to_string())
+            }));
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the time series as Parquet. The unit of each column is in the
+    /// `unit` metadata of its field.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, path: impl AsRef<Path>) -> Result<(), TelemetryError> {
+        use arrow_array::{
+            types::ArrowPrimitiveType, ArrayRef, Float64Array, PrimitiveArray, RecordBatch,
+            TimestampMicrosecondArray,
+        };
+        use arrow_schema::{DataType, Field, Schema, TimeUnit};
+        use parquet::arrow::ArrowWriter;
+        use std::{collections::HashMap, fs::File, sync::Arc};
+
+        // Columns are stored in the precision they were sampled in.
+        #[cfg(not(feature = "f64"))]
+        type ScalarType = arrow_array::types::Float32Type;
+        #[cfg(feature = "f64")]
+        type ScalarType = arrow_array::types::Float64Type;
+
+        let unit = |unit: &str| HashMap::from([("unit".to_string(), unit.to_string())]);
+        let mut fields = vec![
+            Field::new("time", DataType::Float64, false)
+                .with_metadata(unit(second::abbreviation())),
+            Field::new(
+                "utc",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                false,
+            ),
+        ];
+        let utc = self
+            .utc
+            .iter()
+            .map(|utc| utc.timestamp_micros())
+            .collect::<Vec<_>>();
+        let mut arrays: Vec<ArrayRef> = vec![
+            Arc::new(Float64Array::from(self.time.clone())),
+            Arc::new(TimestampMicrosecondArray::from(utc).with_timezone_utc()),
+        ];
+        for column in &self.columns {
+            fields.push(
+                Field::new(&column.name, ScalarType::DATA_TYPE, true)
+                    .with_metadata(unit(column.unit)),
+            );
+            arrays.push(Arc::new(PrimitiveArray::<ScalarType>::from(
+                column.values.clone(),
+            )));
+        }
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+
+        let mut writer = ArrowWriter::try_new(File::create(path)?, schema, None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Write the time series to every format enabled in the config. Files are
+    /// named after the scenario.
+    pub fn write_all(
+        &self,
+        directory: impl AsRef<Path>,
+        name: &str,
+        config: &TelemetryConfig,
+    ) -> Result<Vec<PathBuf>, TelemetryError> {
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory)?;
+        let stem = file_stem(name);
+        let mut written = Vec::new();
+        if config.csv {
+            let path = directory.join(format!("{}.csv", stem));
+            self.write_csv(&path)?;
+            written.push(path);
+        }
+        if config.parquet {
+            #[cfg(feature = "parquet")]
+            {
+                let path = directory.join(format!("{}.parquet", stem));
+                self.write_parquet(&path)?;
+                written.push(path);
+            }
+            #[cfg(not(feature = "parquet"))]
+            warn!("parquet output requires the `parquet` feature");
+        }
+        Ok(written)
+    }
+}
+
+/// A file name that is safe to use on any platform.
+pub fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum TelemetryError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    #[cfg(feature = "parquet")]
+    Arrow(arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+}
+
+impl std::fmt::Display for TelemetryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TelemetryError::Io(e) => write!(f, "{}", e),
+            TelemetryError::Csv(e) => write!(f, "{}", e),
+            #[cfg(feature = "parquet")]
+            TelemetryError::Arrow(e) => write!(f, "{}", e),
+            #[cfg(feature = "parquet")]
+            TelemetryError::Parquet(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TelemetryError {}
+
+impl From<std::io::Error> for TelemetryError {
+    fn from(e: std::io::Error) -> Self {
+        TelemetryError::Io(e)
+    }
+}
+
+impl From<csv::Error> for TelemetryError {
+    fn from(e: csv::Error) -> Self {
+        TelemetryError::Csv(e)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<arrow_schema::ArrowError> for TelemetryError {
+    fn from(e: arrow_schema::ArrowError) -> Self {
+        TelemetryError::Arrow(e)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for TelemetryError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        TelemetryError::Parquet(e)
+    }
+}
+
+/// Sample every channel from every named rigid body, if a sample is due.
+fn sample_telemetry(world: &mut World) {
+    let elapsed = world.resource::<Time<Physics>>().elapsed();
+    let time = elapsed.as_secs_f64();
+    let sample_rate = world.resource::<Scenario>().outputs.telemetry.sample_rate as f64;
+    if sample_rate <= 0.0 || time < world.resource::<TelemetryLog>().next_sample {
+        return;
+    }
+
+    world.resource_scope(|world, mut log: Mut<TelemetryLog>| {
+        let channels = world.resource::<TelemetryChannels>();
+
+        // The columns are fixed by the entities that exist at the first sample.
+        if log.columns.is_empty() {
+            let mut entities = world
+                .iter_entities()
+                .filter(|entity| entity.contains::<RigidBody>())
+                .filter_map(|entity| Some((entity.id(), entity.get::<Name>()?.to_string())))
+                .collect::<Vec<_>>();
+            entities.sort_by_key(|(entity, _)| *entity);
+            for (entity, name) in entities {
+                for (index, channel) in channels.0.iter().enumerate() {
+                    log.columns.push(Column {
+                        name: format!("{}.{}", name, channel.name),
+                        unit: channel.unit,
+                        values: Vec::new(),
+                    });
+                    log.sources.push((entity, index));
+                }
+            }
+        }
+
+        let samples = log
+            .sources
+            .iter()
+            .map(|(entity, index)| {
+                world
+                    .get_entity(*entity)
+                    .ok()
+                    .and_then(|entity| channels.0[*index].sample(&entity))
+            })
+            .collect::<Vec<_>>();
+        for (column, sample) in log.columns.iter_mut().zip(samples) {
+            column.values.push(sample);
+        }
+        log.time.push(time);
+        let utc = world.resource::<SimClock>().at(elapsed);
+        log.utc.push(utc);
+        log.next_sample = time + 1.0 / sample_rate;
+    });
+}
+
+fn write_telemetry(mut log: ResMut<TelemetryLog>, scenario: Res<Scenario>) {
+    // A fault followed by the app exiting shouldn't write the same files twice.
+    if log.is_empty() || log.len() == log.written {
+        return;
+    }
+    log.written = log.len();
+    match log.write_all(
+        &scenario.outputs.directory,
+        &scenario.name,
+        &scenario.outputs.telemetry,
+    ) {
+        Ok(paths) => {
+            for path in paths {
+                info!("wrote telemetry to {}", path.display());
+            }
+        }
+        Err(e) => error!("could not write telemetry: {}", e),
+    }
+}
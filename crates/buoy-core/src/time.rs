@@ -1,11 +1,58 @@
+//! Simulation time: the calendar clock, pausing and time warp.
+//!
+//! [`SimClock`] ties physics time to a calendar date. It starts at the epoch of
+//! the scenario and moves forward with physics time, so it stops when the
+//! simulation is paused and speeds up with the time warp.
+//!
+//! Physics time follows virtual time, which normally runs at the same speed as
+//! the wall clock. A flight lasts hours, so [`TimeWarp`] speeds virtual time up
+//! (or slows it down). Every physics step keeps the same length no matter the
+//! warp factor: a faster warp runs more steps per frame instead of bigger
+//! steps, so the joints and forces stay as stable as they are in real time.
+//! A frame runs at most a fixed number of steps. When the computer can't keep
+//! up, simulated time falls behind the warp instead of piling up steps for the
+//! next frame, and [`TimeWarp::achieved`] shows the warp actually reached.
+
+use std::time::Duration;
+
 use avian3d::prelude::*;
 use bevy::prelude::*;
+use chrono::{DateTime, Datelike, TimeDelta, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{core::SimState, scenario::Scenario};
+
+/// Longest frame of virtual time at a warp factor of one. This is Bevy's
+/// default, and it keeps a slow frame from running a burst of steps.
+const MAX_FRAME_DELTA: Duration = Duration::from_millis(250);
 
-use crate::core::SimState;
+/// Most physics steps that one frame may run, however far behind the warp
+/// the simulation is.
+const MAX_STEPS_PER_FRAME: u32 = 1024;
+
+/// How quickly the measured warp factor follows changes, from 0 to 1.
+const ACHIEVED_SMOOTHING: f64 = 0.05;
 
 pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<TimeWarp>();
+    app.register_type::<SimClock>();
+    app.init_resource::<TimeWarp>();
+    app.init_resource::<SimClock>();
+    app.add_systems(Startup, set_epoch);
+    app.add_systems(
+        FixedPostUpdate,
+        update_clock.after(PhysicsSet::StepSimulation),
+    );
     app.add_systems(OnEnter(SimState::Stopped), pause);
     app.add_systems(OnExit(SimState::Stopped), unpause);
+    app.add_systems(OnEnter(SimState::Faulted), halt);
+    app.add_systems(OnExit(SimState::Faulted), unpause);
+    app.add_systems(
+        PreUpdate,
+        apply_time_warp.run_if(resource_changed::<TimeWarp>),
+    );
+    // Physics steps run during the update, so measure after them.
+    app.add_systems(PostUpdate, measure_time_warp);
 }
 
 pub fn pause(mut physics_time: ResMut<Time<Physics>>, mut next_state: ResMut<NextState<SimState>>) {
@@ -14,6 +61,12 @@ pub fn pause(mut physics_time: ResMut<Time<Physics>>, mut next_state: ResMut<Nex
     next_state.set(SimState::Stopped);
 }
 
+/// Pause physics time without leaving the faulted state.
+fn halt(mut physics_time: ResMut<Time<Physics>>) {
+    physics_time.as_mut().pause();
+    debug!("pausing physics time after a fault");
+}
+
 pub fn unpause(
     mut physics_time: ResMut<Time<Physics>>,
     mut next_state: ResMut<NextState<SimState>>,
@@ -22,3 +75,221 @@ pub fn unpause(
     debug!("unpausing physics time");
     next_state.set(SimState::Running);
 }
+
+/// The calendar date and time (UTC) of the simulation.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Resource, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimClock {
+    /// Date and time when physics time was zero.
+    pub epoch: DateTime<Utc>,
+    /// Physics time elapsed since the epoch.
+    pub elapsed: Duration,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        SimClock::new(Scenario::default().epoch)
+    }
+}
+
+impl SimClock {
+    pub fn new(epoch: DateTime<Utc>) -> Self {
+        SimClock {
+            epoch,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Current date and time of the simulation.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.at(self.elapsed)
+    }
+
+    /// Date and time at some physics time after the epoch.
+    pub fn at(&self, elapsed: Duration) -> DateTime<Utc> {
+        TimeDelta::from_std(elapsed)
+            .ok()
+            .and_then(|elapsed| self.epoch.checked_add_signed(elapsed))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// Current date and time, like `2025-06-21 14:32:10 UTC`.
+    pub fn format(&self) -> String {
+        self.now().format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    }
+}
+
+/// Elevation (degrees) of the sun above the horizon at a latitude and
+/// longitude (degrees) at some time. Good to about a degree, which is plenty
+/// to tell day from night.
+pub fn solar_elevation(latitude: f64, longitude: f64, time: DateTime<Utc>) -> f64 {
+    let (declination, hour_angle) = solar_angles(longitude, time);
+    let latitude = latitude.to_radians();
+    (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .asin()
+    .to_degrees()
+}
+
+/// Declination of the sun and its hour angle (rad) at a longitude (degrees)
+/// at some time. The hour angle is zero at local solar noon.
+pub(crate) fn solar_angles(longitude: f64, time: DateTime<Utc>) -> (f64, f64) {
+    let hours = f64::from(time.num_seconds_from_midnight()) / 3600.0;
+    let day = f64::from(time.ordinal0()) + hours / 24.0;
+    let year = 2.0 * std::f64::consts::PI / 365.25;
+    let declination = -23.44_f64.to_radians() * ((day + 10.0) * year).cos();
+    let solar_time = hours + longitude / 15.0;
+    let hour_angle = (solar_time - 12.0).to_radians() * 15.0;
+    (declination, hour_angle)
+}
+
+fn set_epoch(mut clock: ResMut<SimClock>, scenario: Res<Scenario>) {
+    *clock = SimClock::new(scenario.epoch);
+}
+
+fn update_clock(mut clock: ResMut<SimClock>, physics_time: Res<Time<Physics>>) {
+    clock.elapsed = physics_time.elapsed();
+}
+
+/// How fast simulated time passes compared to the wall clock.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct TimeWarp {
+    factor: f64,
+    /// Warp factor actually reached, which is lower than the requested one
+    /// when the computer can't keep up.
+    achieved: f64,
+}
+
+impl Default for TimeWarp {
+    fn default() -> Self {
+        TimeWarp {
+            factor: 1.0,
+            achieved: 1.0,
+        }
+    }
+}
+
+impl TimeWarp {
+    pub const MIN: f64 = 1.0 / 16.0;
+    pub const MAX: f64 = 1024.0;
+    /// Speeding up or slowing down changes the factor by this much.
+    pub const STEP: f64 = 2.0;
+
+    /// Requested ratio of simulated time to wall clock time.
+    pub fn factor(&self) -> f64 {
+        self.factor
+    }
+
+    /// Measured ratio of simulated time to wall clock time.
+    pub fn achieved(&self) -> f64 {
+        self.achieved
+    }
+
+    pub fn set(&mut self, factor: f64) {
+        self.factor = factor.clamp(TimeWarp::MIN, TimeWarp::MAX);
+    }
+
+    pub fn speed_up(&mut self) {
+        self.set(self.factor * TimeWarp::STEP);
+    }
+
+    pub fn slow_down(&mut self) {
+        self.set(self.factor / TimeWarp::STEP);
+    }
+
+    /// Go back to real time.
+    pub fn reset(&mut self) {
+        self.set(1.0);
+    }
+}
+
+fn apply_time_warp(
+    warp: Res<TimeWarp>,
+    fixed_time: Res<Time<Fixed>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    virtual_time.set_relative_speed_f64(warp.factor);
+    // Let a frame cover enough virtual time to keep up with the warp, but not
+    // so much that a slow frame makes the next one slower still.
+    let max_delta = MAX_FRAME_DELTA
+        .mul_f64(warp.factor.max(1.0))
+        .min(fixed_time.timestep() * MAX_STEPS_PER_FRAME);
+    virtual_time.set_max_delta(max_delta);
+    debug!("time warp set to {}x", warp.factor);
+}
+
+/// Compare how far physics time moved to how far the wall clock moved.
+fn measure_time_warp(
+    real_time: Res<Time<Real>>,
+    physics_time: Res<Time<Physics>>,
+    mut warp: ResMut<TimeWarp>,
+    mut last_elapsed: Local<Duration>,
+) {
+    let elapsed = physics_time.elapsed();
+    let advanced = elapsed.saturating_sub(*last_elapsed);
+    *last_elapsed = elapsed;
+    let real_delta = real_time.delta_secs_f64();
+    if real_delta <= 0.0 {
+        return;
+    }
+    let ratio = advanced.as_secs_f64() / real_delta;
+    // Measuring is not a change to the requested warp.
+    let warp = warp.bypass_change_detection();
+    warp.achieved += ACHIEVED_SMOOTHING * (ratio - warp.achieved);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::headless::HeadlessSim;
+
+    #[test]
+    fn warp_saturates_at_its_limits() {
+        let mut warp = TimeWarp::default();
+        for _ in 0..20 {
+            warp.speed_up();
+        }
+        assert_eq!(warp.factor(), TimeWarp::MAX);
+        warp.reset();
+        for _ in 0..20 {
+            warp.slow_down();
+        }
+        assert_eq!(warp.factor(), TimeWarp::MIN);
+        warp.set(f64::INFINITY);
+        assert_eq!(warp.factor(), TimeWarp::MAX);
+    }
+
+    /// Fixed steps run during the current frame, and in each frame so far.
+    #[derive(Resource, Default)]
+    struct StepsPerFrame {
+        current: u32,
+        frames: Vec<u32>,
+    }
+
+    #[test]
+    fn a_frame_runs_a_limited_number_of_steps() {
+        let mut sim = HeadlessSim::from_app(HeadlessSim::batch_app())
+            .with_max_duration(Duration::from_secs(60));
+        // Ten seconds of wall clock per frame at the fastest warp is almost
+        // three hours of simulated time, far more than a frame may run.
+        sim.app_mut()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(10)))
+            .init_resource::<StepsPerFrame>()
+            .add_systems(FixedUpdate, |mut steps: ResMut<StepsPerFrame>| {
+                steps.current += 1;
+            })
+            .add_systems(Last, |mut steps: ResMut<StepsPerFrame>| {
+                let current = std::mem::take(&mut steps.current);
+                steps.frames.push(current);
+            });
+        sim.world_mut().resource_mut::<TimeWarp>().set(TimeWarp::MAX);
+        sim.run();
+
+        let frames = &sim.world().resource::<StepsPerFrame>().frames;
+        assert!(frames.len() > 2);
+        assert!(frames.iter().all(|steps| *steps <= MAX_STEPS_PER_FRAME));
+        assert_eq!(frames.iter().max(), Some(&MAX_STEPS_PER_FRAME));
+    }
+}
@@ -0,0 +1,449 @@
+//! Gusts of turbulence on top of the mean wind.
+//!
+//! Each body that feels drag carries a [`Gust`]: a random velocity that is
+//! added to the [`Wind`] where the body is. Gusts are white noise from the
+//! [`SimRng`] passed through shaping filters whose spectra follow the Dryden
+//! or von Kármán models of MIL-F-8785C, so two runs with the same seed feel
+//! the same gusts.
+//!
+//! ```ron
+//! turbulence: Some((spectrum: VonKarman, intensity: Moderate)),
+//! ```
+//!
+//! Gusts are split into a longitudinal part along the mean wind, a lateral
+//! part across it and a vertical part. Their strength and length scale change
+//! with height above the launch site:
+//!
+//! - Below 1000 ft, turbulence comes from the ground. It is strongest and
+//!   shortest close to the ground, scaled by the wind speed at 20 ft for each
+//!   intensity.
+//! - Above 2000 ft, turbulence is the same in every direction, with a strength
+//!   from the table of the specification for each intensity and a fixed
+//!   length scale. Light and moderate turbulence die out with altitude.
+//! - In between, strength and length scale are interpolated linearly.
+//!
+//! The filters turn a length scale into a correlation time using the speed of
+//! the body through the air, as if the gusts were frozen in the air mass. A
+//! balloon drifts with the wind, so it is given at least the speed of the
+//! gusts themselves: eddies then last about as long as they take to turn
+//! over.
+//!
+//! Every body has its own gusts, so the bodies of a flight train are pushed
+//! around relative to each other as well as together.
+
+use avian3d::{
+    math::{Matrix3, Scalar, Vector},
+    prelude::*,
+};
+use bevy::prelude::*;
+use rand::Rng;
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+
+use crate::{core::SimState, determinism::SimRng, forces::Drag, scenario::Scenario, wind::Wind};
+
+/// Meters in a foot. The specification is in feet.
+const FOOT: Scalar = 0.3048;
+
+/// Feet per second in a knot.
+const KNOT: Scalar = 1.687_81;
+
+/// Lowest height (ft) used for the low altitude model, which has no gusts at
+/// the ground itself.
+const MIN_HEIGHT: Scalar = 10.0;
+
+/// Top of the low altitude model and bottom of the high altitude model (ft).
+const LOW_ALTITUDE: Scalar = 1000.0;
+const HIGH_ALTITUDE: Scalar = 2000.0;
+
+/// Altitudes (ft) of the table of high altitude turbulence strength.
+const SIGMA_ALTITUDES: [Scalar; 12] = [
+    500.0, 1750.0, 3750.0, 7500.0, 15000.0, 25000.0, 35000.0, 45000.0, 55000.0, 65000.0, 75000.0,
+    80000.0,
+];
+
+/// Slowest speed (m/s) used to find the correlation time of the gusts.
+const MIN_SPEED: Scalar = 0.1;
+
+/// Largest step of the filters, in correlation times. Longer steps are split.
+const MAX_FILTER_STEP: Scalar = 0.1;
+const MAX_FILTER_SUBSTEPS: u32 = 16;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<Gust>();
+    app.add_systems(Startup, load_turbulence);
+    app.add_systems(
+        FixedPreUpdate,
+        (
+            add_gusts,
+            update_gusts.run_if(resource_exists::<Turbulence>),
+        )
+            .chain()
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// Turbulence of the atmosphere. Calm air unless present.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Turbulence {
+    pub spectrum: Spectrum,
+    pub intensity: Intensity,
+}
+
+/// Shape of the power spectrum of the gusts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Spectrum {
+    /// Rational spectra that are easy to generate.
+    #[default]
+    Dryden,
+    /// Spectra that match measured turbulence better, with more energy at
+    /// short wavelengths. Generated with rational approximations.
+    VonKarman,
+}
+
+/// How strong the turbulence is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Intensity {
+    /// 15 kt wind at 20 ft. Above 2000 ft, exceeded with a probability of 10⁻².
+    #[default]
+    Light,
+    /// 30 kt wind at 20 ft. Above 2000 ft, exceeded with a probability of 10⁻³.
+    Moderate,
+    /// 45 kt wind at 20 ft. Above 2000 ft, exceeded with a probability of 10⁻⁵.
+    Severe,
+}
+
+impl Intensity {
+    /// Wind speed (ft/s) 20 ft above the ground.
+    fn wind_speed_20ft(self) -> Scalar {
+        match self {
+            Intensity::Light => 15.0 * KNOT,
+            Intensity::Moderate => 30.0 * KNOT,
+            Intensity::Severe => 45.0 * KNOT,
+        }
+    }
+
+    /// Standard deviation (ft/s) of high altitude gusts at each of the
+    /// [`SIGMA_ALTITUDES`].
+    fn sigmas(self) -> [Scalar; 12] {
+        match self {
+            Intensity::Light => [4.2, 3.6, 3.3, 1.6, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            Intensity::Moderate => [6.6, 6.9, 7.4, 6.7, 4.6, 2.7, 0.4, 0.0, 0.0, 0.0, 0.0, 0.0],
+            Intensity::Severe => [
+                11.8, 13.0, 16.0, 15.1, 11.6, 9.7, 8.1, 8.2, 7.9, 4.9, 3.2, 2.1,
+            ],
+        }
+    }
+
+    /// Standard deviation (ft/s) of high altitude gusts at an altitude (ft).
+    fn sigma(self, altitude: Scalar) -> Scalar {
+        let sigmas = self.sigmas();
+        let upper = SIGMA_ALTITUDES.partition_point(|&a| a < altitude);
+        if upper == 0 {
+            return sigmas[0];
+        }
+        if upper == SIGMA_ALTITUDES.len() {
+            return sigmas[upper - 1];
+        }
+        let t = (altitude - SIGMA_ALTITUDES[upper - 1])
+            / (SIGMA_ALTITUDES[upper] - SIGMA_ALTITUDES[upper - 1]);
+        sigmas[upper - 1] + (sigmas[upper] - sigmas[upper - 1]) * t
+    }
+}
+
+/// Standard deviation (m/s) and length scale (m) of the gusts along each
+/// axis: longitudinal, lateral and vertical.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scales {
+    sigma: [Scalar; 3],
+    length: [Scalar; 3],
+}
+
+impl Scales {
+    fn lerp(self, other: Scales, t: Scalar) -> Scales {
+        let lerp =
+            |a: [Scalar; 3], b: [Scalar; 3]| std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+        Scales {
+            sigma: lerp(self.sigma, other.sigma),
+            length: lerp(self.length, other.length),
+        }
+    }
+
+    fn to_meters(self) -> Scales {
+        Scales {
+            sigma: self.sigma.map(|sigma| sigma * FOOT),
+            length: self.length.map(|length| length * FOOT),
+        }
+    }
+}
+
+impl Turbulence {
+    /// Strength and length scale of the gusts at a height (m) above the
+    /// ground.
+    fn scales(&self, height: Scalar) -> Scales {
+        let height = (height / FOOT).max(MIN_HEIGHT);
+        let scales = if height <= LOW_ALTITUDE {
+            self.low_altitude(height)
+        } else if height >= HIGH_ALTITUDE {
+            self.high_altitude(height)
+        } else {
+            let t = (height - LOW_ALTITUDE) / (HIGH_ALTITUDE - LOW_ALTITUDE);
+            self.low_altitude(LOW_ALTITUDE)
+                .lerp(self.high_altitude(HIGH_ALTITUDE), t)
+        };
+        scales.to_meters()
+    }
+
+    /// Turbulence from the ground, at a height (ft).
+    fn low_altitude(&self, height: Scalar) -> Scales {
+        let factor = 0.177 + 0.000823 * height;
+        let sigma_vertical = 0.1 * self.intensity.wind_speed_20ft();
+        let sigma_horizontal = sigma_vertical / factor.powf(0.4);
+        let length_horizontal = height / factor.powf(1.2);
+        Scales {
+            sigma: [sigma_horizontal, sigma_horizontal, sigma_vertical],
+            length: [length_horizontal, length_horizontal, height],
+        }
+    }
+
+    /// Turbulence that is the same in every direction, at an altitude (ft).
+    fn high_altitude(&self, altitude: Scalar) -> Scales {
+        let length = match self.spectrum {
+            Spectrum::Dryden => 1750.0,
+            Spectrum::VonKarman => 2500.0,
+        };
+        Scales {
+            sigma: [self.intensity.sigma(altitude); 3],
+            length: [length; 3],
+        }
+    }
+
+    /// Shaping filter of the gusts along an axis. The longitudinal axis is the
+    /// first one.
+    fn filter(&self, axis: usize) -> Filter {
+        let longitudinal = axis == 0;
+        match (self.spectrum, longitudinal) {
+            (Spectrum::Dryden, true) => Filter {
+                gain: Scalar::sqrt(2.0),
+                numerator: [1.0, 0.0, 0.0],
+                denominator: [1.0, 1.0, 0.0, 0.0],
+            },
+            (Spectrum::Dryden, false) => Filter {
+                gain: 1.0,
+                numerator: [1.0, Scalar::sqrt(3.0), 0.0],
+                denominator: [1.0, 2.0, 1.0, 0.0],
+            },
+            (Spectrum::VonKarman, true) => Filter {
+                gain: Scalar::sqrt(2.0),
+                numerator: [1.0, 0.25, 0.0],
+                denominator: [1.0, 1.357, 0.1987, 0.0],
+            },
+            (Spectrum::VonKarman, false) => Filter {
+                gain: 1.0,
+                numerator: [1.0, 2.7478, 0.3398],
+                denominator: [1.0, 2.9958, 1.9754, 0.1539],
+            },
+        }
+    }
+}
+
+/// A transfer function from white noise to gusts with unit variance, in time
+/// measured in correlation times. Coefficients are in ascending powers of s.
+struct Filter {
+    gain: Scalar,
+    numerator: [Scalar; 3],
+    denominator: [Scalar; 4],
+}
+
+impl Filter {
+    /// The filter in controllable canonical form. Filters of lower order have
+    /// extra states that decay on their own and are never read.
+    fn system(&self) -> FilterSystem {
+        let order = (1..4)
+            .rev()
+            .find(|&i| self.denominator[i] != 0.0)
+            .unwrap_or(1);
+        let lead = self.denominator[order];
+        let mut columns = [[0.0; 3]; 3];
+        for row in 0..3 {
+            if row + 1 < order {
+                columns[row + 1][row] = 1.0;
+            } else if row + 1 == order {
+                for column in 0..order {
+                    columns[column][row] = -self.denominator[column] / lead;
+                }
+            } else {
+                columns[row][row] = -1.0;
+            }
+        }
+        let mut input = Vector::ZERO;
+        input[order - 1] = 1.0;
+        FilterSystem {
+            matrix: Matrix3::from_cols_array_2d(&columns),
+            input,
+            output: Vector::from_array(self.numerator) * (self.gain / lead),
+        }
+    }
+}
+
+/// State matrix, input and output of a [`Filter`], built once per
+/// [`Turbulence`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FilterSystem {
+    matrix: Matrix3,
+    input: Vector,
+    output: Vector,
+}
+
+impl FilterSystem {
+    /// Advance the state of the filter by a step measured in correlation
+    /// times, and return its output. Backward Euler keeps the fast poles of
+    /// the von Kármán filters stable at any step. The update matrix of the
+    /// last step length is kept in `update`, and only recomputed when the
+    /// step length changes.
+    fn step(
+        &self,
+        state: &mut Vector,
+        update: &mut Option<(Scalar, Matrix3)>,
+        step: Scalar,
+        rng: &mut SimRng,
+    ) -> Scalar {
+        let substeps = ((step / MAX_FILTER_STEP).ceil() as u32).clamp(1, MAX_FILTER_SUBSTEPS);
+        let update = match *update {
+            Some((length, matrix)) if length == step => matrix,
+            _ => {
+                let substep = step / substeps as Scalar;
+                let matrix = (Matrix3::IDENTITY - self.matrix * substep).inverse();
+                *update = Some((step, matrix));
+                matrix
+            }
+        };
+        let step = step / substeps as Scalar;
+        for _ in 0..substeps {
+            let noise = rng.sample::<Scalar, _>(StandardNormal) * step.sqrt();
+            *state = update * (*state + self.input * noise);
+        }
+        self.output.dot(*state)
+    }
+}
+
+/// A random gust that adds to the wind felt by a body.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Gust {
+    /// Velocity (m/s) of the gust in world coordinates.
+    pub velocity: Vector,
+    /// State of the shaping filter of each axis.
+    filters: [Vector; 3],
+    /// Step length of the filter of each axis on the last update, with its
+    /// update matrix. Only a cache, so it is not saved in snapshots.
+    #[reflect(ignore)]
+    updates: [Option<(Scalar, Matrix3)>; 3],
+    /// Bodies draw their noise in the order their gusts were added. The order
+    /// is saved in snapshots along with the gust, unlike the order of
+    /// entities, so a restored body draws the same noise as the original.
+    order: u64,
+}
+
+fn load_turbulence(mut commands: Commands, scenario: Res<Scenario>) {
+    match scenario.turbulence {
+        Some(turbulence) => commands.insert_resource(turbulence),
+        None => commands.remove_resource::<Turbulence>(),
+    }
+}
+
+fn add_gusts(
+    mut commands: Commands,
+    bodies: Query<Entity, (With<Drag>, Without<Gust>)>,
+    mut added: Local<u64>,
+) {
+    for entity in bodies.iter() {
+        commands.entity(entity).insert(Gust {
+            order: *added,
+            ..default()
+        });
+        *added += 1;
+    }
+}
+
+fn update_gusts(
+    time: Res<Time>,
+    turbulence: Res<Turbulence>,
+    scenario: Res<Scenario>,
+    wind: Res<Wind>,
+    mut rng: ResMut<SimRng>,
+    mut bodies: Query<(&mut Gust, &Position, &LinearVelocity)>,
+    mut systems: Local<Option<[FilterSystem; 3]>>,
+) {
+    if turbulence.is_changed() {
+        *systems = None;
+    }
+    let systems =
+        systems.get_or_insert_with(|| std::array::from_fn(|axis| turbulence.filter(axis).system()));
+    let delta = time.delta_secs_f64() as Scalar;
+    let mut bodies = bodies.iter_mut().collect::<Vec<_>>();
+    bodies.sort_by_key(|(gust, ..)| gust.order);
+    for (mut gust, position, velocity) in bodies {
+        let gust = &mut *gust;
+        let mean_wind = wind.velocity(position.0);
+        let scales = turbulence.scales(position.y - scenario.launch_site.altitude);
+        let airspeed = (velocity.0 - mean_wind).length();
+
+        let along = Vector::new(mean_wind.x, 0.0, mean_wind.z)
+            .try_normalize()
+            .unwrap_or(Vector::X);
+        let axes = [along, along.cross(Vector::Y), Vector::Y];
+
+        let mut gust_velocity = Vector::ZERO;
+        for (axis, direction) in axes.into_iter().enumerate() {
+            let sigma = scales.sigma[axis];
+            let speed = airspeed.max(sigma).max(MIN_SPEED);
+            let step = delta * speed / scales.length[axis];
+            let output = systems[axis].step(
+                &mut gust.filters[axis],
+                &mut gust.updates[axis],
+                step,
+                &mut *rng,
+            );
+            gust_velocity += direction * sigma * output;
+        }
+        gust.velocity = gust_velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_have_unit_variance() {
+        // A step well below the correlation time, so the discretization
+        // barely damps the output. The rational von Kármán approximations
+        // are a few percent short of unit variance themselves.
+        const STEP: Scalar = 0.02;
+        const STEPS: usize = 1_000_000;
+        for spectrum in [Spectrum::Dryden, Spectrum::VonKarman] {
+            let turbulence = Turbulence {
+                spectrum,
+                ..default()
+            };
+            for axis in 0..3 {
+                let system = turbulence.filter(axis).system();
+                let mut rng = SimRng::seed_from_u64(7);
+                let mut state = Vector::ZERO;
+                let mut update = None;
+                let mut sum_of_squares = 0.0;
+                for _ in 0..STEPS {
+                    let output = system.step(&mut state, &mut update, STEP, &mut rng);
+                    sum_of_squares += f64::from(output * output);
+                }
+                let variance = sum_of_squares / STEPS as f64;
+                assert!(
+                    (variance - 1.0).abs() < 0.1,
+                    "{spectrum:?} filter of axis {axis} has variance {variance}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! A valve that vents lift gas out of the top of a balloon.
+//!
+//! Gas flows out of the valve through an orifice. The flow rate is
+//!
+//! ```text
+//! ṁ = Cd · A · √(2 · ρ · Δp)
+//! ```
+//!
+//! where `Cd` is the discharge coefficient, `A` is the orifice area, `ρ` is the
+//! density of the lift gas and `Δp` is the difference between the pressure of
+//! the gas and the ambient pressure at the valve. The valve sits at the top of
+//! the envelope, so `Δp` includes the buoyant pressure head of the gas column
+//! below it.
+
+use std::time::Duration;
+
+use avian3d::{
+    math::Scalar,
+    prelude::{Gravity, GravityScale, Position},
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    area::square_meter,
+    mass::kilogram,
+    mass_density::kilogram_per_cubic_meter,
+    mass_rate::kilogram_per_second,
+    pressure::pascal,
+    volume::cubic_meter,
+};
+
+use crate::{
+    atmosphere::Atmosphere,
+    core::SimState,
+    geometry::sphere_radius_from_volume,
+    ideal_gas::IdealGas,
+    quantity::{Area, Mass, MassDensity, MassRate, Pressure},
+};
+
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<VentValve>();
+    app.add_event::<VentCommand>();
+    app.add_systems(
+        FixedUpdate,
+        (handle_vent_commands, vent_gas)
+            .chain()
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// A valve that vents lift gas from the [`IdealGas`] on the same entity.
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, Serialize, Deserialize)]
+pub struct VentValve {
+    /// Area of the orifice when the valve is open.
+    pub orifice_area: Area,
+    /// Ratio of the actual flow rate to the ideal flow rate through the
+    /// orifice.
+    pub discharge_coefficient: Scalar,
+    /// Whether the valve is open.
+    pub open: bool,
+    /// Time left until the valve closes on its own.
+    pub remaining: Option<Duration>,
+    /// Flow rate of gas through the valve during the last step.
+    pub flow_rate: MassRate,
+    /// Total mass of gas vented.
+    pub vented: Mass,
+}
+
+impl VentValve {
+    pub fn new(orifice_area: Area, discharge_coefficient: Scalar) -> Self {
+        VentValve {
+            orifice_area,
+            discharge_coefficient,
+            open: false,
+            remaining: None,
+            flow_rate: MassRate::default(),
+            vented: Mass::default(),
+        }
+    }
+
+    /// Mass flow rate out of the valve when it is open.
+    pub fn mass_flow_rate(
+        &self,
+        gas_density: MassDensity,
+        pressure_difference: Pressure,
+    ) -> MassRate {
+        let dp = pressure_difference.get::<pascal>().max(0.0);
+        let rho = gas_density.get::<kilogram_per_cubic_meter>();
+        MassRate::new::<kilogram_per_second>(
+            self.discharge_coefficient
+                * self.orifice_area.get::<square_meter>()
+                * Scalar::sqrt(2.0 * rho * dp),
+        )
+    }
+}
+
+impl Default for VentValve {
+    fn default() -> Self {
+        VentValve::new(Area::new::<square_meter>(0.005), 0.6)
+    }
+}
+
+/// Commands that control a vent valve on an entity.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct VentCommand {
+    pub entity: Entity,
+    pub action: VentAction,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum VentAction {
+    Open,
+    Close,
+    /// Open the valve and close it again after some time.
+    OpenFor(Duration),
+}
+
+fn handle_vent_commands(
+    mut commands: EventReader<VentCommand>,
+    mut valves: Query<&mut VentValve>,
+) {
+    for command in commands.read() {
+        let Ok(mut valve) = valves.get_mut(command.entity) else {
+            warn!("{:?} does not have a vent valve", command.entity);
+            continue;
+        };
+        match command.action {
+            VentAction::Open => {
+                valve.open = true;
+                valve.remaining = None;
+            }
+            VentAction::Close => {
+                valve.open = false;
+                valve.remaining = None;
+            }
+            VentAction::OpenFor(duration) => {
+                valve.open = true;
+                valve.remaining = Some(duration);
+            }
+        }
+        debug!("{:?} vent valve {:?}", command.entity, command.action);
+    }
+}
+
+fn vent_gas(
+    mut valves: Query<(
+        Entity,
+        &mut VentValve,
+        &mut IdealGas,
+        &Position,
+        &GravityScale,
+    )>,
+    atmosphere: Res<Atmosphere>,
+    gravity: Res<Gravity>,
+    time: Res<Time>,
+) {
+    for (entity, mut valve, mut gas, position, gravity_scale) in valves.iter_mut() {
+        if !valve.open {
+            valve.flow_rate = MassRate::default();
+            continue;
+        }
+
+        // The valve is at the top of the envelope, so the pressure difference
+        // includes the head of lift gas between the bottom and the top.
+        let envelope_height =
+            2.0 * sphere_radius_from_volume(gas.volume().get::<cubic_meter>());
+        let ambient_density = atmosphere.density(position.0);
+        let gas_density = gas.density();
+        let head = (ambient_density - gas_density).get::<kilogram_per_cubic_meter>()
+            * gravity.0.length()
+            * gravity_scale.0
+            * envelope_height;
+        let pressure_difference = gas.pressure - atmosphere.pressure(position.0)
+            + Pressure::new::<pascal>(head);
+
+        let flow_rate = valve.mass_flow_rate(gas_density, pressure_difference);
+        let vented = Mass::new::<kilogram>(
+            flow_rate.get::<kilogram_per_second>() * time.delta_secs_f64() as Scalar,
+        )
+        .min(gas.mass);
+        gas.mass -= vented;
+        valve.vented += vented;
+        valve.flow_rate = flow_rate;
+
+        if let Some(remaining) = valve.remaining {
+            let remaining = remaining.saturating_sub(time.delta());
+            if remaining.is_zero() {
+                valve.open = false;
+                valve.remaining = None;
+                debug!("{:?} vent valve closed", entity);
+            } else {
+                valve.remaining = Some(remaining);
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Oceans and lakes that bodies can float in.
+//!
+//! A [`WaterBody`] is a liquid with a flat surface at some altitude. It covers
+//! the whole world, like an ocean, or a circle or rectangle of it, like a
+//! lake. Only water at sea level may cover the whole world, so a lake must
+//! give its extent. Each one is spawned at startup as a [`FluidVolumeGrid`]
+//! under the root grid, sharing its origin, so its extent is in world
+//! coordinates.
+//!
+//! ```ron
+//! water: [
+//!     (name: "Ocean"),
+//!     (
+//!         name: "Reservoir",
+//!         surface: 1600.0,
+//!         density: 1000.0,
+//!         extent: Circle(center: (200.0, -50.0), radius: 150.0),
+//!     ),
+//! ],
+//! ```
+//!
+//! A body that dips into the water feels the weight of the water it displaces,
+//! and drag from moving through the water in proportion to how much of it is
+//! under. Air drag falls off the same way, as only the part above the surface
+//! is in the air. Bodies that are partly under water are marked [`Afloat`],
+//! and a payload that comes down onto water has landed. The submerged volume
+//! comes from the [`Shape`] of each body as if it were upright, which is close
+//! enough for a payload bobbing on the waves.
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::*,
+};
+use bevy::prelude::*;
+use big_space::prelude::*;
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    acceleration::meter_per_second_squared, mass_density::kilogram_per_cubic_meter,
+    volume::cubic_meter,
+};
+
+use crate::{
+    constants::PI,
+    core::SimState,
+    flight_train::Shape,
+    forces::{buoyancy, drag, Drag},
+    grid::{
+        FluidVolumeGrid, Precision, RootGrid, GRID_CELL_EDGE_LENGTH_METERS,
+        GRID_SWITCHING_THRESHOLD_METERS,
+    },
+    quantity::*,
+    scenario::Scenario,
+};
+
+/// Density (kg/m³) of sea water.
+pub const SEA_WATER_DENSITY: Scalar = 1025.0;
+
+/// Density (kg/m³) of fresh water.
+pub const FRESH_WATER_DENSITY: Scalar = 1000.0;
+
+pub(crate) fn plugin(app: &mut App) {
+    // The root grid is spawned during startup, so wait until it exists.
+    app.add_systems(PostStartup, spawn_water);
+    app.add_systems(
+        FixedUpdate,
+        apply_water_forces
+            .after(PhysicsStepSet::First)
+            .run_if(in_state(SimState::Running)),
+    );
+}
+
+/// A body of liquid with a flat surface.
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaterBody {
+    pub name: String,
+    /// Altitude (m) of the surface above mean sea level.
+    pub surface: Scalar,
+    /// Density (kg/m³) of the liquid.
+    pub density: Scalar,
+    pub extent: WaterExtent,
+}
+
+/// Where a body of water is, seen from above.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum WaterExtent {
+    /// Everywhere, like an ocean.
+    #[default]
+    Everywhere,
+    /// A circle around a point (m) given by its x (east) and z (south).
+    Circle {
+        center: (Scalar, Scalar),
+        radius: Scalar,
+    },
+    /// A rectangle between two corners (m) given by their x and z.
+    Rectangle {
+        min: (Scalar, Scalar),
+        max: (Scalar, Scalar),
+    },
+}
+
+impl Default for WaterBody {
+    fn default() -> Self {
+        WaterBody::ocean()
+    }
+}
+
+impl WaterBody {
+    /// Sea water everywhere, with its surface at sea level.
+    pub fn ocean() -> Self {
+        WaterBody {
+            name: "Ocean".to_string(),
+            surface: 0.0,
+            density: SEA_WATER_DENSITY,
+            extent: WaterExtent::Everywhere,
+        }
+    }
+
+    /// Fresh water in a circle with its surface at some altitude (m).
+    pub fn lake(surface: Scalar, center: (Scalar, Scalar), radius: Scalar) -> Self {
+        WaterBody {
+            name: "Lake".to_string(),
+            surface,
+            density: FRESH_WATER_DENSITY,
+            extent: WaterExtent::Circle { center, radius },
+        }
+    }
+
+    pub fn with_density(self, density: Scalar) -> Self {
+        Self { density, ..self }
+    }
+
+    /// Whether the water is under or above a position, seen from above.
+    pub fn covers(&self, position: Vector) -> bool {
+        match self.extent {
+            WaterExtent::Everywhere => true,
+            WaterExtent::Circle { center, radius } => {
+                let (dx, dz) = (position.x - center.0, position.z - center.1);
+                dx * dx + dz * dz <= radius * radius
+            }
+            WaterExtent::Rectangle { min, max } => {
+                (min.0..=max.0).contains(&position.x) && (min.1..=max.1).contains(&position.z)
+            }
+        }
+    }
+
+    /// Volume (m³) of an upright shape centered at a position that is under
+    /// the surface.
+    pub fn submerged_volume(&self, shape: &Shape, position: Vector) -> Scalar {
+        if !self.covers(position) {
+            return 0.0;
+        }
+        let (height, area) = match *shape {
+            Shape::Sphere { radius } => {
+                let depth = (self.surface - (position.y - radius)).clamp(0.0, 2.0 * radius);
+                return PI * depth * depth * (3.0 * radius - depth) / 3.0;
+            }
+            Shape::Cylinder { radius, height } => (height, PI * radius * radius),
+            Shape::Cube { size } => (size, size * size),
+        };
+        let depth = (self.surface - (position.y - 0.5 * height)).clamp(0.0, height);
+        depth * area
+    }
+
+    /// Fraction (0 to 1) of an upright shape centered at a position that is
+    /// under the surface.
+    pub fn submerged_fraction(&self, shape: &Shape, position: Vector) -> Scalar {
+        self.submerged_volume(shape, position) / shape_volume(shape)
+    }
+}
+
+/// Volume (m³) of an upright shape.
+fn shape_volume(shape: &Shape) -> Scalar {
+    match *shape {
+        Shape::Sphere { radius } => 4.0 / 3.0 * PI * radius * radius * radius,
+        Shape::Cylinder { radius, height } => PI * radius * radius * height,
+        Shape::Cube { size } => size * size * size,
+    }
+}
+
+/// A marker component for a body that is partly or fully under water.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Afloat;
+
+fn spawn_water(
+    mut commands: Commands,
+    scenario: Res<Scenario>,
+    root_grid: Query<Entity, With<RootGrid>>,
+) {
+    let root_grid = root_grid.single();
+    for water in &scenario.water {
+        commands
+            .spawn((
+                Name::new(water.name.clone()),
+                water.clone(),
+                FluidVolumeGrid,
+                Grid::<Precision>::new(
+                    GRID_CELL_EDGE_LENGTH_METERS,
+                    GRID_SWITCHING_THRESHOLD_METERS,
+                ),
+                GridCell::<Precision>::default(),
+                Transform::default(),
+                GlobalTransform::default(),
+            ))
+            .set_parent(root_grid);
+    }
+}
+
+/// Apply buoyancy and drag from the water to bodies that dip into it.
+fn apply_water_forces(
+    mut commands: Commands,
+    mut bodies: Query<(
+        Entity,
+        &mut ExternalForce,
+        &Position,
+        &LinearVelocity,
+        &GravityScale,
+        &Shape,
+        Option<&Drag>,
+        Has<Afloat>,
+    )>,
+    water: Query<&WaterBody>,
+    gravity: Res<Gravity>,
+) {
+    for (entity, mut external_force, position, velocity, gravity_scale, shape, body, afloat) in
+        bodies.iter_mut()
+    {
+        // Bodies of water shouldn't overlap, but if they do the one the body
+        // is deepest in wins.
+        let submerged = water
+            .iter()
+            .map(|water| (water, water.submerged_volume(shape, position.0)))
+            .filter(|(_, volume)| *volume > 0.0)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        let Some((water, volume)) = submerged else {
+            if afloat {
+                commands.entity(entity).remove::<Afloat>();
+            }
+            continue;
+        };
+        if !afloat {
+            commands.entity(entity).insert(Afloat);
+        }
+
+        let gravity_acceleration =
+            Acceleration::new::<meter_per_second_squared>(gravity.0.length() * gravity_scale.0);
+        let density = MassDensity::new::<kilogram_per_cubic_meter>(water.density);
+        external_force.apply_force(buoyancy(
+            gravity_acceleration,
+            Volume::new::<cubic_meter>(volume),
+            density,
+        ));
+        if let Some(body) = body {
+            let fraction = volume / shape_volume(shape);
+            external_force.apply_force(drag(
+                velocity.0,
+                density,
+                body.area * fraction,
+                body.coefficient,
+            ));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Wind that carries bodies along as they move through the atmosphere.
+//!
+//! The wind is a profile of horizontal velocities at a few altitudes. Between
+//! layers the velocity is interpolated linearly. Above the top layer and below
+//! the bottom layer the wind is the same as at that layer.
+//!
+//! Velocities are in world coordinates: x points east, y points up and z
+//! points south.
+
+use avian3d::math::{Scalar, Vector};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::scenario::Scenario;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<Wind>();
+    app.add_systems(Startup, load_wind);
+}
+
+/// Wind velocity at one altitude.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindLayer {
+    /// Altitude (m) above mean sea level.
+    pub altitude: Scalar,
+    /// Velocity (m/s) of the air.
+    pub velocity: [Scalar; 3],
+}
+
+impl WindLayer {
+    fn vector(&self) -> Vector {
+        Vector::from_array(self.velocity)
+    }
+}
+
+/// A profile of wind velocity with altitude. No layers means calm air.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wind {
+    /// Layers sorted from lowest to highest altitude.
+    pub layers: Vec<WindLayer>,
+}
+
+impl Wind {
+    pub fn calm() -> Self {
+        Wind::default()
+    }
+
+    /// The same wind at every altitude.
+    pub fn uniform(velocity: Vector) -> Self {
+        Wind {
+            layers: vec![WindLayer {
+                altitude: 0.0,
+                velocity: velocity.to_array(),
+            }],
+        }
+    }
+
+    /// Add a layer to the profile, keeping the layers sorted by altitude.
+    pub fn with_layer(mut self, altitude: Scalar, velocity: Vector) -> Self {
+        let index = self.layers.partition_point(|layer| layer.altitude < altitude);
+        self.layers.insert(
+            index,
+            WindLayer {
+                altitude,
+                velocity: velocity.to_array(),
+            },
+        );
+        self
+    }
+
+    /// Add the same velocity to every layer, like an error in the forecast.
+    pub fn offset(&mut self, velocity: Vector) {
+        for layer in self.layers.iter_mut() {
+            layer.velocity = (layer.vector() + velocity).to_array();
+        }
+        if self.layers.is_empty() {
+            *self = Wind::uniform(velocity);
+        }
+    }
+
+    /// Velocity (m/s) of the air at a position.
+    pub fn velocity(&self, position: Vector) -> Vector {
+        let altitude = position.y;
+        let upper = self.layers.partition_point(|layer| layer.altitude < altitude);
+        match (upper.checked_sub(1).map(|i| &self.layers[i]), self.layers.get(upper)) {
+            (None, None) => Vector::ZERO,
+            (Some(layer), None) | (None, Some(layer)) => layer.vector(),
+            (Some(below), Some(above)) => {
+                let t = (altitude - below.altitude) / (above.altitude - below.altitude);
+                below.vector().lerp(above.vector(), t)
+            }
+        }
+    }
+}
+
+fn load_wind(mut commands: Commands, scenario: Res<Scenario>) {
+    let mut wind = scenario.wind.clone();
+    wind.layers.sort_by(|a, b| a.altitude.total_cmp(&b.altitude));
+    commands.insert_resource(wind);
+}
@@ -0,0 +1,152 @@
+//! Every atmosphere source must reproduce the published tables it comes from.
+
+use avian3d::math::{Scalar, Vector};
+use buoy_core::prelude::*;
+
+/// Temperature (K) and pressure (Pa) of an atmosphere at a geometric
+/// altitude (m).
+fn air(atmosphere: &Atmosphere, altitude: Scalar) -> (Scalar, Scalar) {
+    let position = Vector::Y * altitude;
+    (
+        atmosphere.temperature(position).get::<kelvin>(),
+        atmosphere.pressure(position).get::<pascal>(),
+    )
+}
+
+/// Checks an atmosphere against rows of a table of geometric altitude (m),
+/// temperature (K) and pressure (Pa, or NaN where the table gives none),
+/// within a temperature (K) and a relative pressure tolerance.
+fn assert_table(
+    source: AtmosphereSource,
+    table: &[(Scalar, Scalar, Scalar)],
+    temperature_tolerance: Scalar,
+    pressure_tolerance: Scalar,
+) {
+    let atmosphere = Atmosphere::new(source);
+    for &(altitude, expected_temperature, expected_pressure) in table {
+        let (temperature, pressure) = air(&atmosphere, altitude);
+        assert!(
+            (temperature - expected_temperature).abs() <= temperature_tolerance,
+            "{source:?} at {altitude} m is {temperature} K, expected {expected_temperature} K"
+        );
+        if !expected_pressure.is_nan() {
+            let error = ((pressure - expected_pressure) / expected_pressure).abs();
+            assert!(
+                error <= pressure_tolerance,
+                "{source:?} at {altitude} m is {pressure} Pa, expected {expected_pressure} Pa"
+            );
+        }
+    }
+}
+
+#[test]
+fn standard_atmosphere_matches_the_1976_table() {
+    let table = [
+        (0.0, 288.15, 101325.0),
+        (11000.0, 216.774, 22700.0),
+        (20000.0, 216.65, 5529.3),
+        (30000.0, 226.509, 1197.0),
+        (50000.0, 270.65, 79.779),
+    ];
+    assert_table(AtmosphereSource::StandardAtmosphere1976, &table, 0.01, 1e-3);
+    assert_table(AtmosphereSource::StandardOffset(0.0), &table, 0.01, 1e-3);
+}
+
+#[test]
+fn standard_offset_shifts_every_temperature() {
+    let standard = Atmosphere::new(AtmosphereSource::StandardAtmosphere1976);
+    let warm = Atmosphere::new(AtmosphereSource::StandardOffset(10.0));
+    for altitude in [0.0, 11000.0, 30000.0] {
+        let (standard_temperature, _) = air(&standard, altitude);
+        let (warm_temperature, _) = air(&warm, altitude);
+        assert!((warm_temperature - standard_temperature - 10.0).abs() < 1e-3);
+    }
+    // Warm air is thinner, so pressure falls more slowly with altitude.
+    assert_eq!(air(&warm, 0.0).1, air(&standard, 0.0).1);
+    assert!(air(&warm, 30000.0).1 > air(&standard, 30000.0).1);
+}
+
+// The supplement and MIL-STD-210 profiles are coarse fits to their tables, so
+// they are held to 1.5 K and 2 % rather than to the last digit.
+
+#[test]
+fn supplements_match_their_tables() {
+    let tables = [
+        (
+            Supplement::Tropical,
+            &[
+                (0.0, 299.7, 101300.0),
+                (10000.0, 237.0, 28600.0),
+                (17000.0, 194.8, Scalar::NAN),
+                (20000.0, 206.7, 5650.0),
+            ][..],
+        ),
+        (
+            Supplement::MidlatitudeSummer,
+            &[
+                (0.0, 294.2, 101300.0),
+                (10000.0, 235.3, 28100.0),
+                (20000.0, 220.4, 5950.0),
+            ][..],
+        ),
+        (
+            Supplement::MidlatitudeWinter,
+            &[
+                (0.0, 272.2, 101800.0),
+                (10000.0, 219.7, 25680.0),
+            ][..],
+        ),
+        (Supplement::SubtropicalSummer, &[(0.0, 301.2, 101500.0)][..]),
+        (Supplement::SubtropicalWinter, &[(0.0, 287.2, 102200.0)][..]),
+        (Supplement::SubarcticSummer, &[(0.0, 287.2, 101000.0)][..]),
+        (
+            Supplement::SubarcticWinter,
+            &[(0.0, 257.2, 101300.0), (1000.0, 259.1, Scalar::NAN)][..],
+        ),
+    ];
+    for (supplement, table) in tables {
+        assert_table(AtmosphereSource::Supplement(supplement), table, 1.5, 0.02);
+    }
+}
+
+#[test]
+fn mil_std_210_days_match_their_tables() {
+    // 103 °F on the hot day; -60 °F on the cold day, under an inversion that
+    // reaches -20 °F at 1 km. Above 32 km both follow the 1976 standard.
+    assert_table(
+        AtmosphereSource::MilStd210(MilStd210::Hot),
+        &[(0.0, 312.6, 101325.0), (40000.0, 250.35, Scalar::NAN)],
+        1.5,
+        0.02,
+    );
+    assert_table(
+        AtmosphereSource::MilStd210(MilStd210::Cold),
+        &[
+            (0.0, 222.1, 101325.0),
+            (1000.0, 244.3, Scalar::NAN),
+            (40000.0, 250.35, Scalar::NAN),
+        ],
+        1.5,
+        0.02,
+    );
+}
+
+#[test]
+fn empirical_atmosphere_is_not_baked() {
+    // A table would hold the air of noon on New Year's Day over the origin
+    // everywhere and all year.
+    let model = Atmosphere::new(AtmosphereSource::Empirical(SpaceWeather::default()));
+    let baked = model.clone().with_table(100.0);
+    assert_eq!(baked.table_step(), None);
+    assert_eq!(baked, model);
+
+    let mut scenario = Scenario::default();
+    scenario.atmosphere_table = Some(10.0);
+    assert!(scenario.validate().is_ok());
+    assert_eq!(scenario.atmosphere().table_step(), Some(10.0));
+    scenario.atmosphere = AtmosphereSource::Empirical(SpaceWeather::default());
+    assert!(matches!(
+        scenario.validate(),
+        Err(ScenarioError::Invalid { field, .. }) if field == "atmosphere_table"
+    ));
+}
@@ -0,0 +1,103 @@
+//! Ballast must leave the payload no faster than its flow rate, lighten the
+//! body that carries it, and drop only once each time a rule is triggered.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::math::Scalar;
+use bevy::prelude::*;
+use buoy_core::{prelude::*, quantity::Length, scenario::BallastScenario};
+use uom::si::length::meter;
+
+/// Every drop of ballast reported so far.
+#[derive(Resource, Default)]
+struct Drops(Vec<BallastDropped>);
+
+fn record_drops(mut dropped: EventReader<BallastDropped>, mut drops: ResMut<Drops>) {
+    drops.0.extend(dropped.read().copied());
+}
+
+/// A simulation of the default flight train with some ballast in its payload,
+/// that keeps every drop of ballast.
+fn sim(ballast: BallastScenario) -> HeadlessSim {
+    let mut scenario = common::scenario();
+    scenario.balloons[0].ballast = Some(ballast);
+    let mut sim = common::sim(scenario, Duration::from_secs(1));
+    sim.app_mut()
+        .init_resource::<Drops>()
+        .add_systems(Update, record_drops);
+    sim
+}
+
+fn payload(sim: &mut HeadlessSim) -> Entity {
+    let world = sim.world_mut();
+    world.query_filtered::<Entity, With<Payload>>().single(world)
+}
+
+/// Ballast (kg) left in the payload, and the mass (kg) of the payload body.
+fn masses(sim: &HeadlessSim, payload: Entity) -> (Scalar, Scalar) {
+    let world = sim.world();
+    let ballast = world.get::<Ballast>(payload).unwrap();
+    let body = world.get::<avian3d::prelude::Mass>(payload).unwrap();
+    (ballast.mass.get::<kilogram>(), body.0)
+}
+
+#[test]
+fn commanded_drop_is_limited_by_the_flow_rate() {
+    let mut sim = sim(BallastScenario {
+        mass: 1.0,
+        flow_rate: 0.1,
+        rules: Vec::new(),
+    });
+    sim.run();
+    let payload = payload(&mut sim);
+    let (ballast, body) = masses(&sim, payload);
+    assert_eq!(ballast, 1.0);
+
+    sim.world_mut().send_event(BallastCommand {
+        entity: payload,
+        action: BallastAction::Release(Mass::new::<kilogram>(0.5)),
+    });
+    // Three seconds at 0.1 kg/s is only 0.3 kg of the 0.5 kg asked for, and
+    // all of it comes off the body that carried it.
+    sim.max_duration = Duration::from_secs(4);
+    sim.run();
+    let (ballast_after, body_after) = masses(&sim, payload);
+    assert!((ballast - ballast_after - 0.3).abs() < 5e-3, "{ballast_after} kg left");
+    assert!((body - body_after - 0.3).abs() < 5e-3, "body is {body_after} kg");
+    assert!(sim.world().resource::<Drops>().0.is_empty());
+
+    // The rest of the drop takes two more seconds, and is reported once.
+    sim.max_duration = Duration::from_secs(10);
+    sim.run();
+    let drops = &sim.world().resource::<Drops>().0;
+    assert_eq!(drops.len(), 1);
+    assert_eq!(drops[0].entity, payload);
+    assert!((drops[0].mass.get::<kilogram>() - 0.5).abs() < 1e-4);
+    assert!((drops[0].remaining.get::<kilogram>() - 0.5).abs() < 1e-4);
+    let (ballast, body) = masses(&sim, payload);
+    assert!((ballast - 0.5).abs() < 1e-4);
+    assert!((body_after - body - 0.2).abs() < 5e-3);
+}
+
+#[test]
+fn rule_drops_once_per_excursion() {
+    // The flight train climbs at about 3 m/s, so it spends several seconds
+    // below the rule's altitude while each drop takes a few steps.
+    let mut sim = sim(BallastScenario {
+        mass: 1.0,
+        flow_rate: 1.0,
+        rules: vec![BallastRule::MinimumAltitude {
+            altitude: Length::new::<meter>(20.0),
+            amount: Mass::new::<kilogram>(0.05),
+        }],
+    });
+    sim.max_duration = Duration::from_secs(30);
+    sim.run();
+    let payload = payload(&mut sim);
+    let drops = &sim.world().resource::<Drops>().0;
+    assert_eq!(drops.len(), 1, "{} drops", drops.len());
+    assert!(drops[0].altitude < 20.0);
+    assert!((masses(&sim, payload).0 - 0.95).abs() < 1e-4);
+}
@@ -0,0 +1,74 @@
+//! Fixtures shared by the integration tests. Each test file uses its own part
+//! of them.
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use avian3d::math::Scalar;
+use bevy::prelude::{Commands, Entity, PostStartup, Query, With};
+use buoy_core::{
+    prelude::*,
+    scenario::{BallastScenario, VentValveScenario},
+};
+
+/// The default scenario, without telemetry files so tests don't write to the
+/// output directory.
+pub fn scenario() -> Scenario {
+    let mut scenario = Scenario::default();
+    scenario.outputs.telemetry.csv = false;
+    scenario.outputs.telemetry.parquet = false;
+    scenario
+}
+
+/// The default scenario with a vent valve of some orifice area (m²) on the
+/// balloon, and some lift gas and ballast (kg) for a controller to work with.
+pub fn controllable_scenario(
+    orifice_area: Scalar,
+    lift_gas: Scalar,
+    ballast: Scalar,
+) -> Scenario {
+    let mut scenario = scenario();
+    let balloon = &mut scenario.balloons[0];
+    balloon.lift_gas.mass = lift_gas;
+    balloon.vent_valve = Some(VentValveScenario {
+        orifice_area,
+        discharge_coefficient: 0.6,
+    });
+    balloon.ballast = Some(BallastScenario {
+        mass: ballast,
+        flow_rate: 0.1,
+        rules: Vec::new(),
+    });
+    scenario
+}
+
+/// A deterministic headless simulation of a scenario, for at most some
+/// duration.
+pub fn sim(scenario: Scenario, max_duration: Duration) -> HeadlessSim {
+    HeadlessSim::deterministic()
+        .with_scenario(scenario)
+        .with_max_duration(max_duration)
+}
+
+/// Run a simulation to the end, and return how it ended with the reason of
+/// every fault.
+pub fn run(sim: &mut HeadlessSim) -> (SimOutcome, Vec<FaultReason>) {
+    let outcome = sim.run();
+    let reasons = sim.faults().map(|fault| fault.reason).collect();
+    (outcome, reasons)
+}
+
+/// Put every balloon of a simulation under a controller that runs some
+/// control law, with the ballast of its payload.
+pub fn control(sim: &mut HeadlessSim, law: impl ControlLaw + Clone) {
+    let attach = move |mut commands: Commands,
+                       balloons: Query<Entity, With<Balloon>>,
+                       payloads: Query<Entity, With<Payload>>| {
+        for (balloon, payload) in balloons.iter().zip(payloads.iter()) {
+            commands
+                .entity(balloon)
+                .insert(Controller::new(law.clone()).with_ballast(payload));
+        }
+    };
+    sim.app_mut().add_systems(PostStartup, attach);
+}
@@ -0,0 +1,118 @@
+//! The built-in control laws must hold a balloon near their target altitude
+//! with the vent valve and ballast it carries.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{math::Scalar, prelude::Position};
+use bevy::prelude::*;
+use buoy_core::{
+    prelude::*,
+    quantity::{Length, MassRate},
+};
+use uom::si::{length::meter, mass_rate::kilogram_per_second};
+
+const TARGET: Scalar = 300.0;
+
+/// Altitude (m) of the balloon at each step, and every drop of ballast.
+#[derive(Resource, Default)]
+struct Flight {
+    altitudes: Vec<(Duration, Scalar)>,
+    drops: Vec<BallastDropped>,
+}
+
+fn record_altitude(
+    balloons: Query<&Position, With<Balloon>>,
+    time: Res<Time>,
+    mut flight: ResMut<Flight>,
+) {
+    let altitude = balloons.single().y;
+    flight.altitudes.push((time.elapsed(), altitude));
+}
+
+fn record_drops(mut dropped: EventReader<BallastDropped>, mut flight: ResMut<Flight>) {
+    flight.drops.extend(dropped.read().copied());
+}
+
+/// Fly a balloon under some control law for a while, and record its flight.
+fn fly(scenario: Scenario, law: impl ControlLaw + Clone, duration: Duration) -> Flight {
+    let mut sim = common::sim(scenario, duration);
+    common::control(&mut sim, law);
+    sim.app_mut()
+        .init_resource::<Flight>()
+        .add_systems(
+            FixedUpdate,
+            record_altitude.run_if(in_state(SimState::Running)),
+        )
+        .add_systems(Update, record_drops);
+    let outcome = sim.run();
+    assert_eq!(outcome.state, SimState::Running);
+    sim.world_mut().remove_resource::<Flight>().unwrap()
+}
+
+/// The lowest and highest altitude (m) of a flight after some time.
+fn range_after(flight: &Flight, time: Duration) -> (Scalar, Scalar) {
+    flight
+        .altitudes
+        .iter()
+        .filter(|(elapsed, _)| *elapsed >= time)
+        .fold((Scalar::INFINITY, Scalar::NEG_INFINITY), |(low, high), (_, altitude)| {
+            (low.min(*altitude), high.max(*altitude))
+        })
+}
+
+#[test]
+fn pid_holds_the_target_altitude() {
+    // Proportional and derivative only: the balloon climbs slowly enough that
+    // braking on the ascent rate stops it at the target without overshoot.
+    let law = PidAltitudeHold::new(Length::new::<meter>(TARGET), 0.01, 0.0, 1.0)
+        .with_ballast_rate(MassRate::new::<kilogram_per_second>(0.005));
+    let flight = fly(
+        common::controllable_scenario(0.0005, 0.6, 1.3),
+        law,
+        Duration::from_secs(1200),
+    );
+    let (low, high) = range_after(&flight, Duration::from_secs(600));
+    assert!(
+        low > TARGET - 30.0 && high < TARGET + 30.0,
+        "balloon wandered between {low} m and {high} m"
+    );
+}
+
+#[test]
+fn bang_bang_holds_the_band_with_one_drop_per_excursion() {
+    const BAND: Scalar = 50.0;
+    const DROP: Scalar = 0.3;
+    let law = BangBangAltitudeHold::new(
+        Length::new::<meter>(TARGET),
+        Length::new::<meter>(BAND),
+        Mass::new::<kilogram>(DROP),
+    );
+    // The valve is small, so the balloon swings a couple hundred meters
+    // above the band while it vents, and a drop sends it back up each time
+    // it sinks below the band.
+    let flight = fly(
+        common::controllable_scenario(0.0001, 0.65, 1.6),
+        law,
+        Duration::from_secs(1800),
+    );
+    let (low, high) = range_after(&flight, Duration::from_secs(600));
+    assert!(
+        low > TARGET - 2.0 * BAND && high < TARGET + 6.0 * BAND,
+        "balloon wandered between {low} m and {high} m"
+    );
+
+    // Count the times the balloon sank below the band after the launch.
+    let lower = TARGET - BAND;
+    let excursions = flight
+        .altitudes
+        .windows(2)
+        .filter(|pair| pair[0].1 >= lower && pair[1].1 < lower)
+        .count();
+    assert!(excursions >= 2, "{excursions} excursions below the band");
+    assert_eq!(flight.drops.len(), excursions);
+    for drop in &flight.drops {
+        assert!((drop.mass.get::<kilogram>() - DROP).abs() < 1e-4);
+    }
+}
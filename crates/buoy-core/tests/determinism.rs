@@ -0,0 +1,67 @@
+//! Two runs of the same scenario in deterministic mode must produce
+//! bit-identical telemetry.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::math::{Scalar, Vector};
+use buoy_core::prelude::*;
+
+/// Long enough to climb well clear of the launch site and swing on the lines.
+const RUN_DURATION: Duration = Duration::from_secs(120);
+
+fn scenario() -> Scenario {
+    let mut scenario = common::scenario();
+    scenario.wind = Wind::uniform(Vector::new(5.0, 0.0, -2.0));
+    scenario.outputs.telemetry.sample_rate = 4.0;
+    scenario
+}
+
+fn run(scenario: Scenario) -> TelemetryLog {
+    let mut sim = common::sim(scenario, RUN_DURATION);
+    sim.run();
+    sim.world().resource::<TelemetryLog>().clone()
+}
+
+/// Compare two logs bit for bit and report the first sample that differs.
+fn assert_identical(first: &TelemetryLog, second: &TelemetryLog) {
+    assert!(!first.is_empty(), "no telemetry was recorded");
+    assert_eq!(first.len(), second.len(), "different number of samples");
+    assert_eq!(
+        first.time.iter().map(|t| t.to_bits()).collect::<Vec<_>>(),
+        second.time.iter().map(|t| t.to_bits()).collect::<Vec<_>>(),
+        "samples were taken at different times"
+    );
+    assert_eq!(first.columns.len(), second.columns.len());
+    for (a, b) in first.columns.iter().zip(&second.columns) {
+        assert_eq!(a.name, b.name);
+        for (row, (x, y)) in a.values.iter().zip(&b.values).enumerate() {
+            assert_eq!(
+                x.map(Scalar::to_bits),
+                y.map(Scalar::to_bits),
+                "{} differs at t = {} s: {:?} != {:?}",
+                a.name,
+                first.time[row],
+                x,
+                y
+            );
+        }
+    }
+}
+
+#[test]
+fn identical_runs_produce_identical_telemetry() {
+    let first = run(scenario());
+    let second = run(scenario());
+    assert_identical(&first, &second);
+}
+
+#[test]
+fn telemetry_changes_with_the_scenario() {
+    let first = run(scenario());
+    let mut lighter = scenario();
+    lighter.balloons[0].payload.mass *= 0.5;
+    let second = run(lighter);
+    assert_ne!(first, second);
+}
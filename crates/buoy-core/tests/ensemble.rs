@@ -0,0 +1,136 @@
+//! The statistics of an ensemble must describe its scatter of points the way
+//! the textbook formulas for a normal distribution do.
+
+use std::time::Duration;
+
+use avian3d::math::{Scalar, Vector, Vector2};
+use buoy_core::{constants::PI, prelude::*};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+
+/// Radius, in standard deviations, of the circle that holds half of a
+/// circular normal distribution: √(2 ln 2).
+const CEP_PER_SIGMA: Scalar = 1.177_410_0;
+
+fn assert_close(actual: Scalar, expected: Scalar, tolerance: Scalar, what: &str) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{what} is {actual}, expected {expected}"
+    );
+}
+
+/// Points drawn from a normal distribution around the origin, with standard
+/// deviations (m) along axes that are turned by an angle (rad) from x and y.
+fn normal_points(sigma_x: Scalar, sigma_y: Scalar, angle: Scalar) -> Vec<Vector2> {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let (sin, cos) = angle.sin_cos();
+    (0..20_000)
+        .map(|_| {
+            let x = rng.sample::<Scalar, _>(StandardNormal) * sigma_x;
+            let y = rng.sample::<Scalar, _>(StandardNormal) * sigma_y;
+            Vector2::new(cos * x - sin * y, sin * x + cos * y)
+        })
+        .collect()
+}
+
+#[test]
+fn scatter_of_a_cross() {
+    // Two points 4 m to either side and two 2 m above and below the center
+    // at (10, 5): variances of 16/2 and 4/2, and half of the points are
+    // within 2 m.
+    let points = [
+        Vector2::new(14.0, 5.0),
+        Vector2::new(6.0, 5.0),
+        Vector2::new(10.0, 7.0),
+        Vector2::new(10.0, 3.0),
+    ];
+    let scatter = Scatter::from_points(&points).unwrap();
+    assert_eq!(scatter.count, 4);
+    assert_eq!(scatter.mean, Vector2::new(10.0, 5.0));
+    assert_eq!(scatter.covariance.x_axis, Vector2::new(8.0, 0.0));
+    assert_eq!(scatter.covariance.y_axis, Vector2::new(0.0, 2.0));
+    assert_eq!(scatter.cep, 2.0);
+
+    // The 39.35 % ellipse, where √(-2 ln(1 - p)) is 1, has the standard
+    // deviations as its semi-axes.
+    let ellipse = scatter.ellipse(1.0 - Scalar::exp(-0.5));
+    assert_eq!(ellipse.center, scatter.mean);
+    assert_close(ellipse.semi_major, Scalar::sqrt(8.0), 1e-4, "semi-major axis");
+    assert_close(ellipse.semi_minor, Scalar::sqrt(2.0), 1e-4, "semi-minor axis");
+    assert_eq!(ellipse.orientation, 0.0);
+
+    assert!(Scatter::from_points(&[]).is_none());
+}
+
+#[test]
+fn cep_of_a_circular_normal_scatter() {
+    let scatter = Scatter::from_points(&normal_points(20.0, 20.0, 0.0)).unwrap();
+    assert_close(scatter.cep, CEP_PER_SIGMA * 20.0, 0.5, "CEP");
+}
+
+#[test]
+fn error_ellipse_of_an_elliptical_normal_scatter() {
+    // Turned 30° from the x axis, with standard deviations of 30 m and 10 m.
+    let angle = PI / 6.0;
+    let points = normal_points(30.0, 10.0, angle);
+    let scatter = Scatter::from_points(&points).unwrap();
+    assert_close(scatter.mean.length(), 0.0, 1.0, "distance of the mean");
+
+    let ellipse = scatter.ellipse(0.5);
+    assert_close(ellipse.semi_major, CEP_PER_SIGMA * 30.0, 0.6, "semi-major axis");
+    assert_close(ellipse.semi_minor, CEP_PER_SIGMA * 10.0, 0.2, "semi-minor axis");
+    assert_close(ellipse.orientation, angle, 0.01, "orientation");
+
+    // The 90 % ellipse holds 90 % of the points.
+    let ellipse = scatter.ellipse(0.9);
+    let (sin, cos) = ellipse.orientation.sin_cos();
+    let inside = points
+        .iter()
+        .filter(|point| {
+            let d = **point - ellipse.center;
+            let major = cos * d.x + sin * d.y;
+            let minor = -sin * d.x + cos * d.y;
+            (major / ellipse.semi_major).powi(2) + (minor / ellipse.semi_minor).powi(2) <= 1.0
+        })
+        .count();
+    assert_close(inside as Scalar / points.len() as Scalar, 0.9, 0.01, "share inside");
+}
+
+#[test]
+fn burst_altitude_of_the_runs_that_burst() {
+    let run = |index, bursts: Vec<Vector>| RunResult {
+        index,
+        perturbation: Perturbation::default(),
+        outcome: SimOutcome {
+            state: SimState::Stopped,
+            elapsed: Duration::ZERO,
+            steps: 0,
+        },
+        bursts,
+        landings: Vec::new(),
+    };
+    let mut result = EnsembleResult {
+        runs: vec![run(0, Vec::new())],
+    };
+    assert_eq!(result.burst_altitude(), None);
+
+    // Bursts at 1, 2 and 3 km, and a run that never burst.
+    result.runs.extend([
+        run(1, vec![Vector::new(100.0, 1000.0, 0.0)]),
+        run(2, vec![Vector::new(0.0, 2000.0, -50.0)]),
+        run(3, vec![Vector::new(0.0, 3000.0, 0.0)]),
+    ]);
+    let (mean, std_dev) = result.burst_altitude().unwrap();
+    assert_close(mean, 2000.0, 1e-3, "mean burst altitude");
+    // √((1000² + 0 + 1000²) / 3)
+    assert_close(std_dev, 816.4966, 1e-2, "standard deviation");
+    assert_eq!(
+        result.burst_points(),
+        vec![
+            Vector2::new(100.0, 0.0),
+            Vector2::new(0.0, -50.0),
+            Vector2::new(0.0, 0.0),
+        ]
+    );
+}
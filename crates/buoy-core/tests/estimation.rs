@@ -0,0 +1,110 @@
+//! Fitting a parameter to a flight simulated with a known value of it must
+//! find that value, within the confidence interval it reports.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{Physics, PhysicsSet, Position},
+};
+use bevy::prelude::*;
+use buoy_core::prelude::*;
+use chrono::TimeDelta;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::Normal;
+
+const DRAG_COEFFICIENT: Scalar = 0.3;
+/// Standard deviation (m) of the noise added to each axis of every fix.
+const GPS_NOISE: Scalar = 2.0;
+const FIX_INTERVAL: Duration = Duration::from_secs(10);
+const FLIGHT: Duration = Duration::from_secs(200);
+
+/// Position (m) of the balloon at every fix, and when.
+#[derive(Resource, Default)]
+struct Fixes(Vec<(Duration, Vector)>);
+
+fn record_fixes(
+    balloons: Query<&Position, With<Balloon>>,
+    time: Res<Time<Physics>>,
+    mut fixes: ResMut<Fixes>,
+) {
+    let elapsed = time.elapsed();
+    if elapsed.as_nanos() % FIX_INTERVAL.as_nanos() == 0 {
+        fixes.0.push((elapsed, balloons.single().0));
+    }
+}
+
+/// The track of a flight simulated with the default drag coefficient, as a
+/// GPS with some noise would have recorded it.
+fn recorded_track(scenario: &Scenario) -> Track {
+    let mut sim = common::sim(scenario.clone(), FLIGHT);
+    sim.app_mut().init_resource::<Fixes>().add_systems(
+        FixedPostUpdate,
+        record_fixes
+            .after(PhysicsSet::StepSimulation)
+            .run_if(in_state(SimState::Running)),
+    );
+    assert_eq!(sim.run().state, SimState::Running);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let noise = Normal::new(0.0, GPS_NOISE).unwrap();
+    let points = sim
+        .world()
+        .resource::<Fixes>()
+        .0
+        .iter()
+        .map(|(elapsed, position)| {
+            let position = *position
+                + Vector::new(rng.sample(noise), rng.sample(noise), rng.sample(noise));
+            let (latitude, longitude) = scenario.launch_site.coordinates(position);
+            TrackPoint {
+                time: scenario.epoch + TimeDelta::from_std(*elapsed).unwrap(),
+                latitude,
+                longitude,
+                altitude: position.y,
+            }
+        })
+        .collect();
+    Track::new(points).unwrap()
+}
+
+#[test]
+fn fitted_drag_coefficient_covers_the_true_one() {
+    let mut scenario = common::scenario();
+    scenario.balloons[0].envelope.drag_coefficient = DRAG_COEFFICIENT;
+    let track = recorded_track(&scenario);
+    assert_eq!(track.points().len(), 20);
+
+    // Start a quarter too high.
+    scenario.balloons[0].envelope.drag_coefficient = 1.25 * DRAG_COEFFICIENT;
+    let result = Estimation::new(scenario, track, "Balloon")
+        .with_parameters(vec![Parameter::DragCoefficient])
+        .with_max_runs(40)
+        .run()
+        .unwrap();
+
+    let [fitted] = result.parameters.as_slice() else {
+        panic!("expected one parameter, got {:?}", result.parameters);
+    };
+    let (low, high) = fitted.confidence_interval().unwrap();
+    let truth = DRAG_COEFFICIENT as f64;
+    assert!(
+        low <= truth && truth <= high,
+        "fitted {} with a 95% interval of {low} to {high}",
+        fitted.value
+    );
+    assert!(((fitted.value - truth) / truth).abs() < 0.02);
+
+    // What is left is the noise: √3 σ over the three axes of each fix.
+    assert_eq!(result.fixes, 20);
+    let noise = 3.0_f64.sqrt() * GPS_NOISE as f64;
+    assert!(
+        (result.rms - noise).abs() < 0.2 * noise,
+        "rms position error {} m, expected about {noise} m",
+        result.rms
+    );
+    assert!(result.initial_rms > result.rms);
+}
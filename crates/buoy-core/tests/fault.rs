@@ -0,0 +1,106 @@
+//! The fault policy of a scenario decides whether a fault stops the run.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{math::Vector, prelude::LinearVelocity};
+use bevy::prelude::*;
+use buoy_core::prelude::*;
+
+const RUN_DURATION: Duration = Duration::from_secs(5);
+
+/// A balloon that bursts on the first step, with a fault policy for bursts.
+fn burst(action: FaultAction) -> (SimOutcome, Vec<FaultReason>) {
+    let mut scenario = common::scenario();
+    // High enough that the remains of the balloon stay inside the atmosphere
+    // model while they fall.
+    scenario.launch_site.altitude = 1600.0;
+    scenario.balloons[0].envelope.burst_diameter = Some(0.1);
+    scenario.faults.burst = action;
+    common::run(&mut common::sim(scenario, RUN_DURATION))
+}
+
+#[test]
+fn stop_policy_faults_the_run() {
+    let (outcome, reasons) = burst(FaultAction::Stop);
+    assert_eq!(outcome.state, SimState::Faulted);
+    assert!(outcome.elapsed < RUN_DURATION);
+    assert!(matches!(reasons[..], [FaultReason::Burst { .. }]));
+}
+
+#[test]
+fn log_policy_keeps_running() {
+    let (outcome, reasons) = burst(FaultAction::Log);
+    assert_eq!(outcome.state, SimState::Running);
+    assert_eq!(outcome.elapsed, RUN_DURATION);
+    assert!(matches!(reasons[..], [FaultReason::Burst { .. }]));
+}
+
+/// The remains of a balloon that bursts on the first step just above the
+/// bottom of the atmosphere model, and fall out of it.
+fn fall_out_of_the_atmosphere(out_of_range: OutOfRange) -> (SimOutcome, Vec<FaultReason>) {
+    let mut scenario = common::scenario();
+    scenario.launch_site.altitude = -50.0;
+    scenario.balloons[0].envelope.burst_diameter = Some(0.1);
+    scenario.atmosphere_out_of_range = out_of_range;
+    common::run(&mut common::sim(scenario, Duration::from_secs(20)))
+}
+
+#[test]
+fn leaving_the_atmosphere_faults_only_when_it_cannot_answer() {
+    let (outcome, reasons) = fall_out_of_the_atmosphere(OutOfRange::Error);
+    assert_eq!(outcome.state, SimState::Faulted);
+    assert!(reasons
+        .iter()
+        .any(|reason| matches!(reason, FaultReason::AtmosphereOutOfBounds { .. })));
+
+    for out_of_range in [OutOfRange::Clamp, OutOfRange::Extrapolate] {
+        let (outcome, reasons) = fall_out_of_the_atmosphere(out_of_range);
+        assert_eq!(outcome.state, SimState::Running, "{:?}", out_of_range);
+        assert!(matches!(reasons[..], [FaultReason::Burst { .. }]), "{:?}", reasons);
+    }
+}
+
+#[test]
+fn a_state_that_is_not_a_number_faults_the_run() {
+    fn poison(mut payloads: Query<&mut LinearVelocity, With<Payload>>) {
+        for mut velocity in payloads.iter_mut() {
+            velocity.0 = Vector::NAN;
+        }
+    }
+    let mut sim = common::sim(common::scenario(), RUN_DURATION);
+    sim.app_mut().add_systems(FixedUpdate, poison);
+    let (outcome, reasons) = common::run(&mut sim);
+    assert_eq!(outcome.state, SimState::Faulted);
+    assert!(reasons.contains(&FaultReason::NanState), "{:?}", reasons);
+}
+
+/// A flight train that starts high above the launch site with a balloon
+/// that bursts at once, so the payload comes down on the ground under its
+/// parachute.
+fn drop_onto_the_ground(action: FaultAction) -> (SimOutcome, Vec<FaultReason>) {
+    let mut scenario = common::scenario();
+    scenario.balloons[0].offset = [0.0, 50.0, 0.0];
+    scenario.balloons[0].envelope.burst_diameter = Some(0.1);
+    scenario.faults.ground_impact = action;
+    common::run(&mut common::sim(scenario, Duration::from_secs(120)))
+}
+
+#[test]
+fn ground_impact_follows_its_policy() {
+    let (outcome, reasons) = drop_onto_the_ground(FaultAction::Stop);
+    assert_eq!(outcome.state, SimState::Faulted);
+    let Some(FaultReason::GroundImpact { speed }) = reasons.last() else {
+        panic!("expected a ground impact, got {:?}", reasons);
+    };
+    assert!(*speed > 0.0);
+
+    // Landing is also a stop condition of the default scenario.
+    let (outcome, reasons) = drop_onto_the_ground(FaultAction::Log);
+    assert_eq!(outcome.state, SimState::Stopped);
+    assert!(matches!(
+        reasons[..],
+        [FaultReason::Burst { .. }, FaultReason::GroundImpact { .. }]
+    ));
+}
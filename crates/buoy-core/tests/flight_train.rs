@@ -0,0 +1,93 @@
+//! The lines of a flight train must carry the payload at their length, and
+//! the balloon must burst when it grows to its burst diameter.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{DistanceJoint, Position, Rotation},
+};
+use bevy::prelude::{Entity, With};
+use buoy_core::{constants::PI, prelude::*};
+
+/// Standard gravity (m/s²). The flights here stay low enough that the change
+/// of gravity with altitude is well inside the tolerance.
+const GRAVITY: Scalar = 9.80665;
+
+#[test]
+fn rigging_line_holds_the_payload_at_its_length() {
+    let scenario = common::scenario();
+    let payload_mass = scenario.balloons[0].payload.mass;
+    let line = scenario.balloons[0].rigging_line.clone();
+    let mut sim = common::sim(scenario, Duration::from_secs(60));
+    let outcome = sim.run();
+    assert_eq!(outcome.state, SimState::Running);
+
+    let world = sim.world_mut();
+    let payload = world
+        .query_filtered::<Entity, With<Payload>>()
+        .single(world);
+    let (rigging_line, joint) = world
+        .query::<(&RiggingLine, &DistanceJoint)>()
+        .iter(world)
+        .find(|(rigging_line, _)| rigging_line.lower.0 == payload)
+        .map(|(rigging_line, joint)| (*rigging_line, *joint))
+        .unwrap();
+    let anchor = |entity, local_anchor: Vector| {
+        let position = world.get::<Position>(entity).unwrap();
+        let rotation = world.get::<Rotation>(entity).unwrap();
+        position.0 + rotation.0 * local_anchor
+    };
+    let spacing = anchor(joint.entity1, joint.local_anchor1)
+        .distance(anchor(joint.entity2, joint.local_anchor2));
+
+    // The payload hangs from the line while it climbs, so the line is taut
+    // but stretches only by its load over its stiffness, about a millimeter.
+    assert!(
+        (spacing - line.length).abs() < 0.01,
+        "line is {spacing} m long, expected {} m",
+        line.length
+    );
+    // The line carries the weight of the payload and the little drag on it.
+    let weight = payload_mass * GRAVITY;
+    assert!(
+        rigging_line.tension > 0.5 * weight && rigging_line.tension < 2.0 * weight,
+        "tension is {} N under a {weight} N payload",
+        rigging_line.tension
+    );
+}
+
+#[test]
+fn balloon_bursts_at_its_burst_diameter() {
+    // The default balloon is about 1.89 m across at sea level and reaches
+    // 1.95 m a little under a kilometer up.
+    const BURST_DIAMETER: Scalar = 1.95;
+    let mut scenario = common::scenario();
+    scenario.balloons[0].envelope.burst_diameter = Some(BURST_DIAMETER);
+    scenario.faults.burst = FaultAction::Stop;
+    let gas_mass = scenario.balloons[0].lift_gas.mass;
+    let mut sim = common::sim(scenario, Duration::from_secs(900));
+    let (outcome, reasons) = common::run(&mut sim);
+    assert_eq!(outcome.state, SimState::Faulted);
+    let [FaultReason::Burst { altitude }] = reasons[..] else {
+        panic!("expected a single burst, got {:?}", reasons);
+    };
+
+    // Size of the lift gas in the air where the balloon burst.
+    let atmosphere = sim.world().resource::<Atmosphere>();
+    let position = Vector::Y * altitude;
+    let gas = IdealGas::new(
+        GasSpecies::helium(),
+        atmosphere.temperature(position),
+        atmosphere.pressure(position),
+        Mass::new::<kilogram>(gas_mass),
+    );
+    let diameter = 2.0 * (3.0 * gas.volume().get::<cubic_meter>() / (4.0 * PI)).cbrt();
+    // A step of the climb grows the balloon by far less than a millimeter.
+    assert!(
+        diameter >= BURST_DIAMETER && diameter < BURST_DIAMETER + 1e-3,
+        "balloon burst {diameter} m across at {altitude} m"
+    );
+}
@@ -0,0 +1,62 @@
+//! Moist air must follow the saturation vapor pressure and virtual temperature
+//! of the meteorological tables.
+
+use avian3d::math::Scalar;
+use buoy_core::{
+    humidity::{dewpoint, saturation_vapor_pressure, virtual_temperature},
+    prelude::*,
+};
+
+fn temperature(kelvins: Scalar) -> ThermodynamicTemperature {
+    ThermodynamicTemperature::new::<kelvin>(kelvins)
+}
+
+fn pressure(pascals: Scalar) -> Pressure {
+    Pressure::new::<pascal>(pascals)
+}
+
+#[test]
+fn magnus_matches_the_saturation_table() {
+    // Saturation vapor pressure (Pa) over liquid water, from the WMO tables.
+    // The Magnus fit is good to a few tenths of a percent over this range.
+    for (celsius, expected) in [
+        (-10.0, 286.5),
+        (0.0, 611.2),
+        (10.0, 1228.1),
+        (20.0, 2339.2),
+        (30.0, 4246.0),
+        (40.0, 7384.9),
+    ] {
+        let actual = saturation_vapor_pressure(temperature(celsius + 273.15)).get::<pascal>();
+        let error = ((actual - expected) / expected).abs();
+        assert!(
+            error < 5e-3,
+            "saturation at {celsius} °C is {actual} Pa, expected {expected} Pa"
+        );
+    }
+}
+
+#[test]
+fn dewpoint_inverts_the_saturation_vapor_pressure() {
+    for kelvins in [250.0, 273.15, 300.0] {
+        let vapor_pressure = saturation_vapor_pressure(temperature(kelvins));
+        let actual = dewpoint(vapor_pressure).get::<kelvin>();
+        assert!((actual - kelvins).abs() < 1e-3, "dewpoint is {actual} K");
+    }
+}
+
+#[test]
+fn virtual_temperature_of_moist_air() {
+    // Dry air is its own virtual temperature.
+    let dry = virtual_temperature(temperature(288.15), pressure(101325.0), pressure(0.0));
+    assert_eq!(dry.get::<kelvin>(), 288.15);
+
+    // 2 kPa of vapor in 100 kPa of air at 300 K: a specific humidity q of
+    // 12.53 g/kg, and the textbook Tv = T (1 + 0.608 q) is 302.29 K.
+    let moist = virtual_temperature(temperature(300.0), pressure(100000.0), pressure(2000.0));
+    assert!(
+        (moist.get::<kelvin>() - 302.29).abs() < 0.01,
+        "virtual temperature is {:?}",
+        moist
+    );
+}
@@ -0,0 +1,104 @@
+//! The fill calculator must hit the target it was asked for, and the fill it
+//! gives must actually produce that lift in the atmosphere at the launch site.
+
+use avian3d::math::{Scalar, Vector};
+use buoy_core::{
+    constants::PI,
+    launch::fill,
+    prelude::*,
+    quantity::{Length, Velocity},
+};
+use uom::si::{length::meter, velocity::meter_per_second};
+
+/// Standard gravity (m/s²). The launch sites here are low enough that the
+/// change of gravity with altitude is well inside the tolerance.
+const GRAVITY: Scalar = 9.80665;
+
+fn request(target: FillTarget) -> FillRequest {
+    FillRequest {
+        payload_mass: Mass::new::<kilogram>(1.5),
+        balloon_mass: Mass::new::<kilogram>(1.2),
+        burst_diameter: Length::new::<meter>(8.0),
+        drag_coefficient: 0.3,
+        species: GasSpecies::helium(),
+        launch_altitude: Length::new::<meter>(250.0),
+        target,
+    }
+}
+
+fn assert_close(actual: Scalar, expected: Scalar, what: &str) {
+    let error = ((actual - expected) / expected).abs();
+    assert!(
+        error < 1e-3,
+        "{what} is {actual}, expected {expected} (relative error {error})"
+    );
+}
+
+/// Free lift (kg) the fill really gives: the air it displaces less the gas,
+/// balloon and payload.
+fn actual_free_lift(request: &FillRequest, solution: &FillSolution, air: &Atmosphere) -> Scalar {
+    let site = Vector::Y * request.launch_altitude.get::<meter>();
+    let air_density = air.density(site).get::<kilogram_per_cubic_meter>();
+    solution.fill_volume.get::<cubic_meter>() * air_density
+        - solution.gas_mass.get::<kilogram>()
+        - (request.payload_mass + request.balloon_mass).get::<kilogram>()
+}
+
+#[test]
+fn fill_hits_the_requested_free_lift() {
+    let atmosphere = Atmosphere::new(AtmosphereSource::StandardAtmosphere1976);
+    let request = request(FillTarget::FreeLift(Mass::new::<kilogram>(0.8)));
+    let solution = fill(&request, &atmosphere).unwrap();
+
+    assert_close(solution.free_lift.get::<kilogram>(), 0.8, "free lift");
+    assert_close(
+        actual_free_lift(&request, &solution, &atmosphere),
+        0.8,
+        "lift of the fill",
+    );
+    assert_close(
+        solution.neck_lift.get::<kilogram>(),
+        1.5 + 0.8,
+        "neck lift",
+    );
+    assert!(solution.burst_altitude > request.launch_altitude);
+    assert!(solution.time_to_burst.value > 0.0);
+}
+
+#[test]
+fn fill_hits_the_requested_ascent_rate() {
+    let atmosphere = Atmosphere::new(AtmosphereSource::StandardAtmosphere1976);
+    let target = 5.0;
+    let request = request(FillTarget::AscentRate(Velocity::new::<meter_per_second>(
+        target,
+    )));
+    let solution = fill(&request, &atmosphere).unwrap();
+
+    assert_close(
+        solution.ascent_rate.get::<meter_per_second>(),
+        target,
+        "ascent rate",
+    );
+    // At the reported ascent rate, drag on the filled balloon must balance
+    // the weight of the free lift.
+    let site = Vector::Y * request.launch_altitude.get::<meter>();
+    let air_density = atmosphere.density(site).get::<kilogram_per_cubic_meter>();
+    let radius = 0.5 * solution.fill_diameter.get::<meter>();
+    let area = PI * radius * radius;
+    let drag_force = 0.5 * air_density * target * target * request.drag_coefficient * area;
+    let free_lift = actual_free_lift(&request, &solution, &atmosphere);
+    assert_close(drag_force, free_lift * GRAVITY, "drag at the ascent rate");
+}
+
+#[test]
+fn fill_rejects_targets_it_cannot_reach() {
+    let atmosphere = Atmosphere::new(AtmosphereSource::StandardAtmosphere1976);
+    let no_free_lift = request(FillTarget::FreeLift(Mass::new::<kilogram>(0.0)));
+    assert_eq!(
+        fill(&no_free_lift, &atmosphere).unwrap_err(),
+        FillError::InvalidTarget
+    );
+    let mut air = request(FillTarget::FreeLift(Mass::new::<kilogram>(0.8)));
+    air.species = GasSpecies::air();
+    assert_eq!(fill(&air, &atmosphere).unwrap_err(), FillError::NoLift);
+}
@@ -0,0 +1,308 @@
+//! Recorded tracks must be read from real samples of each format, and a
+//! ghost must follow its track and measure how far a balloon strays from it.
+
+mod common;
+
+use std::{fs, time::Duration};
+
+use avian3d::{
+    math::{Scalar, Vector},
+    prelude::{Physics, PhysicsSet, Position},
+};
+use bevy::prelude::*;
+use buoy_core::prelude::*;
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+
+/// Meters in a foot.
+const FOOT: f64 = 0.3048;
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64, what: &str) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{what} is {actual}, expected {expected}"
+    );
+}
+
+#[test]
+fn aprs_uncompressed_position() {
+    let track = Track::from_aprs(
+        "2024-06-01 14:32:10 UTC: KD0ABC-11>APRS,WIDE2-1:!4003.50N/10500.25WO/A=012345\n",
+    )
+    .unwrap();
+    let point = track.start();
+    assert_eq!(
+        point.time,
+        Utc.with_ymd_and_hms(2024, 6, 1, 14, 32, 10).unwrap()
+    );
+    assert_close(point.latitude, 40.0 + 3.5 / 60.0, 1e-9, "latitude");
+    assert_close(point.longitude, -(105.0 + 0.25 / 60.0), 1e-9, "longitude");
+    assert_close(point.altitude as f64, 12345.0 * FOOT, 0.01, "altitude");
+}
+
+#[test]
+fn aprs_compressed_position() {
+    // The example of the APRS 1.01 specification, at 49°30' N 72°45' W, with
+    // an altitude in the comment.
+    let track =
+        Track::from_aprs("2024-06-01T14:32:10Z KD0ABC-11>APRS:=/5L!!<*e7>7P[/A=001000\n")
+            .unwrap();
+    let point = track.start();
+    assert_eq!(
+        point.time,
+        Utc.with_ymd_and_hms(2024, 6, 1, 14, 32, 10).unwrap()
+    );
+    assert_close(point.latitude, 49.5, 1e-6, "latitude");
+    assert_close(point.longitude, -72.75, 1e-6, "longitude");
+    assert_close(point.altitude as f64, 1000.0 * FOOT, 0.01, "altitude");
+}
+
+#[test]
+fn aprs_compressed_altitude() {
+    // `S]` is the altitude example of the specification: 10004 ft.
+    let track = Track::from_aprs("2024-06-01 14:32:10 UTC: KD0ABC-11>APRS:!/5L!!<*e7OS]S\n")
+        .unwrap();
+    assert_close(track.start().altitude as f64, 10004.0 * FOOT, 1.0, "altitude");
+}
+
+#[test]
+fn aprs_skips_packets_without_a_position_and_altitude() {
+    let log = "\
+2024-06-01 14:32:10 UTC: KD0ABC-11>APRS,WIDE2-1:>status text
+2024-06-01 14:32:40 UTC: KD0ABC-11>APRS,WIDE2-1:!4003.50N/10500.25WO
+2024-06-01 14:33:10 UTC: KD0ABC-11>APRS,WIDE2-1:!4003.60N/10500.30WO/A=012500
+";
+    let track = Track::from_aprs(log).unwrap();
+    assert_eq!(track.points().len(), 1);
+    assert!(matches!(Track::from_aprs(""), Err(TrackError::Empty)));
+}
+
+#[test]
+fn igc_b_records() {
+    let log = "\
+AXXXABC FLIGHT:1
+HFDTE160701
+B2359555206343N00006198WA0058700558
+B0000105206400N00006250WA0059000000
+";
+    let track = Track::from_igc(log).unwrap();
+    let [first, second] = track.points() else {
+        panic!("expected two fixes, got {:?}", track.points());
+    };
+    assert_eq!(
+        first.time,
+        Utc.with_ymd_and_hms(2001, 7, 16, 23, 59, 55).unwrap()
+    );
+    assert_close(first.latitude, 52.0 + 6.343 / 60.0, 1e-9, "latitude");
+    assert_close(first.longitude, -6.198 / 60.0, 1e-9, "longitude");
+    // The GNSS altitude wins over the pressure altitude.
+    assert_eq!(first.altitude, 558.0);
+    // Times that go backwards roll over to the next day, and a fix without a
+    // GNSS altitude falls back to the pressure altitude.
+    assert_eq!(
+        second.time,
+        Utc.with_ymd_and_hms(2001, 7, 17, 0, 0, 10).unwrap()
+    );
+    assert_eq!(second.altitude, 590.0);
+}
+
+#[test]
+fn igc_needs_a_date_before_its_fixes() {
+    assert!(matches!(
+        Track::from_igc("B1101355206343N00006198WA0058700558\n"),
+        Err(TrackError::MissingDate)
+    ));
+}
+
+#[test]
+fn csv_columns() {
+    let csv = "\
+Timestamp,Lat,Lon,Altitude (m)
+2024-06-01T14:32:10Z,40.0583,-105.0042,1655.5
+1717252360,40.0590,-105.0050,1710.0
+";
+    let track = Track::from_csv(csv.as_bytes()).unwrap();
+    let [first, second] = track.points() else {
+        panic!("expected two fixes, got {:?}", track.points());
+    };
+    assert_eq!(
+        first.time,
+        Utc.with_ymd_and_hms(2024, 6, 1, 14, 32, 10).unwrap()
+    );
+    assert_eq!(first.latitude, 40.0583);
+    assert_eq!(first.longitude, -105.0042);
+    assert_eq!(first.altitude, 1655.5);
+    // Seconds since the Unix epoch.
+    assert_eq!(
+        second.time,
+        Utc.with_ymd_and_hms(2024, 6, 1, 14, 32, 40).unwrap()
+    );
+    assert!(matches!(
+        Track::from_csv("time,lat,lon\n0,1,2\n".as_bytes()),
+        Err(TrackError::MissingColumn("altitude"))
+    ));
+}
+
+/// A fix at the equator and the prime meridian, some seconds after noon.
+fn fix(seconds: i64, longitude: f64, altitude: Scalar) -> TrackPoint {
+    TrackPoint {
+        time: noon() + TimeDelta::seconds(seconds),
+        latitude: 0.0,
+        longitude,
+        altitude,
+    }
+}
+
+fn noon() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn ghost_interpolates_between_fixes() {
+    // Up 100 m while moving 0.001° east, then back down without moving.
+    let track = Track::new(vec![
+        fix(0, 0.0, 100.0),
+        fix(10, 0.001, 200.0),
+        fix(30, 0.001, 100.0),
+    ])
+    .unwrap();
+    let ghost = Ghost::new(&track, &LaunchSite::default(), "Balloon");
+    let at = |seconds: f64| noon() + TimeDelta::milliseconds((seconds * 1000.0) as i64);
+    // 0.001° of longitude at the equator, on a sphere of 6371.0072 km.
+    let east = 111.195_05;
+
+    let start = ghost.position_at(at(0.0)).unwrap();
+    assert_eq!(start, Vector::new(0.0, 100.0, 0.0));
+    let position = ghost.position_at(at(2.5)).unwrap();
+    assert_close(position.x as f64, 0.25 * east, 1e-3, "east");
+    assert_close(position.y as f64, 125.0, 1e-3, "altitude");
+    assert_eq!(position.z, 0.0);
+    let position = ghost.position_at(at(10.0)).unwrap();
+    assert_close(position.x as f64, east, 1e-3, "east");
+    assert_eq!(position.y, 200.0);
+    let position = ghost.position_at(at(25.0)).unwrap();
+    assert_close(position.x as f64, east, 1e-3, "east");
+    assert_close(position.y as f64, 125.0, 1e-3, "altitude");
+
+    // Outside the track there is nothing to interpolate, so the clamped
+    // position holds at the nearest end.
+    assert_eq!(ghost.position_at(at(-1.0)), None);
+    assert_eq!(ghost.position_at(at(31.0)), None);
+    assert_eq!(ghost.clamped_position_at(at(-1.0)), start);
+    let end = ghost.clamped_position_at(at(31.0));
+    assert_close(end.x as f64, east, 1e-3, "east");
+    assert_eq!(end.y, 100.0);
+    assert_eq!(ghost.clamped_position_at(at(5.0)), ghost.position_at(at(5.0)).unwrap());
+}
+
+#[test]
+fn metrics_accumulate_altitude_error_and_drift() {
+    let mut metrics = ReplayMetrics::default();
+    assert_eq!(metrics.altitude_rms(), None);
+    assert_eq!(metrics.mean_horizontal_drift(), None);
+    assert_eq!(metrics.to_string(), "no overlap with the recorded track");
+
+    // 3 m too low and 5 m away, 4 m too low and 12 m away, then on the
+    // recorded altitude and 1 m away.
+    metrics.add(Vector::new(3.0, 10.0, 4.0), Vector::new(0.0, 13.0, 0.0));
+    metrics.add(Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 4.0, -12.0));
+    metrics.add(Vector::new(1.0, 50.0, 0.0), Vector::new(0.0, 50.0, 0.0));
+    assert_eq!(metrics.samples, 3);
+    // √((9 + 16 + 0) / 3)
+    assert_close(metrics.altitude_rms().unwrap() as f64, 2.886_751, 1e-5, "altitude RMS");
+    assert_close(metrics.mean_horizontal_drift().unwrap() as f64, 6.0, 1e-5, "mean drift");
+    assert_eq!(metrics.max_horizontal_drift, 12.0);
+    assert_eq!(metrics.last_horizontal_drift, 1.0);
+    assert_eq!(
+        metrics.to_string(),
+        "altitude RMS 3 m, horizontal drift mean 6 m, max 12 m, last 1 m"
+    );
+}
+
+/// Position (m) of the balloon after every physics step, and when.
+#[derive(Resource, Default)]
+struct BalloonPath(Vec<(Duration, Vector)>);
+
+fn record_balloon(
+    balloons: Query<&Position, With<Balloon>>,
+    time: Res<Time<Physics>>,
+    mut path: ResMut<BalloonPath>,
+) {
+    path.0.push((time.elapsed(), balloons.single().0));
+}
+
+#[test]
+fn ghost_is_compared_to_its_balloon_while_the_track_lasts() {
+    // A balloon that hung still 50 m above the launch site from ten to twenty
+    // seconds after the start of the scenario.
+    let directory = std::env::temp_dir().join(format!("buoy-replay-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let track = directory.join("track.csv");
+    fs::write(
+        &track,
+        "time,latitude,longitude,altitude\n\
+         2025-01-01T12:00:10Z,0,0,50\n\
+         2025-01-01T12:00:20Z,0,0,50\n",
+    )
+    .unwrap();
+    let mut scenario = common::scenario();
+    scenario.replays = vec![ReplayConfig {
+        track,
+        format: None,
+        balloon: "Balloon".to_string(),
+    }];
+    let mut sim = common::sim(scenario, Duration::from_secs(30));
+    sim.app_mut().init_resource::<BalloonPath>().add_systems(
+        FixedPostUpdate,
+        record_balloon
+            .after(PhysicsSet::StepSimulation)
+            .run_if(in_state(SimState::Running)),
+    );
+    sim.run();
+    fs::remove_dir_all(&directory).unwrap();
+
+    // Every step from the first fix to the last one, both included.
+    let window = Duration::from_secs(10)..=Duration::from_secs(20);
+    let compared = sim
+        .world()
+        .resource::<BalloonPath>()
+        .0
+        .iter()
+        .filter(|(elapsed, _)| window.contains(elapsed))
+        .map(|(_, position)| *position)
+        .collect::<Vec<_>>();
+    assert_eq!(compared.len(), 10 * 64 + 1);
+    let count = compared.len() as f64;
+    let altitude_rms = (compared
+        .iter()
+        .map(|position| ((position.y - 50.0) as f64).powi(2))
+        .sum::<f64>()
+        / count)
+        .sqrt();
+    let drifts = compared
+        .iter()
+        .map(|position| position.xz().length() as f64)
+        .collect::<Vec<_>>();
+
+    let world = sim.world_mut();
+    let metrics = world.query::<&Ghost>().single(world).metrics;
+    assert_eq!(metrics.samples, compared.len());
+    assert_close(metrics.altitude_rms().unwrap() as f64, altitude_rms, 1e-3, "altitude RMS");
+    assert_close(
+        metrics.mean_horizontal_drift().unwrap() as f64,
+        drifts.iter().sum::<f64>() / count,
+        1e-3,
+        "mean drift",
+    );
+    assert_close(
+        metrics.max_horizontal_drift as f64,
+        drifts.iter().copied().fold(0.0, f64::max),
+        1e-3,
+        "largest drift",
+    );
+    assert_close(
+        metrics.last_horizontal_drift as f64,
+        drifts[drifts.len() - 1],
+        1e-3,
+        "last drift",
+    );
+}
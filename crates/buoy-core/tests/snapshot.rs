@@ -0,0 +1,189 @@
+//! A simulation restored from a snapshot must pick up where the original one
+//! left off.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{
+    math::Vector,
+    prelude::{LinearVelocity, Position},
+};
+use bevy::prelude::{FixedUpdate, Query, Res, Time, With};
+use buoy_core::{
+    prelude::*,
+    quantity::{Length, MassRate},
+};
+use uom::si::{length::meter, mass_rate::kilogram_per_second};
+
+/// When the snapshot is taken, well after launch.
+const SNAPSHOT_TIME: Duration = Duration::from_secs(60);
+const RUN_DURATION: Duration = Duration::from_secs(120);
+
+fn scenario() -> Scenario {
+    let mut scenario = common::scenario();
+    scenario.wind = Wind::uniform(Vector::new(5.0, 0.0, -2.0));
+    scenario
+}
+
+fn sim(max_duration: Duration) -> HeadlessSim {
+    common::sim(scenario(), max_duration)
+}
+
+fn balloon_state(sim: &mut HeadlessSim) -> (Vector, Vector) {
+    let world = sim.world_mut();
+    let (position, velocity) = world
+        .query_filtered::<(&Position, &LinearVelocity), With<Balloon>>()
+        .single(world);
+    (position.0, velocity.0)
+}
+
+#[test]
+fn snapshot_round_trips_through_ron() {
+    let mut original = sim(SNAPSHOT_TIME);
+    original.run();
+    let text = original.snapshot().to_ron(original.world()).unwrap();
+
+    let mut restored = sim(RUN_DURATION);
+    let snapshot = Snapshot::from_ron(&text, restored.world()).unwrap();
+    restored.restore(&snapshot).unwrap();
+
+    assert_eq!(restored.elapsed(), original.elapsed());
+    assert_eq!(
+        restored.world().resource::<SimClock>(),
+        original.world().resource::<SimClock>()
+    );
+    assert_eq!(balloon_state(&mut restored), balloon_state(&mut original));
+    let gas = |sim: &mut HeadlessSim| {
+        let world = sim.world_mut();
+        world
+            .query_filtered::<&IdealGas, With<Balloon>>()
+            .single(world)
+            .clone()
+    };
+    assert_eq!(gas(&mut restored), gas(&mut original));
+}
+
+#[test]
+fn restored_run_continues_like_the_original() {
+    let mut uninterrupted = sim(RUN_DURATION);
+    uninterrupted.run();
+
+    let mut first_half = sim(SNAPSHOT_TIME);
+    first_half.run();
+    let snapshot = first_half.snapshot();
+    let mut second_half = sim(RUN_DURATION);
+    second_half.restore(&snapshot).unwrap();
+    second_half.run();
+
+    assert_eq!(second_half.elapsed(), uninterrupted.elapsed());
+    assert_eq!(
+        balloon_state(&mut second_half),
+        balloon_state(&mut uninterrupted)
+    );
+}
+
+#[test]
+fn restored_run_draws_the_same_gusts() {
+    let turbulent = |max_duration| {
+        let mut scenario = scenario();
+        scenario.turbulence = Some(Turbulence {
+            spectrum: Spectrum::VonKarman,
+            intensity: Intensity::Moderate,
+        });
+        common::sim(scenario, max_duration)
+    };
+    let mut uninterrupted = turbulent(RUN_DURATION);
+    uninterrupted.run();
+
+    let mut first_half = turbulent(SNAPSHOT_TIME);
+    first_half.run();
+    let text = first_half.snapshot().to_ron(first_half.world()).unwrap();
+    let mut second_half = turbulent(RUN_DURATION);
+    let snapshot = Snapshot::from_ron(&text, second_half.world()).unwrap();
+    second_half.restore(&snapshot).unwrap();
+    second_half.run();
+
+    assert_eq!(
+        balloon_state(&mut second_half),
+        balloon_state(&mut uninterrupted)
+    );
+    let gust = |sim: &mut HeadlessSim| {
+        let world = sim.world_mut();
+        *world
+            .query_filtered::<&Gust, With<Balloon>>()
+            .single(world)
+    };
+    assert_eq!(gust(&mut second_half), gust(&mut uninterrupted));
+}
+
+#[test]
+fn restored_controller_picks_up_where_it_left_off() {
+    // The integral of the PID loop carries over from the first half.
+    let controlled = |max_duration| {
+        let mut sim = common::sim(common::controllable_scenario(0.0005, 0.6, 1.3), max_duration);
+        let law = PidAltitudeHold::new(Length::new::<meter>(100.0), 0.01, 0.001, 1.0)
+            .with_ballast_rate(MassRate::new::<kilogram_per_second>(0.005));
+        common::control(&mut sim, law);
+        sim
+    };
+    let mut uninterrupted = controlled(RUN_DURATION);
+    uninterrupted.run();
+
+    let mut first_half = controlled(SNAPSHOT_TIME);
+    first_half.run();
+    let text = first_half.snapshot().to_ron(first_half.world()).unwrap();
+    let mut second_half = controlled(RUN_DURATION);
+    let snapshot = Snapshot::from_ron(&text, second_half.world()).unwrap();
+    second_half.restore(&snapshot).unwrap();
+    second_half.run();
+
+    assert_eq!(
+        balloon_state(&mut second_half),
+        balloon_state(&mut uninterrupted)
+    );
+    let world = second_half.world_mut();
+    let controllers = world
+        .query_filtered::<(), (With<Controller>, With<Balloon>)>()
+        .iter(world)
+        .count();
+    assert_eq!(controllers, 1);
+}
+
+#[test]
+fn restoring_a_faulted_sim_runs_it_again() {
+    fn poison(mut payloads: Query<&mut LinearVelocity, With<Payload>>, time: Res<Time>) {
+        if time.elapsed() >= Duration::from_secs(90) {
+            for mut velocity in payloads.iter_mut() {
+                velocity.0 = Vector::NAN;
+            }
+        }
+    }
+    let mut sim = sim(SNAPSHOT_TIME);
+    sim.app_mut().add_systems(FixedUpdate, poison);
+    sim.run();
+    let snapshot = sim.snapshot();
+    sim.max_duration = RUN_DURATION;
+    assert_eq!(sim.run().state, SimState::Faulted);
+
+    // The restored run goes on until just before the payload is poisoned
+    // again, with none of the faults of the first run.
+    sim.restore(&snapshot).unwrap();
+    sim.max_duration = Duration::from_secs(80);
+    let outcome = sim.run();
+    assert_eq!(outcome.state, SimState::Running);
+    assert_eq!(outcome.elapsed, Duration::from_secs(80));
+    assert_eq!(sim.faults().count(), 0);
+}
+
+#[test]
+fn default_path_is_safe_for_any_scenario_name() {
+    let mut scenario = scenario();
+    scenario.name = "Flight 1/2: test".to_string();
+    let path = Snapshot::default_path(&scenario);
+    assert_eq!(
+        path.file_name().unwrap().to_str().unwrap(),
+        "Flight_1_2__test.snapshot.ron"
+    );
+    assert_eq!(path.parent().unwrap(), scenario.outputs.directory);
+}
@@ -0,0 +1,112 @@
+//! Telemetry must be written to the output directory when a run stops or
+//! faults, with a column for every channel of every body.
+
+mod common;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use avian3d::{math::Vector, prelude::LinearVelocity};
+use bevy::prelude::*;
+use buoy_core::{prelude::*, telemetry::file_stem};
+
+/// An output directory of its own for a test, emptied before the run.
+fn output_directory(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("buoy-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    directory
+}
+
+/// The default scenario, writing every telemetry format to a directory.
+fn scenario(directory: PathBuf) -> Scenario {
+    let mut scenario = common::scenario();
+    scenario.name = "Telemetry test".to_string();
+    scenario.outputs.directory = directory;
+    scenario.outputs.telemetry.csv = true;
+    scenario.outputs.telemetry.parquet = cfg!(feature = "parquet");
+    scenario
+}
+
+/// Columns that the default channels give each body, without their units.
+fn expected_columns() -> Vec<String> {
+    let channels = TelemetryChannels::default();
+    let mut columns = vec!["time".to_string(), "utc".to_string()];
+    for body in ["Balloon", "Parachute", "Payload"] {
+        columns.extend(
+            channels
+                .0
+                .iter()
+                .map(|channel| format!("{}.{}", body, channel.name)),
+        );
+    }
+    columns
+}
+
+/// Check the files written for a scenario against the log it recorded.
+fn assert_written(scenario: &Scenario, log: &TelemetryLog) {
+    assert!(!log.is_empty());
+    let stem = scenario.outputs.directory.join(file_stem(&scenario.name));
+
+    let csv = fs::read_to_string(stem.with_extension("csv")).unwrap();
+    let mut lines = csv.lines();
+    let header = lines
+        .next()
+        .unwrap()
+        .split(',')
+        .map(|column| column.split(" (").next().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(header, expected_columns());
+    assert!(csv.lines().next().unwrap().contains("Balloon.position_y (m)"));
+    assert_eq!(lines.count(), log.len());
+
+    #[cfg(feature = "parquet")]
+    {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let file = fs::File::open(stem.with_extension("parquet")).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let schema = reader.schema().clone();
+        let names = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, expected_columns());
+        let position = schema.field_with_name("Balloon.position_y").unwrap();
+        assert_eq!(position.metadata().get("unit").unwrap(), "m");
+        let rows = reader
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum::<usize>();
+        assert_eq!(rows, log.len());
+    }
+}
+
+#[test]
+fn telemetry_is_written_when_the_run_stops() {
+    let mut scenario = scenario(output_directory("stop"));
+    scenario.stop_conditions = vec![StopCondition::Duration(2.0)];
+    let mut sim = common::sim(scenario.clone(), Duration::from_secs(10));
+    assert_eq!(sim.run().state, SimState::Stopped);
+
+    assert_written(&scenario, sim.world().resource::<TelemetryLog>());
+    fs::remove_dir_all(&scenario.outputs.directory).unwrap();
+}
+
+#[test]
+fn telemetry_is_written_when_the_run_faults() {
+    fn poison(mut payloads: Query<&mut LinearVelocity, With<Payload>>, time: Res<Time>) {
+        if time.elapsed() >= Duration::from_secs(2) {
+            for mut velocity in payloads.iter_mut() {
+                velocity.0 = Vector::NAN;
+            }
+        }
+    }
+    let scenario = scenario(output_directory("fault"));
+    let mut sim = common::sim(scenario.clone(), Duration::from_secs(10));
+    sim.app_mut().add_systems(FixedUpdate, poison);
+    assert_eq!(sim.run().state, SimState::Faulted);
+
+    assert_written(&scenario, sim.world().resource::<TelemetryLog>());
+    fs::remove_dir_all(&scenario.outputs.directory).unwrap();
+}
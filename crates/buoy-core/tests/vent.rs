@@ -0,0 +1,89 @@
+//! An open vent valve must let lift gas out at the rate of flow through its
+//! orifice, and let none out once it closes.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{math::Scalar, prelude::Position};
+use bevy::prelude::*;
+use buoy_core::{constants::PI, prelude::*, quantity::MassRate, scenario::VentValveScenario};
+use uom::si::mass_rate::kilogram_per_second;
+
+/// Standard gravity (m/s²), close enough to gravity a few meters up.
+const GRAVITY: Scalar = 9.80665;
+const ORIFICE_AREA: Scalar = 0.005;
+const DISCHARGE_COEFFICIENT: Scalar = 0.6;
+
+fn balloon(sim: &mut HeadlessSim) -> Entity {
+    let world = sim.world_mut();
+    world.query_filtered::<Entity, With<Balloon>>().single(world)
+}
+
+fn gas_mass(sim: &HeadlessSim, balloon: Entity) -> Scalar {
+    sim.world()
+        .get::<IdealGas>(balloon)
+        .unwrap()
+        .mass
+        .get::<kilogram>()
+}
+
+fn command(sim: &mut HeadlessSim, entity: Entity, action: VentAction) {
+    sim.world_mut().send_event(VentCommand { entity, action });
+}
+
+#[test]
+fn valve_vents_at_the_orifice_rate_until_it_closes() {
+    let mut scenario = common::scenario();
+    scenario.balloons[0].vent_valve = Some(VentValveScenario {
+        orifice_area: ORIFICE_AREA,
+        discharge_coefficient: DISCHARGE_COEFFICIENT,
+    });
+    let mut sim = common::sim(scenario, Duration::from_secs(1));
+    sim.run();
+    let balloon = balloon(&mut sim);
+    let closed = gas_mass(&sim, balloon);
+
+    command(&mut sim, balloon, VentAction::Open);
+    sim.max_duration = Duration::from_secs(3);
+    sim.run();
+    let opened = gas_mass(&sim, balloon);
+
+    // The gas is at the ambient pressure, so it is pushed out only by the
+    // buoyant head of the gas column under the valve: about 20 Pa, which
+    // drives some 8 g/s of helium through the orifice. The gas lags the
+    // ambient pressure by a step of the climb, which moves the rate by about
+    // a percent.
+    let world = sim.world();
+    let gas = world.get::<IdealGas>(balloon).unwrap();
+    let position = world.get::<Position>(balloon).unwrap().0;
+    let air_density = world
+        .resource::<Atmosphere>()
+        .density(position)
+        .get::<kilogram_per_cubic_meter>();
+    let gas_density = gas.density().get::<kilogram_per_cubic_meter>();
+    let height = 2.0 * (3.0 * gas.volume().get::<cubic_meter>() / (4.0 * PI)).cbrt();
+    let head = (air_density - gas_density) * GRAVITY * height;
+    let expected = DISCHARGE_COEFFICIENT * ORIFICE_AREA * (2.0 * gas_density * head).sqrt();
+
+    let valve = world.get::<VentValve>(balloon).unwrap();
+    let flow_rate = valve.flow_rate.get::<kilogram_per_second>();
+    assert!(
+        ((flow_rate - expected) / expected).abs() < 0.03,
+        "vents {flow_rate} kg/s, expected {expected} kg/s"
+    );
+    // The flow barely changes over two seconds.
+    let vented = closed - opened;
+    assert!(
+        ((vented - 2.0 * expected) / (2.0 * expected)).abs() < 0.05,
+        "vented {vented} kg in 2 s at {expected} kg/s"
+    );
+    assert!((valve.vented.get::<kilogram>() - vented).abs() < 1e-5);
+
+    command(&mut sim, balloon, VentAction::Close);
+    sim.max_duration = Duration::from_secs(5);
+    sim.run();
+    assert_eq!(gas_mass(&sim, balloon), opened);
+    let valve = sim.world().get::<VentValve>(balloon).unwrap();
+    assert_eq!(valve.flow_rate, MassRate::default());
+}
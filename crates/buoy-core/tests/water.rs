@@ -0,0 +1,89 @@
+//! A body that comes down on water must float at the draft where it displaces
+//! its own weight.
+
+mod common;
+
+use std::time::Duration;
+
+use avian3d::{
+    math::Scalar,
+    prelude::{ExternalForce, GravityScale, NoAutoMass, Position, RigidBody},
+};
+use bevy::{math::DVec3, prelude::*};
+use big_space::prelude::*;
+use buoy_core::{prelude::*, quantity::Area, water::SEA_WATER_DENSITY};
+use uom::si::area::square_meter;
+
+/// Mass (kg) and edge length (m) of the payload.
+const MASS: Scalar = 10.0;
+const SIZE: Scalar = 0.3;
+
+/// Release a payload on its own with its bottom half a meter above the water.
+fn release_payload(
+    mut commands: Commands,
+    root_grid: Query<(Entity, &Grid<Precision>), With<RootGrid>>,
+) {
+    let (root_grid_id, root_grid) = root_grid.single();
+    let (cell, translation) = root_grid.translation_to_grid(DVec3::new(0.0, 0.65, 0.0));
+    let shape = Shape::Cube { size: SIZE };
+    commands
+        .spawn((
+            Payload,
+            Drag::new(1.05, Area::new::<square_meter>(SIZE * SIZE)),
+            shape,
+            shape.collider(),
+            RigidBody::Dynamic,
+            avian3d::prelude::Mass(MASS),
+            NoAutoMass,
+            GravityScale(1.0),
+            ExternalForce::default().with_persistence(false),
+            cell,
+            Transform::from_translation(translation),
+        ))
+        .set_parent(root_grid_id);
+}
+
+#[test]
+fn payload_settles_at_its_equilibrium_draft() {
+    let mut scenario = common::scenario();
+    scenario.launch_site.altitude = 0.0;
+    scenario.balloons.clear();
+    scenario.water = vec![WaterBody::ocean()];
+    let mut sim = common::sim(scenario, Duration::from_secs(60));
+    sim.app_mut().add_systems(PostStartup, release_payload);
+    let outcome = sim.run();
+    assert_eq!(outcome.state, SimState::Running);
+
+    let world = sim.world_mut();
+    let (position, afloat) = world
+        .query_filtered::<(&Position, Has<Afloat>), With<Payload>>()
+        .single(world);
+    assert!(afloat);
+    // Afloat where the weight of the water it displaces equals its own.
+    let draft = MASS / (SEA_WATER_DENSITY * SIZE * SIZE);
+    let expected = 0.5 * SIZE - draft;
+    assert!(
+        (position.y - expected).abs() < 0.01,
+        "payload floats at {} m, expected {} m",
+        position.y,
+        expected
+    );
+}
+
+#[test]
+fn only_water_at_sea_level_may_cover_the_world() {
+    // A lake that forgets its extent would flood everything below 1600 m.
+    let lake: WaterBody = ron::from_str(r#"(name: "Lake", surface: 1600.0)"#).unwrap();
+    let mut scenario = Scenario::default();
+    scenario.water = vec![lake];
+    assert!(matches!(
+        scenario.validate(),
+        Err(ScenarioError::Invalid { field, .. }) if field == "water.extent"
+    ));
+
+    scenario.water = vec![
+        WaterBody::ocean(),
+        WaterBody::lake(1600.0, (200.0, -50.0), 150.0),
+    ];
+    assert!(scenario.validate().is_ok());
+}
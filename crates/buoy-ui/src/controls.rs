@@ -1,10 +1,10 @@
 use bevy::prelude::*;
 
-use buoy_core::prelude::SimState;
+use buoy_core::prelude::{Scenario, SimClock, SimState, Snapshot, SnapshotCommand, TimeWarp};
 
 pub fn plugin(app: &mut App) {
     app.init_resource::<KeyBindingsConfig>();
-    app.add_plugins(PausePlayPlugin);
+    app.add_plugins((PausePlayPlugin, TimeWarpPlugin, SnapshotPlugin));
 }
 
 #[allow(dead_code)]
@@ -12,6 +12,7 @@ pub fn plugin(app: &mut App) {
 pub struct KeyBindingsConfig {
     pub debug_controls: DebugControls,
     pub time_controls: TimeControls,
+    pub snapshot_controls: SnapshotControls,
 }
 
 #[derive(Reflect)]
@@ -26,6 +27,15 @@ pub struct DebugControls {
 #[derive(Reflect)]
 pub struct TimeControls {
     pub toggle_pause: KeyCode,
+    pub speed_up: KeyCode,
+    pub slow_down: KeyCode,
+    pub real_time: KeyCode,
+}
+
+#[derive(Reflect)]
+pub struct SnapshotControls {
+    pub save: KeyCode,
+    pub load: KeyCode,
 }
 
 // ============================ DEFAULT KEYBINDINGS ============================
@@ -46,6 +56,18 @@ impl Default for TimeControls {
     fn default() -> Self {
         Self {
             toggle_pause: KeyCode::Space,
+            speed_up: KeyCode::Equal,
+            slow_down: KeyCode::Minus,
+            real_time: KeyCode::Digit0,
+        }
+    }
+}
+
+impl Default for SnapshotControls {
+    fn default() -> Self {
+        Self {
+            save: KeyCode::F6,
+            load: KeyCode::F9,
         }
     }
 }
@@ -74,3 +96,94 @@ fn toggle_pause(
         }
     }
 }
+
+struct TimeWarpPlugin;
+
+impl Plugin for TimeWarpPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_clock_text);
+        app.add_systems(Update, (change_time_warp, update_clock_text).chain());
+    }
+}
+
+#[derive(Component)]
+struct ClockText;
+
+fn spawn_clock_text(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Right),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+        ClockText,
+    ));
+}
+
+fn change_time_warp(
+    mut time_warp: ResMut<TimeWarp>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindingsConfig>,
+) {
+    let controls = &key_bindings.time_controls;
+    if key_input.just_pressed(controls.speed_up) {
+        time_warp.speed_up();
+    } else if key_input.just_pressed(controls.slow_down) {
+        time_warp.slow_down();
+    } else if key_input.just_pressed(controls.real_time) {
+        time_warp.reset();
+    }
+}
+
+fn update_clock_text(
+    clock: Res<SimClock>,
+    time_warp: Res<TimeWarp>,
+    sim_state: Res<State<SimState>>,
+    mut text: Query<&mut Text, With<ClockText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let factor = time_warp.factor();
+    let mut warp_text = if factor >= 1.0 {
+        format!("{:.0}x", factor)
+    } else {
+        format!("1/{:.0}x", 1.0 / factor)
+    };
+    // Show the warp that is really reached when the computer can't keep up.
+    if *sim_state.get() == SimState::Running && time_warp.achieved() < 0.9 * factor {
+        warp_text = format!("{} (actual {:.1}x)", warp_text, time_warp.achieved());
+    }
+    text.0 = format!("{}\n{:?}  {}", clock.format(), sim_state.get(), warp_text);
+}
+
+struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, save_or_load_snapshot);
+    }
+}
+
+fn save_or_load_snapshot(
+    scenario: Res<Scenario>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindingsConfig>,
+    mut snapshot_commands: EventWriter<SnapshotCommand>,
+) {
+    let controls = &key_bindings.snapshot_controls;
+    let path = Snapshot::default_path(&scenario);
+    if key_input.just_pressed(controls.save) {
+        snapshot_commands.send(SnapshotCommand::Save(path));
+    } else if key_input.just_pressed(controls.load) {
+        snapshot_commands.send(SnapshotCommand::Load(path));
+    }
+}
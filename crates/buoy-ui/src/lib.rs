@@ -2,6 +2,7 @@ mod controls;
 mod colors;
 mod camera;
 mod lighting;
+mod replay;
 
 #[cfg(feature = "dev")]
 mod debug;
@@ -35,10 +36,11 @@ impl Plugin for AppPlugins {
                     .into(),
                     ..default()
                 }),
-            buoy_core::BuoyPlugin,
+            buoy_core::BuoyPlugin::default(),
             controls::plugin,
             camera::plugin,
             lighting::plugin,
+            replay::plugin,
         ));
 
         #[cfg(feature = "dev")]
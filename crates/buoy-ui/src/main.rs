@@ -2,9 +2,24 @@
 #![cfg_attr(not(feature = "dev"), windows_subsystem = "windows")]
 
 use bevy::prelude::*;
+use buoy_core::scenario::Scenario;
 
-fn main() {
-    App::new()
-        .add_plugins(buoy_ui::AppPlugins)
-        .run();
+fn main() -> AppExit {
+    let mut app = App::new();
+    app.add_plugins(buoy_ui::AppPlugins);
+
+    // Optionally load a scenario file given as the first argument.
+    if let Some(path) = std::env::args().nth(1) {
+        match Scenario::load(&path) {
+            Ok(scenario) => {
+                app.insert_resource(scenario);
+            }
+            Err(e) => {
+                error!("could not load scenario {}: {}", path, e);
+                return AppExit::error();
+            }
+        }
+    }
+
+    app.run()
 }
@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+
+use crate::colors::ColorPalette;
+use buoy_core::prelude::Ghost;
+
+/// Radius of the marker drawn where the recorded flight was.
+const GHOST_MARKER_RADIUS: f32 = 2.0;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(Update, draw_ghosts);
+}
+
+fn draw_ghosts(mut gizmos: Gizmos, ghosts: Query<&GlobalTransform, With<Ghost>>) {
+    for transform in ghosts.iter() {
+        gizmos.sphere(
+            Isometry3d::from_translation(transform.translation()),
+            GHOST_MARKER_RADIUS,
+            ColorPalette::BrightOrange.color(),
+        );
+    }
+}